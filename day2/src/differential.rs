@@ -0,0 +1,245 @@
+//! Differential tests between this day's interpreter and the shared Intcode VM.
+//!
+//! Both must stop at the same instruction for the same reason and leave memory in the same state,
+//! including when a program rewrites its own code into opcodes other than 1, 2 and 99.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use intcode::{ExecutionError, Intcode, Limits, Status};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::{execute_program_with_limits, ProgramError};
+
+/// Why a run stopped, in terms that both interpreters can report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stop {
+    Halted,
+
+    /// The instruction at `addr` isn't 1, 2 or 99.
+    UnknownOpcode { addr: usize },
+
+    /// The result of the instruction at `addr` doesn't fit in the VM's `i32` cells.
+    Overflow { addr: usize },
+}
+
+/// How the two interpreters disagree about a program.
+#[derive(Debug)]
+enum Divergence {
+    /// They stopped for different reasons or at different instructions.
+    Stop { day2: Stop, intcode: Stop },
+
+    /// The first cell where their final memory disagrees. A missing value means that one memory
+    /// is shorter than the other.
+    Memory { addr: usize, day2: Option<i32>, intcode: Option<i32> },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Divergence::Stop { day2, intcode } => {
+                write!(f, "runs stop differently: day2 = {:?}, intcode = {:?}", day2, intcode)
+            }
+            Divergence::Memory { addr, day2, intcode } => write!(
+                f,
+                "memory diverges at {}: day2 = {:?}, intcode = {:?}",
+                addr, day2, intcode
+            ),
+        }
+    }
+}
+
+/// Runs the program on day2's interpreter one instruction at a time.
+///
+/// Returns `None` if the program accesses memory past its end, since the VM reads such cells as
+/// zero instead of failing.
+fn run_day2(program: &[usize]) -> Option<(Stop, Vec<i32>)> {
+    let mut memory = program.to_vec();
+    let mut pc = 0;
+    let limits = Limits { max_instructions: Some(1), ..Limits::default() };
+
+    let stop = loop {
+        let before = memory.clone();
+        let addr = pc;
+
+        let result = execute_program_with_limits(&mut memory, &mut pc, &limits);
+
+        // The VM rejects results that don't fit in a cell before storing them.
+        if memory.iter().any(|&value| value > i32::MAX as usize) {
+            memory = before;
+            break Stop::Overflow { addr };
+        }
+
+        match result {
+            Ok(()) => break Stop::Halted,
+            Err(ProgramError::Limit(_)) => (),
+            Err(ProgramError::UnknownOpcode { addr, .. }) => break Stop::UnknownOpcode { addr },
+            Err(ProgramError::Overflow { pc }) => break Stop::Overflow { addr: pc },
+            Err(ProgramError::OutOfBounds { .. }) => return None,
+        }
+    };
+
+    Some((stop, memory.into_iter().map(|value| value as i32).collect()))
+}
+
+/// Runs the program on the VM, stopping before any instruction that day2 doesn't support.
+fn run_intcode(program: &[usize]) -> (Stop, Vec<i32>) {
+    let program = program.iter().map(ToString::to_string).collect::<Vec<_>>().join(",");
+
+    let mut computer = Intcode::load(&program, vec![]);
+    let limits = Limits { max_instructions: Some(10_000), ..Limits::default() };
+
+    let stop = loop {
+        let addr = computer.pc();
+
+        match computer.mem().get(addr) {
+            1 | 2 | 99 => (),
+            _ => break Stop::UnknownOpcode { addr },
+        }
+
+        match computer.step(&limits) {
+            Ok(Some(Status::Halted)) => break Stop::Halted,
+            Ok(_) => (),
+            Err(ExecutionError::Overflow { addr }) => break Stop::Overflow { addr },
+            Err(error) => panic!("{} for program {}", error, program),
+        }
+    };
+
+    (stop, computer.mem().to_vec())
+}
+
+/// Compares how both interpreters stop and their final memory, or returns `None` if the program
+/// can't be compared.
+fn compare(program: &[usize]) -> Option<Result<Stop, Divergence>> {
+    let (day2_stop, day2) = run_day2(program)?;
+    let (intcode_stop, intcode) = run_intcode(program);
+
+    if day2_stop != intcode_stop {
+        return Some(Err(Divergence::Stop { day2: day2_stop, intcode: intcode_stop }));
+    }
+
+    let len = day2.len().max(intcode.len());
+
+    for addr in 0..len {
+        let day2 = day2.get(addr).copied();
+        let intcode = intcode.get(addr).copied();
+
+        if day2 != intcode {
+            return Some(Err(Divergence::Memory { addr, day2, intcode }));
+        }
+    }
+
+    Some(Ok(day2_stop))
+}
+
+fn assert_agree(program: &[usize]) {
+    if let Some(Err(divergence)) = compare(program) {
+        panic!("{} for program {:?}", divergence, program);
+    }
+}
+
+/// Generates a program of additions and multiplications followed by a data section.
+///
+/// Sources and destinations may be any cell, so programs can rewrite their own code into
+/// something that isn't day2-compatible.
+fn random_program(rng: &mut impl Rng) -> Vec<usize> {
+    let instructions = rng.gen_range(0..8);
+    let data_start = instructions * 4 + 1;
+    let len = data_start + rng.gen_range(1..8);
+
+    let mut program = Vec::with_capacity(len);
+
+    for _ in 0..instructions {
+        program.push(if rng.gen() { 1 } else { 2 });
+        program.push(rng.gen_range(0..len));
+        program.push(rng.gen_range(0..len));
+        program.push(rng.gen_range(0..len));
+    }
+
+    program.push(99);
+
+    while program.len() < len {
+        program.push(rng.gen_range(0..100));
+    }
+
+    program
+}
+
+#[test]
+fn day2_test_cases() {
+    assert_agree(&[1, 0, 0, 0, 99]);
+    assert_agree(&[2, 3, 0, 3, 99]);
+    assert_agree(&[2, 4, 4, 5, 99, 0]);
+    assert_agree(&[1, 1, 1, 4, 99, 5, 6, 0, 99]);
+}
+
+#[test]
+fn puzzle_input() {
+    let program = include_str!("../../inputs/day2.txt")
+        .split(',')
+        .map(|opcode| opcode.trim().parse().unwrap())
+        .collect::<Vec<usize>>();
+
+    for &(noun, verb) in &[(12, 2), (0, 0), (99, 99)] {
+        let mut program = program.clone();
+        program[1] = noun;
+        program[2] = verb;
+
+        assert_agree(&program);
+    }
+}
+
+#[test]
+fn errors() {
+    // Overwrites the halt with an output instruction.
+    assert_eq!(compare(&[1, 0, 5, 4, 99, 3]).unwrap().unwrap(), Stop::UnknownOpcode { addr: 4 });
+
+    // Squares 1000 twice.
+    assert_eq!(compare(&[2, 9, 9, 9, 2, 9, 9, 9, 99, 1000]).unwrap().unwrap(), Stop::Overflow { addr: 4 });
+
+    // Reads past the end, which the VM allows.
+    assert!(compare(&[1, 0, 9, 0, 99]).is_none());
+}
+
+#[test]
+fn random_programs() {
+    let mut rng = StdRng::seed_from_u64(2019);
+    let mut stops = HashMap::new();
+
+    for _ in 0..1000 {
+        let program = random_program(&mut rng);
+
+        match compare(&program) {
+            Some(Ok(stop)) => *stops.entry(stop_kind(stop)).or_insert(0) += 1,
+            Some(Err(divergence)) => panic!("{} for program {:?}", divergence, program),
+            None => (),
+        }
+    }
+
+    // Make sure that the out of bounds filter isn't hiding most of the programs, and that some
+    // of them rewrite their own code into something that day2 rejects.
+    assert!(stops["halted"] > 300, "only {:?} programs were compared", stops);
+    assert!(stops["unknown opcode"] > 100, "only {:?} programs were compared", stops);
+}
+
+/// The kind of a stop, ignoring where it happened.
+fn stop_kind(stop: Stop) -> &'static str {
+    match stop {
+        Stop::Halted => "halted",
+        Stop::UnknownOpcode { .. } => "unknown opcode",
+        Stop::Overflow { .. } => "overflow",
+    }
+}
+
+#[test]
+fn reports_divergence() {
+    let divergence = Divergence::Memory { addr: 3, day2: Some(6), intcode: None };
+    assert_eq!(divergence.to_string(), "memory diverges at 3: day2 = Some(6), intcode = None");
+
+    let divergence = Divergence::Stop { day2: Stop::Halted, intcode: Stop::Overflow { addr: 4 } };
+    assert_eq!(
+        divergence.to_string(),
+        "runs stop differently: day2 = Halted, intcode = Overflow { addr: 4 }"
+    );
+}
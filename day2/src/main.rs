@@ -1,64 +1,184 @@
+use std::convert::TryFrom;
+use std::env;
 use std::error::Error;
-use std::fs;
+use std::fmt;
 
-fn execute_program(memory: &mut [usize]) {
-    let mut pc = 0;
+use intcode::patch::PatchFile;
+use intcode::{program, LimitExceeded, Limits};
 
-    loop {
-        match memory[pc] {
-            1 => {
-                memory[memory[pc + 3]] = memory[memory[pc + 1]] + memory[memory[pc + 2]];
+#[cfg(test)]
+mod differential;
+
+/// Why a program stopped before halting.
+#[derive(Debug, PartialEq, Eq)]
+enum ProgramError {
+    Limit(LimitExceeded),
+
+    /// The instruction at `addr` isn't 1, 2 or 99.
+    UnknownOpcode { opcode: usize, addr: usize },
+
+    /// The instruction at `pc` accessed `addr`, which is past the end of memory.
+    OutOfBounds { pc: usize, addr: usize },
+
+    /// The instruction at `pc` computed a result that doesn't fit in a cell.
+    Overflow { pc: usize },
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProgramError::Limit(limit) => write!(f, "{}", limit),
+            ProgramError::UnknownOpcode { opcode, addr } => {
+                write!(f, "unknown opcode {} at address {}", opcode, addr)
             }
-            2 => {
-                memory[memory[pc + 3]] = memory[memory[pc + 1]] * memory[memory[pc + 2]];
+            ProgramError::OutOfBounds { pc, addr } => write!(
+                f,
+                "the instruction at {} accessed address {}, past the end of memory",
+                pc, addr
+            ),
+            ProgramError::Overflow { pc } => write!(f, "the instruction at {} overflowed", pc),
+        }
+    }
+}
+
+impl Error for ProgramError {}
+
+impl From<LimitExceeded> for ProgramError {
+    fn from(limit: LimitExceeded) -> Self {
+        ProgramError::Limit(limit)
+    }
+}
+
+/// Reads a cell for the instruction at `pc`.
+fn read(memory: &[usize], pc: usize, addr: usize) -> Result<usize, ProgramError> {
+    memory.get(addr).copied().ok_or(ProgramError::OutOfBounds { pc, addr })
+}
+
+/// Runs the program from `pc` until it halts, a limit is hit, or it does something invalid.
+///
+/// `pc` is left pointing at the next instruction, so a run that hit a limit can be resumed. Memory
+/// never grows and there is no output, so only the instruction and deadline limits apply.
+fn execute_program_with_limits(
+    memory: &mut [usize],
+    pc: &mut usize,
+    limits: &Limits,
+) -> Result<(), ProgramError> {
+    let mut steps = 0;
+
+    loop {
+        limits.check(steps)?;
+
+        let cell = |addr| read(memory, *pc, addr);
+
+        match cell(*pc)? {
+            opcode @ 1 | opcode @ 2 => {
+                let a = cell(cell(*pc + 1)?)?;
+                let b = cell(cell(*pc + 2)?)?;
+                let dst = cell(*pc + 3)?;
+
+                let result = if opcode == 1 { a.checked_add(b) } else { a.checked_mul(b) };
+                let result = result.ok_or(ProgramError::Overflow { pc: *pc })?;
+
+                match memory.get_mut(dst) {
+                    Some(cell) => *cell = result,
+                    None => return Err(ProgramError::OutOfBounds { pc: *pc, addr: dst }),
+                }
             }
             99 => break,
-            unknown_opcode => unreachable!("unknown opcode: {}", unknown_opcode)
+            opcode => return Err(ProgramError::UnknownOpcode { opcode, addr: *pc }),
         }
 
-        pc += 4;
+        *pc += 4;
+        steps += 1;
     }
+
+    Ok(())
+}
+
+/// Converts a program to the memory of this day's interpreter.
+fn to_memory(program: &[i32]) -> Result<Vec<usize>, Box<dyn Error>> {
+    Ok(program.iter().map(|&cell| usize::try_from(cell)).collect::<Result<_, _>>()?)
 }
 
+/// Usage: `day2 [PATCH_FILE [SET]]`
+///
+/// The patch set is applied for part 1, and part 2 searches for the values of the cells that it
+/// patches.
 fn main() -> Result<(), Box<dyn Error>> {
-    let input = fs::read_to_string("inputs/day2.txt")?;
+    let mut args = env::args().skip(1);
+    let patch_path = args.next().unwrap_or_else(|| String::from("inputs/day2.patch"));
+    let set = args.next().unwrap_or_else(|| String::from("1202 program alarm"));
 
-    let program = input
-        .split(',')
-        .map(|opcode| opcode.trim().parse())
-        .collect::<Result<Vec<_>, _>>()?;
+    let program = program::read_file("inputs/day2.txt")?;
+    let patch_file = PatchFile::read_file(&patch_path)?;
 
-    let mut memory = program.clone();
+    let mut patched = program.clone();
+    for warning in patch_file.apply(&mut patched, Some(&set))? {
+        eprintln!("warning: {}", warning);
+    }
 
-    memory[1] = 12;
-    memory[2] = 2;
+    let mut memory = to_memory(&patched)?;
 
-    execute_program(&mut memory);
+    execute_program_with_limits(&mut memory, &mut 0, &Limits::default())?;
 
     println!("part 1: {}", memory[0]);
 
+    let (noun_addr, verb_addr) = match patch_file.patches(Some(&set))?[..] {
+        [noun, verb] => (noun.addr, verb.addr),
+        _ => return Err(format!("patch set `{}` must patch exactly the noun and the verb", set).into()),
+    };
+
+    let memory = to_memory(&program)?;
+
+    // Don't let a single runaway noun and verb pair stall the search.
+    let limits = Limits { max_instructions: Some(10_000), ..Limits::default() };
+
+    let mut found = None;
+    let mut failed = 0;
+
     'outer: for noun in 0..100 {
         for verb in 0..100 {
-            let mut memory = program.clone();
+            let mut memory = memory.clone();
 
-            memory[1] = noun;
-            memory[2] = verb;
+            memory[noun_addr] = noun;
+            memory[verb_addr] = verb;
 
-            execute_program(&mut memory);
+            if let Err(e) = execute_program_with_limits(&mut memory, &mut 0, &limits) {
+                if failed == 0 {
+                    eprintln!("warning: noun {} and verb {}: {}", noun, verb, e);
+                }
+                failed += 1;
+                continue;
+            }
 
             if memory[0] == 19_690_720 {
-                println!("part 2: {}", 100 * noun + verb);
+                found = Some(100 * noun + verb);
                 break 'outer;
             }
         }
     }
 
+    if failed > 0 {
+        eprintln!("warning: {} noun and verb pairs didn't halt", failed);
+    }
+
+    match found {
+        Some(answer) => println!("part 2: {}", answer),
+        None => println!("part 2: no noun and verb pair produces 19690720"),
+    }
+
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::execute_program;
+    use intcode::{LimitExceeded, Limits};
+
+    use super::{execute_program_with_limits, ProgramError};
+
+    fn execute_program(memory: &mut [usize]) {
+        execute_program_with_limits(memory, &mut 0, &Limits::default()).unwrap();
+    }
 
     #[test]
     fn test_case_1() {
@@ -87,4 +207,74 @@ mod tests {
         execute_program(&mut memory);
         assert_eq!(&memory, &[30, 1, 1, 4, 2, 5, 6, 0, 99]);
     }
+
+    #[test]
+    fn instruction_limit() {
+        let mut memory = [1, 0, 0, 0, 2, 0, 0, 0, 99];
+        let mut pc = 0;
+        let limits = Limits { max_instructions: Some(1), ..Limits::default() };
+
+        assert_eq!(
+            execute_program_with_limits(&mut memory, &mut pc, &limits),
+            Err(ProgramError::Limit(LimitExceeded::Instructions))
+        );
+        assert_eq!(pc, 4);
+        assert_eq!(&memory, &[2, 0, 0, 0, 2, 0, 0, 0, 99]);
+
+        assert_eq!(execute_program_with_limits(&mut memory, &mut pc, &Limits::default()), Ok(()));
+        assert_eq!(&memory, &[4, 0, 0, 0, 2, 0, 0, 0, 99]);
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let mut memory = [1, 0, 0, 0, 3, 0, 0, 0, 99];
+
+        assert_eq!(
+            execute_program_with_limits(&mut memory, &mut 0, &Limits::default()),
+            Err(ProgramError::UnknownOpcode { opcode: 3, addr: 4 })
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        let limits = Limits::default();
+
+        let mut memory = [1, 5, 6, 0, 99, usize::MAX, 1];
+        assert_eq!(
+            execute_program_with_limits(&mut memory, &mut 0, &limits),
+            Err(ProgramError::Overflow { pc: 0 })
+        );
+
+        let mut memory = [1, 0, 0, 0, 2, 9, 9, 0, 99, usize::MAX];
+        assert_eq!(
+            execute_program_with_limits(&mut memory, &mut 0, &limits),
+            Err(ProgramError::Overflow { pc: 4 })
+        );
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let limits = Limits::default();
+
+        // Reads past the end.
+        let mut memory = [1, 0, 9, 0, 99];
+        assert_eq!(
+            execute_program_with_limits(&mut memory, &mut 0, &limits),
+            Err(ProgramError::OutOfBounds { pc: 0, addr: 9 })
+        );
+
+        // Writes past the end.
+        let mut memory = [2, 0, 0, 5, 99];
+        assert_eq!(
+            execute_program_with_limits(&mut memory, &mut 0, &limits),
+            Err(ProgramError::OutOfBounds { pc: 0, addr: 5 })
+        );
+
+        // Runs off the end without halting.
+        let mut memory = [1, 0, 0, 0];
+        assert_eq!(
+            execute_program_with_limits(&mut memory, &mut 0, &limits),
+            Err(ProgramError::OutOfBounds { pc: 4, addr: 4 })
+        );
+    }
 }
@@ -1,12 +1,13 @@
 use std::collections::HashSet;
-use std::convert::TryFrom;
 use std::error::Error;
-use std::fmt::{self, Display};
+use std::fmt;
 use std::fs;
 use std::iter::FromIterator;
 use std::ops::Deref;
 use std::str::FromStr;
 
+use grid::{Direction, Location};
+
 struct Wire(Vec<Segment>);
 
 impl Deref for Wire {
@@ -29,57 +30,6 @@ impl FromStr for Wire {
     }
 }
 
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-impl fmt::Display for Direction {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Direction::Up => "U",
-                Direction::Down => "D",
-                Direction::Left => "L",
-                Direction::Right => "R",
-            }
-        )
-    }
-}
-
-#[derive(Debug)]
-struct DirectionParseError;
-
-impl Display for DirectionParseError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "could not parse direction")?;
-
-        Ok(())
-    }
-}
-
-impl Error for DirectionParseError {}
-
-impl FromStr for Direction {
-    type Err = DirectionParseError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let d = match s {
-            "U" => Direction::Up,
-            "D" => Direction::Down,
-            "L" => Direction::Left,
-            "R" => Direction::Right,
-            _ => return Err(DirectionParseError),
-        };
-
-        Ok(d)
-    }
-}
-
 struct Segment {
     direction: Direction,
     magnitude: u64,
@@ -105,24 +55,6 @@ impl fmt::Debug for Segment {
 
 }
 
-#[derive(Default, Copy, Clone, Hash, PartialEq, Eq)]
-struct Location {
-    x: i64,
-    y: i64,
-}
-
-impl Location {
-    fn manhattan_distance(&self, other: Location) -> u64 {
-        u64::try_from((self.x - other.x).abs()).unwrap() + u64::try_from((self.y - other.y).abs()).unwrap()
-    }
-}
-
-impl fmt::Debug for Location {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", (self.x, self.y))
-    }
-}
-
 fn trace_wire(wire: &Wire) -> Vec<Location> {
     let mut path = Vec::with_capacity(wire.len());
 
@@ -132,13 +64,7 @@ fn trace_wire(wire: &Wire) -> Vec<Location> {
 
     for vector in wire.iter() {
         for _ in 1..=vector.magnitude {
-            match vector.direction {
-                Direction::Up => location.y += 1,
-                Direction::Down => location.y -= 1,
-                Direction::Left => location.x -= 1,
-                Direction::Right => location.x += 1,
-            }
-
+            location = location.step(vector.direction);
             path.push(location);
         }
     }
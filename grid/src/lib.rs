@@ -0,0 +1,134 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn turn_left(self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    pub fn turn_right(self) -> Self {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Direction::Up => "U",
+                Direction::Down => "D",
+                Direction::Left => "L",
+                Direction::Right => "R",
+            }
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct DirectionParseError;
+
+impl Display for DirectionParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "could not parse direction")?;
+
+        Ok(())
+    }
+}
+
+impl Error for DirectionParseError {}
+
+impl FromStr for Direction {
+    type Err = DirectionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let d = match s {
+            "U" => Direction::Up,
+            "D" => Direction::Down,
+            "L" => Direction::Left,
+            "R" => Direction::Right,
+            _ => return Err(DirectionParseError),
+        };
+
+        Ok(d)
+    }
+}
+
+/// A point on the grid. `y` grows upwards.
+#[derive(Default, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct Location {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Location {
+    pub fn manhattan_distance(&self, other: Location) -> u64 {
+        u64::try_from((self.x - other.x).abs()).unwrap() + u64::try_from((self.y - other.y).abs()).unwrap()
+    }
+
+    /// The adjacent location in the given direction.
+    pub fn step(self, direction: Direction) -> Location {
+        let Location { x, y } = self;
+
+        match direction {
+            Direction::Up => Location { x, y: y + 1 },
+            Direction::Down => Location { x, y: y - 1 },
+            Direction::Left => Location { x: x - 1, y },
+            Direction::Right => Location { x: x + 1, y },
+        }
+    }
+}
+
+impl fmt::Debug for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", (self.x, self.y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Direction, Location};
+
+    #[test]
+    fn turns() {
+        assert_eq!(Direction::Up.turn_left(), Direction::Left);
+        assert_eq!(Direction::Up.turn_right(), Direction::Right);
+        assert_eq!(Direction::Left.turn_left().turn_left(), Direction::Right);
+
+        let mut direction = Direction::Down;
+        for _ in 0..4 {
+            direction = direction.turn_right();
+        }
+        assert_eq!(direction, Direction::Down);
+    }
+
+    #[test]
+    fn step() {
+        let origin = Location::default();
+
+        assert_eq!(origin.step(Direction::Up), Location { x: 0, y: 1 });
+        assert_eq!(origin.step(Direction::Left).step(Direction::Down), Location { x: -1, y: -1 });
+        assert_eq!(origin.step(Direction::Right).manhattan_distance(origin), 1);
+    }
+}
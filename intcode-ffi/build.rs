@@ -0,0 +1,21 @@
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).unwrap();
+
+    let bindings = cbindgen::generate_with_config(&crate_dir, config)
+        .expect("could not generate the header");
+    bindings.write_to_file(out_dir.join("intcode.h"));
+
+    // The committed header is only replaced on request, so building never touches the sources.
+    if env::var_os("INTCODE_UPDATE_HEADER").is_some() {
+        bindings.write_to_file(crate_dir.join("include/intcode.h"));
+    }
+
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=INTCODE_UPDATE_HEADER");
+}
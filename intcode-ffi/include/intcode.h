@@ -0,0 +1,145 @@
+#ifndef INTCODE_H
+#define INTCODE_H
+
+/* Generated by the intcode-ffi build script. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The call succeeded.
+#define INTCODE_OK 0
+
+// The program halted.
+#define INTCODE_HALTED 1
+
+// The program needs input, and the input queue is empty.
+#define INTCODE_NEEDS_INPUT 2
+
+// The program output a value, which can be taken with `intcode_pop_output`.
+#define INTCODE_OUTPUT 3
+
+// A required pointer was null.
+#define INTCODE_ERROR_NULL -1
+
+// The program ran an instruction with an unknown opcode.
+#define INTCODE_ERROR_UNKNOWN_OPCODE -2
+
+// The run reached its instruction or memory limit.
+#define INTCODE_ERROR_LIMIT -3
+
+// There is no output to take.
+#define INTCODE_ERROR_NO_OUTPUT -4
+
+// An address is past the end of memory.
+#define INTCODE_ERROR_OUT_OF_RANGE -5
+
+// The machine failed in an unexpected way.
+#define INTCODE_ERROR_INTERNAL -6
+
+// The result of an instruction, or an address or relative base it computes, didn't fit in a
+// cell.
+#define INTCODE_ERROR_OVERFLOW -7
+
+// An instruction has a parameter mode that doesn't exist, or writes to an immediate parameter.
+#define INTCODE_ERROR_INVALID_MODE -8
+
+// An instruction accessed a negative address.
+#define INTCODE_ERROR_NEGATIVE_ADDRESS -9
+
+// A jump has a negative target.
+#define INTCODE_ERROR_INVALID_JUMP_TARGET -10
+
+// The number of cells that memory may grow to, unless changed with `intcode_set_max_memory`.
+#define INTCODE_DEFAULT_MAX_MEMORY (1 << 20)
+
+// An Intcode machine. Created by `intcode_new` and destroyed by `intcode_free`.
+typedef struct IntcodeMachine IntcodeMachine;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates a machine that runs a copy of `program`, which is `len` cells long.
+//
+// Returns null if `program` is null and `len` isn't zero.
+//
+// # Safety
+//
+// `program` must point to `len` readable cells.
+struct IntcodeMachine *intcode_new(const int32_t *program, size_t len);
+
+// Destroys a machine. Does nothing if `machine` is null.
+//
+// # Safety
+//
+// `machine` must have been returned by `intcode_new`, and not freed already.
+void intcode_free(struct IntcodeMachine *machine);
+
+// Adds a value to the end of the input queue.
+//
+// # Safety
+//
+// `machine` must be null or a live machine.
+int32_t intcode_push_input(struct IntcodeMachine *machine, int32_t value);
+
+// Runs the program until it halts, needs input, or outputs a value, and returns which of
+// `INTCODE_HALTED`, `INTCODE_NEEDS_INPUT` and `INTCODE_OUTPUT` happened.
+//
+// At most `max_instructions` instructions are executed, unless it's zero. If the limit is
+// reached first, `INTCODE_ERROR_LIMIT` is returned and the run can be resumed by calling this
+// again.
+//
+// # Safety
+//
+// `machine` must be null or a live machine.
+int32_t intcode_run(struct IntcodeMachine *machine, uint64_t max_instructions);
+
+// Sets the number of cells that memory may grow to, or removes the limit if `max_memory` is
+// zero. Memory that a program already has is kept.
+//
+// # Safety
+//
+// `machine` must be null or a live machine.
+int32_t intcode_set_max_memory(struct IntcodeMachine *machine, size_t max_memory);
+
+// Takes the oldest output value that hasn't been taken yet, storing it in `value`.
+//
+// Returns `INTCODE_ERROR_NO_OUTPUT` if there is none.
+//
+// # Safety
+//
+// `machine` must be null or a live machine, and `value` must be null or writable.
+int32_t intcode_pop_output(struct IntcodeMachine *machine, int32_t *value);
+
+// The number of cells of memory. Memory grows when the program writes past its end.
+//
+// # Safety
+//
+// `machine` must be null or a live machine.
+size_t intcode_memory_len(const struct IntcodeMachine *machine);
+
+// Reads the cell at `addr` into `value`.
+//
+// # Safety
+//
+// `machine` must be null or a live machine, and `value` must be null or writable.
+int32_t intcode_read(const struct IntcodeMachine *machine, size_t addr, int32_t *value);
+
+// Writes `value` to the cell at `addr`, which must already exist.
+//
+// # Safety
+//
+// `machine` must be null or a live machine.
+int32_t intcode_write(struct IntcodeMachine *machine, size_t addr, int32_t value);
+
+// A static, nul-terminated description of a status or error code.
+const char *intcode_error_message(int32_t code);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* INTCODE_H */
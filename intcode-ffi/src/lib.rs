@@ -0,0 +1,408 @@
+//! A C API for the Intcode machine.
+//!
+//! The header is generated by the build script, and a copy is committed as `include/intcode.h`.
+//! Build with `INTCODE_UPDATE_HEADER` set to update the copy.
+//!
+//! Every function returns an `INTCODE_*` code (or a null pointer, for [`intcode_new`]) instead of
+//! panicking, since unwinding into C is undefined behavior. Negative codes are errors, and
+//! [`intcode_error_message`] describes them.
+//!
+//! Machines start with a memory limit of [`INTCODE_DEFAULT_MAX_MEMORY`] cells, so a program that
+//! writes to a huge address fails with `INTCODE_ERROR_LIMIT` instead of exhausting memory.
+
+use std::collections::VecDeque;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use intcode::{ExecutionError, Intcode, Limits, Status};
+
+/// The call succeeded.
+pub const INTCODE_OK: i32 = 0;
+
+/// The program halted.
+pub const INTCODE_HALTED: i32 = 1;
+
+/// The program needs input, and the input queue is empty.
+pub const INTCODE_NEEDS_INPUT: i32 = 2;
+
+/// The program output a value, which can be taken with `intcode_pop_output`.
+pub const INTCODE_OUTPUT: i32 = 3;
+
+/// A required pointer was null.
+pub const INTCODE_ERROR_NULL: i32 = -1;
+
+/// The program ran an instruction with an unknown opcode.
+pub const INTCODE_ERROR_UNKNOWN_OPCODE: i32 = -2;
+
+/// The run reached its instruction or memory limit.
+pub const INTCODE_ERROR_LIMIT: i32 = -3;
+
+/// There is no output to take.
+pub const INTCODE_ERROR_NO_OUTPUT: i32 = -4;
+
+/// An address is past the end of memory.
+pub const INTCODE_ERROR_OUT_OF_RANGE: i32 = -5;
+
+/// The machine failed in an unexpected way.
+pub const INTCODE_ERROR_INTERNAL: i32 = -6;
+
+/// The result of an instruction, or an address or relative base it computes, didn't fit in a
+/// cell.
+pub const INTCODE_ERROR_OVERFLOW: i32 = -7;
+
+/// An instruction has a parameter mode that doesn't exist, or writes to an immediate parameter.
+pub const INTCODE_ERROR_INVALID_MODE: i32 = -8;
+
+/// An instruction accessed a negative address.
+pub const INTCODE_ERROR_NEGATIVE_ADDRESS: i32 = -9;
+
+/// A jump has a negative target.
+pub const INTCODE_ERROR_INVALID_JUMP_TARGET: i32 = -10;
+
+/// The number of cells that memory may grow to, unless changed with `intcode_set_max_memory`.
+pub const INTCODE_DEFAULT_MAX_MEMORY: usize = 1 << 20;
+
+/// An Intcode machine. Created by `intcode_new` and destroyed by `intcode_free`.
+pub struct IntcodeMachine {
+    computer: Intcode,
+
+    /// Output that hasn't been taken yet.
+    output: VecDeque<i32>,
+
+    /// The limits of every run. The instruction limit is passed to each run instead.
+    limits: Limits,
+}
+
+/// Runs `f`, turning a panic into `INTCODE_ERROR_INTERNAL`. The machine reports the errors of
+/// programs itself, so this is a backstop for bugs, since unwinding into C is undefined behavior.
+fn guard(f: impl FnOnce() -> i32) -> i32 {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(INTCODE_ERROR_INTERNAL)
+}
+
+fn error_code(error: ExecutionError) -> i32 {
+    match error {
+        ExecutionError::UnknownOpcode { .. } => INTCODE_ERROR_UNKNOWN_OPCODE,
+        ExecutionError::Limit(_) => INTCODE_ERROR_LIMIT,
+        ExecutionError::Overflow { .. } => INTCODE_ERROR_OVERFLOW,
+        ExecutionError::InvalidMode { .. } => INTCODE_ERROR_INVALID_MODE,
+        ExecutionError::NegativeAddress { .. } => INTCODE_ERROR_NEGATIVE_ADDRESS,
+        ExecutionError::InvalidJumpTarget { .. } => INTCODE_ERROR_INVALID_JUMP_TARGET,
+
+        // A machine that runs out of input reports it as a status instead, and cycle detection
+        // and memory protection can't be enabled through this API.
+        ExecutionError::MissingInput { .. } | ExecutionError::Cycle(_) | ExecutionError::Protection(_) => {
+            INTCODE_ERROR_INTERNAL
+        }
+    }
+}
+
+/// Creates a machine that runs a copy of `program`, which is `len` cells long.
+///
+/// Returns null if `program` is null and `len` isn't zero.
+///
+/// # Safety
+///
+/// `program` must point to `len` readable cells.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_new(program: *const i32, len: usize) -> *mut IntcodeMachine {
+    if program.is_null() && len != 0 {
+        return ptr::null_mut();
+    }
+
+    let program = if len == 0 { &[][..] } else { slice::from_raw_parts(program, len) };
+
+    panic::catch_unwind(|| {
+        let computer = Intcode::new(program.to_vec(), vec![]);
+        let limits = Limits { max_memory: Some(INTCODE_DEFAULT_MAX_MEMORY), ..Limits::default() };
+        Box::into_raw(Box::new(IntcodeMachine { computer, output: VecDeque::new(), limits }))
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Destroys a machine. Does nothing if `machine` is null.
+///
+/// # Safety
+///
+/// `machine` must have been returned by `intcode_new`, and not freed already.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_free(machine: *mut IntcodeMachine) {
+    if !machine.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(machine))));
+    }
+}
+
+/// Adds a value to the end of the input queue.
+///
+/// # Safety
+///
+/// `machine` must be null or a live machine.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_push_input(machine: *mut IntcodeMachine, value: i32) -> i32 {
+    let machine = match machine.as_mut() {
+        Some(machine) => machine,
+        None => return INTCODE_ERROR_NULL,
+    };
+
+    guard(|| {
+        machine.computer.push_input(value);
+        INTCODE_OK
+    })
+}
+
+/// Runs the program until it halts, needs input, or outputs a value, and returns which of
+/// `INTCODE_HALTED`, `INTCODE_NEEDS_INPUT` and `INTCODE_OUTPUT` happened.
+///
+/// At most `max_instructions` instructions are executed, unless it's zero. If the limit is
+/// reached first, `INTCODE_ERROR_LIMIT` is returned and the run can be resumed by calling this
+/// again.
+///
+/// # Safety
+///
+/// `machine` must be null or a live machine.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_run(machine: *mut IntcodeMachine, max_instructions: u64) -> i32 {
+    let machine = match machine.as_mut() {
+        Some(machine) => machine,
+        None => return INTCODE_ERROR_NULL,
+    };
+
+    guard(|| {
+        let mut steps = 0;
+
+        loop {
+            if max_instructions != 0 && steps == max_instructions {
+                return INTCODE_ERROR_LIMIT;
+            }
+
+            match machine.computer.step(&machine.limits) {
+                Ok(None) => steps += 1,
+                Ok(Some(Status::Halted)) => return INTCODE_HALTED,
+                Ok(Some(Status::NeedsInput)) => return INTCODE_NEEDS_INPUT,
+                Err(e) => return error_code(e),
+            }
+
+            let output = machine.computer.take_output();
+            if !output.is_empty() {
+                machine.output.extend(output);
+                return INTCODE_OUTPUT;
+            }
+        }
+    })
+}
+
+/// Sets the number of cells that memory may grow to, or removes the limit if `max_memory` is
+/// zero. Memory that a program already has is kept.
+///
+/// # Safety
+///
+/// `machine` must be null or a live machine.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_set_max_memory(machine: *mut IntcodeMachine, max_memory: usize) -> i32 {
+    let machine = match machine.as_mut() {
+        Some(machine) => machine,
+        None => return INTCODE_ERROR_NULL,
+    };
+
+    machine.limits.max_memory = Some(max_memory).filter(|&max| max != 0);
+    INTCODE_OK
+}
+
+/// Takes the oldest output value that hasn't been taken yet, storing it in `value`.
+///
+/// Returns `INTCODE_ERROR_NO_OUTPUT` if there is none.
+///
+/// # Safety
+///
+/// `machine` must be null or a live machine, and `value` must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_pop_output(machine: *mut IntcodeMachine, value: *mut i32) -> i32 {
+    let (machine, value) = match (machine.as_mut(), value.as_mut()) {
+        (Some(machine), Some(value)) => (machine, value),
+        _ => return INTCODE_ERROR_NULL,
+    };
+
+    match machine.output.pop_front() {
+        Some(output) => {
+            *value = output;
+            INTCODE_OK
+        }
+        None => INTCODE_ERROR_NO_OUTPUT,
+    }
+}
+
+/// The number of cells of memory. Memory grows when the program writes past its end.
+///
+/// # Safety
+///
+/// `machine` must be null or a live machine.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_memory_len(machine: *const IntcodeMachine) -> usize {
+    machine.as_ref().map_or(0, |machine| machine.computer.mem().len())
+}
+
+/// Reads the cell at `addr` into `value`.
+///
+/// # Safety
+///
+/// `machine` must be null or a live machine, and `value` must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_read(machine: *const IntcodeMachine, addr: usize, value: *mut i32) -> i32 {
+    let (machine, value) = match (machine.as_ref(), value.as_mut()) {
+        (Some(machine), Some(value)) => (machine, value),
+        _ => return INTCODE_ERROR_NULL,
+    };
+
+    guard(|| {
+        let mem = machine.computer.mem();
+        if addr >= mem.len() {
+            return INTCODE_ERROR_OUT_OF_RANGE;
+        }
+
+        *value = mem[addr];
+        INTCODE_OK
+    })
+}
+
+/// Writes `value` to the cell at `addr`, which must already exist.
+///
+/// # Safety
+///
+/// `machine` must be null or a live machine.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_write(machine: *mut IntcodeMachine, addr: usize, value: i32) -> i32 {
+    let machine = match machine.as_mut() {
+        Some(machine) => machine,
+        None => return INTCODE_ERROR_NULL,
+    };
+
+    guard(|| {
+        let mem = machine.computer.mem_mut();
+        if addr >= mem.len() {
+            return INTCODE_ERROR_OUT_OF_RANGE;
+        }
+
+        mem[addr] = value;
+        INTCODE_OK
+    })
+}
+
+/// A static, nul-terminated description of a status or error code.
+#[no_mangle]
+pub extern "C" fn intcode_error_message(code: i32) -> *const c_char {
+    let message: &'static [u8] = match code {
+        INTCODE_OK => b"ok\0",
+        INTCODE_HALTED => b"halted\0",
+        INTCODE_NEEDS_INPUT => b"needs input\0",
+        INTCODE_OUTPUT => b"output\0",
+        INTCODE_ERROR_NULL => b"null pointer\0",
+        INTCODE_ERROR_UNKNOWN_OPCODE => b"unknown opcode\0",
+        INTCODE_ERROR_LIMIT => b"limit exceeded\0",
+        INTCODE_ERROR_NO_OUTPUT => b"no output\0",
+        INTCODE_ERROR_OUT_OF_RANGE => b"address out of range\0",
+        INTCODE_ERROR_INTERNAL => b"internal error\0",
+        INTCODE_ERROR_OVERFLOW => b"overflow\0",
+        INTCODE_ERROR_INVALID_MODE => b"invalid parameter mode\0",
+        INTCODE_ERROR_NEGATIVE_ADDRESS => b"negative address\0",
+        INTCODE_ERROR_INVALID_JUMP_TARGET => b"invalid jump target\0",
+        _ => b"unknown code\0",
+    };
+
+    message.as_ptr().cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+
+    use super::*;
+
+    #[test]
+    fn runs_until_each_output() {
+        let program = [104, 1, 104, 2, 99];
+
+        unsafe {
+            let machine = intcode_new(program.as_ptr(), program.len());
+            let mut value = 0;
+
+            assert_eq!(intcode_run(machine, 0), INTCODE_OUTPUT);
+            assert_eq!(intcode_run(machine, 0), INTCODE_OUTPUT);
+            assert_eq!(intcode_run(machine, 0), INTCODE_HALTED);
+
+            assert_eq!(intcode_pop_output(machine, &mut value), INTCODE_OK);
+            assert_eq!(value, 1);
+            assert_eq!(intcode_pop_output(machine, &mut value), INTCODE_OK);
+            assert_eq!(value, 2);
+            assert_eq!(intcode_pop_output(machine, &mut value), INTCODE_ERROR_NO_OUTPUT);
+
+            intcode_free(machine);
+        }
+    }
+
+    #[test]
+    fn resumes_after_limit() {
+        let program = [1101, 1, 1, 7, 104, 5, 99, 0];
+
+        unsafe {
+            let machine = intcode_new(program.as_ptr(), program.len());
+
+            assert_eq!(intcode_run(machine, 1), INTCODE_ERROR_LIMIT);
+            assert_eq!(intcode_run(machine, 1), INTCODE_OUTPUT);
+
+            let mut value = 0;
+            assert_eq!(intcode_read(machine, 7, &mut value), INTCODE_OK);
+            assert_eq!(value, 2);
+
+            intcode_free(machine);
+        }
+    }
+
+    #[test]
+    fn reports_invalid_instructions() {
+        let programs: &[(&[i32], i32)] = &[
+            // Parameter mode 3 doesn't exist.
+            (&[304, 0, 99], INTCODE_ERROR_INVALID_MODE),
+            (&[4, -1, 99], INTCODE_ERROR_NEGATIVE_ADDRESS),
+            (&[1105, 1, -1], INTCODE_ERROR_INVALID_JUMP_TARGET),
+            (&[109, 2147483647, 109, 1, 99], INTCODE_ERROR_OVERFLOW),
+        ];
+
+        for &(program, code) in programs {
+            unsafe {
+                let machine = intcode_new(program.as_ptr(), program.len());
+                assert_eq!(intcode_run(machine, 0), code, "{:?}", program);
+                intcode_free(machine);
+            }
+        }
+
+        let message = unsafe { CStr::from_ptr(intcode_error_message(INTCODE_ERROR_INVALID_MODE)) };
+        assert_eq!(message.to_str(), Ok("invalid parameter mode"));
+    }
+
+    #[test]
+    fn limits_memory() {
+        // Writes to the last cell that the default limit allows, and then past it.
+        let last = INTCODE_DEFAULT_MAX_MEMORY as i32 - 1;
+        let program = [1101, 0, 0, last, 1101, 0, 0, last + 1, 99];
+
+        unsafe {
+            let machine = intcode_new(program.as_ptr(), program.len());
+            assert_eq!(intcode_run(machine, 0), INTCODE_ERROR_LIMIT);
+            assert_eq!(intcode_memory_len(machine), INTCODE_DEFAULT_MAX_MEMORY);
+
+            assert_eq!(intcode_set_max_memory(machine, 0), INTCODE_OK);
+            assert_eq!(intcode_run(machine, 0), INTCODE_HALTED);
+            assert_eq!(intcode_memory_len(machine), INTCODE_DEFAULT_MAX_MEMORY + 1);
+            intcode_free(machine);
+
+            let machine = intcode_new(program.as_ptr(), program.len());
+            assert_eq!(intcode_set_max_memory(machine, 100), INTCODE_OK);
+            assert_eq!(intcode_run(machine, 0), INTCODE_ERROR_LIMIT);
+            assert_eq!(intcode_memory_len(machine), program.len());
+            intcode_free(machine);
+
+            assert_eq!(intcode_set_max_memory(ptr::null_mut(), 0), INTCODE_ERROR_NULL);
+        }
+    }
+}
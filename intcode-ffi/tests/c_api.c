@@ -0,0 +1,139 @@
+/* Exercises the C API. Exits with a nonzero status on the first failed check. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "intcode.h"
+
+#define CHECK(cond)                                                          \
+    do {                                                                     \
+        if (!(cond)) {                                                       \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, \
+                    #cond);                                                  \
+            return 1;                                                        \
+        }                                                                    \
+    } while (0)
+
+/* Outputs 1 if its input is 8, or 0 otherwise. From day 5. */
+static const int32_t EQUAL_TO_8[] = {3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8};
+
+/* Echoes input until it reads 0. */
+static const int32_t ECHO[] = {3, 11, 1006, 11, 10, 4, 11, 1105, 1, 0, 99, 0};
+
+static int equal_to_8(void) {
+    IntcodeMachine *machine = intcode_new(EQUAL_TO_8, sizeof(EQUAL_TO_8) / sizeof(EQUAL_TO_8[0]));
+    CHECK(machine != NULL);
+    CHECK(intcode_memory_len(machine) == 11);
+
+    int32_t value = -1;
+    CHECK(intcode_pop_output(machine, &value) == INTCODE_ERROR_NO_OUTPUT);
+
+    CHECK(intcode_run(machine, 0) == INTCODE_NEEDS_INPUT);
+    CHECK(intcode_push_input(machine, 8) == INTCODE_OK);
+    CHECK(intcode_run(machine, 0) == INTCODE_OUTPUT);
+    CHECK(intcode_pop_output(machine, &value) == INTCODE_OK);
+    CHECK(value == 1);
+    CHECK(intcode_run(machine, 0) == INTCODE_HALTED);
+
+    CHECK(intcode_read(machine, 9, &value) == INTCODE_OK);
+    CHECK(value == 1);
+    CHECK(intcode_read(machine, 11, &value) == INTCODE_ERROR_OUT_OF_RANGE);
+
+    intcode_free(machine);
+    return 0;
+}
+
+static int echo(void) {
+    IntcodeMachine *machine = intcode_new(ECHO, sizeof(ECHO) / sizeof(ECHO[0]));
+    CHECK(machine != NULL);
+
+    for (int32_t i = 3; i >= 0; i--) {
+        CHECK(intcode_push_input(machine, i) == INTCODE_OK);
+    }
+
+    int32_t expected = 3;
+    int status;
+    while ((status = intcode_run(machine, 0)) == INTCODE_OUTPUT) {
+        int32_t value;
+        CHECK(intcode_pop_output(machine, &value) == INTCODE_OK);
+        CHECK(value == expected--);
+    }
+
+    CHECK(status == INTCODE_HALTED);
+    CHECK(expected == 0);
+
+    intcode_free(machine);
+    return 0;
+}
+
+static int memory_and_limits(void) {
+    IntcodeMachine *machine = intcode_new(ECHO, sizeof(ECHO) / sizeof(ECHO[0]));
+    CHECK(machine != NULL);
+
+    /* Replace the first instruction with a jump to itself. */
+    CHECK(intcode_write(machine, 0, 1105) == INTCODE_OK);
+    CHECK(intcode_write(machine, 1, 1) == INTCODE_OK);
+    CHECK(intcode_write(machine, 2, 0) == INTCODE_OK);
+    CHECK(intcode_write(machine, 12, 0) == INTCODE_ERROR_OUT_OF_RANGE);
+    CHECK(intcode_run(machine, 1000) == INTCODE_ERROR_LIMIT);
+
+    intcode_free(machine);
+    return 0;
+}
+
+static int errors(void) {
+    static const int32_t UNKNOWN[] = {42};
+    static const int32_t BAD_MODE[] = {301, 0, 0, 0, 99};
+    static const int32_t OVERFLOW[] = {1101, 2147483647, 1, 0, 99};
+    static const int32_t NEGATIVE[] = {4, -1, 99};
+    static const int32_t BAD_JUMP[] = {1105, 1, -1};
+    static const int32_t HUGE_WRITE[] = {1101, 0, 0, 2147483647, 99};
+
+    IntcodeMachine *machine = intcode_new(UNKNOWN, 1);
+    CHECK(intcode_run(machine, 0) == INTCODE_ERROR_UNKNOWN_OPCODE);
+    intcode_free(machine);
+
+    machine = intcode_new(BAD_MODE, 5);
+    CHECK(intcode_run(machine, 0) == INTCODE_ERROR_INVALID_MODE);
+    intcode_free(machine);
+
+    machine = intcode_new(OVERFLOW, 5);
+    CHECK(intcode_run(machine, 0) == INTCODE_ERROR_OVERFLOW);
+    intcode_free(machine);
+
+    machine = intcode_new(NEGATIVE, 3);
+    CHECK(intcode_run(machine, 0) == INTCODE_ERROR_NEGATIVE_ADDRESS);
+    intcode_free(machine);
+
+    machine = intcode_new(BAD_JUMP, 3);
+    CHECK(intcode_run(machine, 0) == INTCODE_ERROR_INVALID_JUMP_TARGET);
+    intcode_free(machine);
+
+    /* The default memory limit stops the write instead of allocating 8 GiB. */
+    machine = intcode_new(HUGE_WRITE, 5);
+    CHECK(intcode_run(machine, 0) == INTCODE_ERROR_LIMIT);
+    CHECK(intcode_memory_len(machine) == 5);
+    CHECK(intcode_set_max_memory(machine, 10) == INTCODE_OK);
+    CHECK(intcode_run(machine, 0) == INTCODE_ERROR_LIMIT);
+    intcode_free(machine);
+
+    int32_t value;
+    CHECK(intcode_new(NULL, 3) == NULL);
+    CHECK(intcode_run(NULL, 0) == INTCODE_ERROR_NULL);
+    CHECK(intcode_pop_output(NULL, &value) == INTCODE_ERROR_NULL);
+    CHECK(intcode_memory_len(NULL) == 0);
+    intcode_free(NULL);
+
+    CHECK(strcmp(intcode_error_message(INTCODE_ERROR_UNKNOWN_OPCODE), "unknown opcode") == 0);
+    CHECK(strcmp(intcode_error_message(100), "unknown code") == 0);
+
+    return 0;
+}
+
+int main(void) {
+    int failed = equal_to_8() || echo() || memory_and_limits() || errors();
+    if (!failed) {
+        printf("ok\n");
+    }
+    return failed;
+}
@@ -0,0 +1,42 @@
+//! Compiles `c_api.c` against the static library and the generated header, and runs it.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn c_api() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let library = test_support::build_library("intcode-ffi", "libintcode_ffi.a");
+    let exe = Path::new(env!("CARGO_TARGET_TMPDIR")).join("c_api_test");
+
+    let status = Command::new(env::var("CC").unwrap_or_else(|_| String::from("cc")))
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg(manifest_dir.join("tests/c_api.c"))
+        .arg(library)
+        .args(["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&exe)
+        .status()
+        .expect("could not run the C compiler");
+    assert!(status.success());
+
+    let output = Command::new(&exe).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(output.stdout, b"ok\n");
+}
+
+#[test]
+fn header_is_up_to_date() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let generated = fs::read_to_string(Path::new(env!("OUT_DIR")).join("intcode.h")).unwrap();
+    let committed = fs::read_to_string(manifest_dir.join("include/intcode.h")).unwrap();
+
+    assert!(
+        generated == committed,
+        "include/intcode.h is out of date; build with INTCODE_UPDATE_HEADER=1 to update it"
+    );
+}
@@ -0,0 +1,230 @@
+//! Python bindings for the Intcode machine.
+//!
+//! The extension module is named `intcode`, and exposes a `Machine` class:
+//!
+//! ```python
+//! import intcode
+//!
+//! machine = intcode.Machine.load("inputs/day5.txt")
+//! machine.feed([5])
+//! assert machine.run() == "halted"
+//! print(machine.outputs)
+//! ```
+//!
+//! A machine supports the buffer protocol, so `memoryview(machine)` (or `machine.memory`) is a
+//! view of its memory as native `int` cells. Memory isn't contiguous inside the machine, so each
+//! view is a read-only snapshot of memory when it was created, and doesn't change as the program
+//! runs. Cells are changed with `Machine.write` instead.
+
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr;
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyBufferError, PyException, PyIndexError, PyOSError, PyValueError};
+use pyo3::ffi;
+use pyo3::prelude::*;
+use pyo3::types::PyMemoryView;
+
+use intcode::{program, ExecutionError, Intcode, Limits, Status};
+
+create_exception!(intcode, IntcodeError, PyException, "An error that stopped a running program.");
+create_exception!(intcode, UnknownOpcodeError, IntcodeError, "An instruction has an unknown opcode.");
+create_exception!(intcode, LimitExceededError, IntcodeError, "A run reached its instruction limit.");
+create_exception!(
+    intcode,
+    InvalidInstructionError,
+    IntcodeError,
+    "An instruction couldn't be decoded, such as one with an invalid parameter mode."
+);
+
+fn execution_error(error: ExecutionError) -> PyErr {
+    match error {
+        ExecutionError::UnknownOpcode { .. } => UnknownOpcodeError::new_err(error.to_string()),
+        ExecutionError::Limit(_) => LimitExceededError::new_err(error.to_string()),
+        ExecutionError::InvalidMode { .. }
+        | ExecutionError::NegativeAddress { .. }
+        | ExecutionError::InvalidJumpTarget { .. } => InvalidInstructionError::new_err(error.to_string()),
+        ExecutionError::MissingInput { .. }
+        | ExecutionError::Overflow { .. }
+        | ExecutionError::Cycle(_)
+        | ExecutionError::Protection(_) => IntcodeError::new_err(error.to_string()),
+    }
+}
+
+fn status(status: Status) -> &'static str {
+    match status {
+        Status::Halted => "halted",
+        Status::NeedsInput => "needs_input",
+    }
+}
+
+/// An Intcode machine.
+#[pyclass(unsendable, module = "intcode")]
+struct Machine {
+    computer: Intcode,
+}
+
+/// A copy of memory owned by an exported buffer, with the shape and strides of the buffer.
+struct Snapshot {
+    cells: Vec<i32>,
+    layout: [ffi::Py_ssize_t; 2],
+}
+
+impl Machine {
+    fn new(program: Vec<i32>) -> Self {
+        Machine { computer: Intcode::new(program, vec![]) }
+    }
+
+    /// Executes one instruction.
+    fn step_once(&mut self) -> PyResult<Option<Status>> {
+        self.computer.step(&Limits::default()).map_err(execution_error)
+    }
+
+    fn run_until(&mut self, max_instructions: Option<u64>) -> PyResult<&'static str> {
+        let mut steps = 0;
+
+        loop {
+            if max_instructions.is_some_and(|max| steps >= max) {
+                return Err(execution_error(ExecutionError::Limit(intcode::LimitExceeded::Instructions)));
+            }
+
+            if let Some(status) = self.step_once()? {
+                return Ok(self::status(status));
+            }
+
+            steps += 1;
+        }
+    }
+}
+
+#[pymethods]
+impl Machine {
+    /// Creates a machine that runs `program`, a list of cells.
+    #[new]
+    fn py_new(program: Vec<i32>) -> Self {
+        Machine::new(program)
+    }
+
+    /// Creates a machine that runs the program in a file of comma-separated cells.
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Self> {
+        let program = program::read_file(path).map_err(|e| PyOSError::new_err(e.to_string()))?;
+        Ok(Machine::new(program))
+    }
+
+    /// Creates a machine that runs a program of comma-separated cells.
+    #[staticmethod]
+    fn parse(text: &str) -> PyResult<Self> {
+        let program = program::parse(text).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(Machine::new(program))
+    }
+
+    /// Adds values to the end of the input queue.
+    fn feed(&mut self, values: Vec<i32>) {
+        for value in values {
+            self.computer.push_input(value);
+        }
+    }
+
+    /// Runs the program until it halts or needs more input than has been fed to it, returning
+    /// `"halted"` or `"needs_input"`.
+    ///
+    /// Raises `LimitExceededError` if `max_instructions` are executed first. The run can be
+    /// resumed by calling this again.
+    #[pyo3(signature = (max_instructions=None))]
+    fn run(&mut self, max_instructions: Option<u64>) -> PyResult<&'static str> {
+        self.run_until(max_instructions)
+    }
+
+    /// Executes the instruction at the program counter. Returns `None` if it was executed, or
+    /// the status that prevented it like `run` does.
+    fn step(&mut self) -> PyResult<Option<&'static str>> {
+        Ok(self.step_once()?.map(status))
+    }
+
+    /// Writes `value` to the cell at `addr`, which must already exist.
+    fn write(&mut self, addr: usize, value: i32) -> PyResult<()> {
+        let mem = self.computer.mem_mut();
+        if addr >= mem.len() {
+            return Err(PyIndexError::new_err(format!("address {} is past the end of memory", addr)));
+        }
+
+        mem[addr] = value;
+        Ok(())
+    }
+
+    /// All of the values output so far.
+    #[getter]
+    fn outputs(&self) -> Vec<i32> {
+        self.computer.output().to_vec()
+    }
+
+    #[getter]
+    fn pc(&self) -> usize {
+        self.computer.pc()
+    }
+
+    #[getter]
+    fn relative_base(&self) -> i32 {
+        self.computer.relative_base()
+    }
+
+    /// A read-only snapshot of memory.
+    #[getter]
+    fn memory<'py>(slf: &Bound<'py, Self>) -> PyResult<Bound<'py, PyMemoryView>> {
+        PyMemoryView::from(slf.as_any())
+    }
+
+    fn __len__(&self) -> usize {
+        self.computer.mem().len()
+    }
+
+    unsafe fn __getbuffer__(slf: Bound<'_, Self>, view: *mut ffi::Py_buffer, flags: c_int) -> PyResult<()> {
+        if view.is_null() {
+            return Err(PyBufferError::new_err("view is null"));
+        }
+
+        if flags & ffi::PyBUF_WRITABLE == ffi::PyBUF_WRITABLE {
+            return Err(PyBufferError::new_err("memory is a read-only snapshot"));
+        }
+
+        let cells = slf.borrow().computer.mem().to_vec();
+        let layout = [cells.len() as ffi::Py_ssize_t, std::mem::size_of::<i32>() as ffi::Py_ssize_t];
+        let snapshot = Box::into_raw(Box::new(Snapshot { cells, layout }));
+
+        let view = &mut *view;
+        view.buf = (*snapshot).cells.as_mut_ptr() as *mut c_void;
+        view.len = ((*snapshot).cells.len() * std::mem::size_of::<i32>()) as ffi::Py_ssize_t;
+        view.itemsize = std::mem::size_of::<i32>() as ffi::Py_ssize_t;
+        view.readonly = 1;
+        view.ndim = 1;
+        view.format = if flags & ffi::PyBUF_FORMAT == ffi::PyBUF_FORMAT {
+            b"i\0".as_ptr() as *mut c_char
+        } else {
+            ptr::null_mut()
+        };
+        view.shape = if flags & ffi::PyBUF_ND == ffi::PyBUF_ND { &mut (*snapshot).layout[0] } else { ptr::null_mut() };
+        view.strides =
+            if flags & ffi::PyBUF_STRIDES == ffi::PyBUF_STRIDES { &mut (*snapshot).layout[1] } else { ptr::null_mut() };
+        view.suboffsets = ptr::null_mut();
+        view.internal = snapshot as *mut c_void;
+        view.obj = slf.into_any().into_ptr();
+
+        Ok(())
+    }
+
+    unsafe fn __releasebuffer__(&self, view: *mut ffi::Py_buffer) {
+        drop(Box::from_raw((*view).internal as *mut Snapshot));
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "intcode")]
+fn intcode_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Machine>()?;
+    m.add("IntcodeError", m.py().get_type::<IntcodeError>())?;
+    m.add("UnknownOpcodeError", m.py().get_type::<UnknownOpcodeError>())?;
+    m.add("LimitExceededError", m.py().get_type::<LimitExceededError>())?;
+    m.add("InvalidInstructionError", m.py().get_type::<InvalidInstructionError>())?;
+    Ok(())
+}
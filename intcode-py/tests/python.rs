@@ -0,0 +1,27 @@
+//! Runs `test_machine.py` against the extension module.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn python() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    // Python only imports the module from a file named after it.
+    let module_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("python");
+    fs::create_dir_all(&module_dir).unwrap();
+    let library = test_support::build_library("intcode-py", "libintcode_py.so");
+    fs::copy(library, module_dir.join("intcode.so")).unwrap();
+
+    let output = Command::new(env::var("PYTHON").unwrap_or_else(|_| String::from("python3")))
+        .args(["-m", "unittest", "-v", "test_machine"])
+        .current_dir(manifest_dir.join("tests"))
+        .env("PYTHONPATH", &module_dir)
+        .env("PYTHONDONTWRITEBYTECODE", "1")
+        .output()
+        .expect("could not run Python");
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
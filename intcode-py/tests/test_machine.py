@@ -0,0 +1,97 @@
+"""Tests for the `intcode` extension module, run by `python.rs`."""
+
+import os
+import unittest
+
+import intcode
+
+INPUTS = os.path.join(os.path.dirname(__file__), "..", "..", "inputs")
+
+# Outputs 1 if its input is 8, or 0 otherwise. From day 5.
+EQUAL_TO_8 = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8]
+
+
+class MachineTest(unittest.TestCase):
+    def test_day5(self):
+        machine = intcode.Machine.load(os.path.join(INPUTS, "day5.txt"))
+        machine.feed([1])
+        self.assertEqual(machine.run(), "halted")
+        self.assertEqual(machine.outputs[-1], 15426686)
+        self.assertTrue(all(code == 0 for code in machine.outputs[:-1]))
+
+        machine = intcode.Machine.load(os.path.join(INPUTS, "day5.txt"))
+        machine.feed([5])
+        machine.run()
+        self.assertEqual(machine.outputs, [11430197])
+
+    def test_needs_input(self):
+        machine = intcode.Machine(EQUAL_TO_8)
+        self.assertEqual(machine.run(), "needs_input")
+        self.assertEqual(machine.step(), "needs_input")
+
+        machine.feed([8])
+        self.assertIsNone(machine.step())
+        self.assertEqual(machine.pc, 2)
+        self.assertEqual(machine.run(), "halted")
+        self.assertEqual(machine.outputs, [1])
+
+    def test_parse(self):
+        machine = intcode.Machine.parse("104,42,99\n")
+        machine.run()
+        self.assertEqual(machine.outputs, [42])
+
+        with self.assertRaises(ValueError):
+            intcode.Machine.parse("1,x")
+
+        with self.assertRaises(OSError):
+            intcode.Machine.load(os.path.join(INPUTS, "missing.txt"))
+
+    def test_memory(self):
+        machine = intcode.Machine(EQUAL_TO_8)
+        memory = machine.memory
+        self.assertEqual(memory.format, "i")
+        self.assertTrue(memory.readonly)
+        self.assertEqual(len(memory), len(machine))
+        self.assertEqual(memory.tolist(), EQUAL_TO_8)
+
+        with self.assertRaises(TypeError):
+            memory[10] = 7
+
+        # Compare with 7 instead of 8.
+        machine.write(10, 7)
+        machine.feed([7])
+        machine.run()
+        self.assertEqual(machine.outputs, [1])
+
+        # The snapshot doesn't change, but a new one does.
+        self.assertEqual(memory[9], -1)
+        self.assertEqual(machine.memory[9], 1)
+        memory.release()
+
+        with memoryview(machine) as view:
+            self.assertEqual(view[10], 7)
+
+        with self.assertRaises(IndexError):
+            machine.write(len(machine), 0)
+
+    def test_errors(self):
+        machine = intcode.Machine([104, 1, 42])
+        with self.assertRaises(intcode.UnknownOpcodeError) as error:
+            machine.run()
+        self.assertEqual(str(error.exception), "unknown opcode 42 in instruction 42 at address 2")
+        self.assertEqual(machine.outputs, [1])
+
+        machine = intcode.Machine([1105, 1, 0])
+        with self.assertRaises(intcode.LimitExceededError):
+            machine.run(max_instructions=100)
+
+        machine = intcode.Machine([304, 0, 99])
+        with self.assertRaises(intcode.InvalidInstructionError):
+            machine.step()
+
+        self.assertTrue(issubclass(intcode.UnknownOpcodeError, intcode.IntcodeError))
+        self.assertTrue(issubclass(intcode.IntcodeError, Exception))
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,61 @@
+//! Compares running a program with no observer attached to running it with an observer that
+//! ignores every event, and one that counts instructions.
+//!
+//! The time without an observer should match the time without the hooks at all. To compare, save
+//! a baseline with the hooks compiled out, then run against it:
+//!
+//! ```text
+//! cargo bench -p intcode --bench observer --no-default-features -- --save-baseline no-hooks
+//! cargo bench -p intcode --bench observer -- --baseline no-hooks "no observer"
+//! ```
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use intcode::{Intcode, Observer};
+
+/// Counts from 0 to its input, then outputs the count.
+const COUNT: &str = "3,17,1001,16,1,16,8,16,17,18,1006,18,2,4,16,99,0,0,0";
+
+struct Ignore;
+
+impl Observer for Ignore {}
+
+#[derive(Default)]
+struct Count(u64);
+
+impl Observer for Count {
+    fn before_instruction(&mut self, _: usize, _: i32) {
+        self.0 += 1;
+    }
+}
+
+fn run(computer: &Intcode) -> Vec<i32> {
+    let mut computer = computer.clone();
+    computer.push_input(black_box(100_000));
+    computer.execute()
+}
+
+fn observer(c: &mut Criterion) {
+    let computer = Intcode::load(COUNT, vec![]);
+    assert_eq!(run(&computer), vec![100_000]);
+
+    let mut group = c.benchmark_group("count to 100000");
+
+    group.bench_function("no observer", |b| b.iter(|| run(&computer)));
+
+    let mut ignored = computer.clone();
+    ignored.set_observer(Rc::new(RefCell::new(Ignore)));
+    group.bench_function("ignoring observer", |b| b.iter(|| run(&ignored)));
+
+    let mut counted = computer.clone();
+    counted.set_observer(Rc::new(RefCell::new(Count::default())));
+    group.bench_function("counting observer", |b| b.iter(|| run(&counted)));
+
+    group.finish();
+}
+
+criterion_group!(benches, observer);
+criterion_main!(benches);
@@ -0,0 +1,703 @@
+//! Concolic testing: finding inputs that make a program take each of its branches.
+//!
+//! A program is run concretely, while a shadow memory tracks which cells hold values that are
+//! derived from the input, as linear expressions over the input values. Each comparison and
+//! conditional jump whose outcome depends on the input adds a constraint to the path of the run.
+//! Negating one constraint and solving the path up to it gives an input that takes the other side
+//! of the branch, which is run in turn.
+//!
+//! Constraints are built from what each instruction says it computes or tests, in
+//! [`isa::Compute`] and [`isa::Condition`]. Products of two input-dependent values, and
+//! input-dependent addresses and jump targets, are replaced by their concrete values, so the
+//! branches that depend on them can't be flipped. The solver only changes one input value per
+//! constraint, keeping the others as they were in the run that is being extended.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+
+use crate::isa::{self, Compute, Mode, Operation};
+use crate::{Intcode, Limits, Param, Status};
+
+/// A linear expression over the input values: the sum of `terms`, which map the index of an input
+/// value to its coefficient, and a constant.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Linear {
+    terms: BTreeMap<usize, i64>,
+    constant: i64,
+}
+
+impl Linear {
+    fn constant(value: i32) -> Self {
+        Linear { terms: BTreeMap::new(), constant: i64::from(value) }
+    }
+
+    fn input(index: usize) -> Self {
+        Linear { terms: std::iter::once((index, 1)).collect(), constant: 0 }
+    }
+
+    fn is_constant(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// `self + factor * other`, or `None` if it overflows.
+    fn add_scaled(&self, other: &Linear, factor: i64) -> Option<Linear> {
+        let mut sum = self.clone();
+
+        for (&index, &coefficient) in &other.terms {
+            let term = sum.terms.entry(index).or_insert(0);
+            *term = term.checked_add(coefficient.checked_mul(factor)?)?;
+
+            if *term == 0 {
+                sum.terms.remove(&index);
+            }
+        }
+
+        sum.constant = sum.constant.checked_add(other.constant.checked_mul(factor)?)?;
+
+        Some(sum)
+    }
+
+    /// Evaluates the expression with every input except `var` replaced by its value in `input`,
+    /// giving the coefficient of `var` and the constant.
+    fn substitute(&self, var: usize, input: &[i32]) -> Option<(i64, i64)> {
+        let mut constant = self.constant;
+
+        for (&index, &coefficient) in self.terms.iter().filter(|&(&index, _)| index != var) {
+            let value = i64::from(input.get(index).copied().unwrap_or(0));
+            constant = constant.checked_add(coefficient.checked_mul(value)?)?;
+        }
+
+        Some((self.terms.get(&var).copied().unwrap_or(0), constant))
+    }
+}
+
+/// How an expression is compared with zero.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Relation {
+    LessThan,
+    Equal,
+}
+
+/// A condition on the input: `expr < 0` or `expr == 0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Condition {
+    expr: Linear,
+    relation: Relation,
+}
+
+/// A condition that held, or didn't, on the path of a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Constraint {
+    condition: Condition,
+    holds: bool,
+}
+
+impl Constraint {
+    fn negate(&self) -> Constraint {
+        Constraint { condition: self.condition.clone(), holds: !self.holds }
+    }
+}
+
+/// What the shadow memory knows about a cell that depends on the input.
+#[derive(Debug, Clone)]
+enum Symbol {
+    Linear(Linear),
+
+    /// The cell holds 1 if the condition holds, and 0 otherwise.
+    Condition(Condition),
+}
+
+impl Symbol {
+    /// The symbol as a linear expression. Conditions aren't linear, so they become constants.
+    fn linear(&self, concrete: i32) -> Linear {
+        match self {
+            Symbol::Linear(linear) => linear.clone(),
+            Symbol::Condition(_) => Linear::constant(concrete),
+        }
+    }
+
+    /// The constraint that the value is nonzero, given whether it is.
+    fn nonzero(&self, nonzero: bool) -> Constraint {
+        match self {
+            Symbol::Condition(condition) => Constraint { condition: condition.clone(), holds: nonzero },
+            Symbol::Linear(expr) => Constraint {
+                condition: Condition { expr: expr.clone(), relation: Relation::Equal },
+                holds: !nonzero,
+            },
+        }
+    }
+}
+
+/// A branch taken by a run that depends on the input.
+#[derive(Debug, Clone)]
+struct Branch {
+    pc: usize,
+    taken: bool,
+    constraint: Constraint,
+}
+
+/// A concrete run, and the symbolic facts gathered along the way.
+struct Run {
+    /// The input that was read, including the zeroes that were read after the given input ran out.
+    input: Vec<i32>,
+    output: Vec<i32>,
+
+    /// The outcome of every comparison and conditional jump.
+    outcomes: Vec<(usize, bool)>,
+
+    /// The input-dependent branches, in order.
+    path: Vec<Branch>,
+
+    /// Each output that depends on the input, with the length of the path when it was output.
+    symbolic_outputs: Vec<(usize, Symbol, i32)>,
+}
+
+fn run(program: &[i32], input: &[i32], limits: &Limits) -> Run {
+    let mut computer = Intcode::new(program.to_vec(), vec![]);
+    let mut shadow = HashMap::<usize, Symbol>::new();
+
+    let mut run = Run {
+        input: vec![],
+        output: vec![],
+        outcomes: vec![],
+        path: vec![],
+        symbolic_outputs: vec![],
+    };
+
+    let mut steps = 0;
+
+    while limits.check(steps).is_ok() {
+        let pc = computer.pc();
+        let instr = computer.mem().get(pc);
+
+        let isa::Decoded { opcode, modes } = isa::decode(instr);
+
+        let instruction = match isa::lookup(opcode) {
+            Some(instruction) => instruction,
+            None => break,
+        };
+
+        // The concrete and symbolic values of the read parameters, and the write address.
+        let mut args = vec![];
+        let mut dst = None;
+
+        // Whether each read parameter is an immediate constant, which makes a branch that only
+        // depends on constants unconditional.
+        let mut constant = vec![];
+
+        for ((i, param), mode) in instruction.params.iter().enumerate().zip(modes) {
+            let cell = computer.mem().get(pc + 1 + i);
+            let addr = match mode {
+                Ok(Mode::Position) => usize::try_from(cell).ok(),
+                Ok(Mode::Relative) => usize::try_from(computer.relative_base() + cell).ok(),
+                _ => None,
+            };
+
+            match param {
+                Param::Read => {
+                    // An immediate parameter is the cell itself, which may hold input too.
+                    let source = addr.unwrap_or(pc + 1 + i);
+                    args.push((computer.mem().get(source), shadow.get(&source).cloned()));
+                    constant.push(addr.is_none() && !shadow.contains_key(&source));
+                }
+                Param::Write => dst = addr,
+            }
+        }
+
+        match computer.step(limits) {
+            Ok(None) => (),
+            Ok(Some(Status::NeedsInput)) => {
+                computer.push_input(input.get(run.input.len()).copied().unwrap_or(0));
+                continue;
+            }
+            Ok(Some(Status::Halted)) | Err(_) => break,
+        }
+
+        steps += 1;
+
+        let result = match instruction.operation {
+            Operation::Compute(compute) => {
+                let taken = dst.is_some_and(|dst| computer.mem().get(dst) != 0);
+                let result = self::compute(compute, &args);
+                let compares = matches!(compute, Compute::LessThan | Compute::Equals);
+
+                if compares && !(constant[0] && constant[1]) {
+                    run.outcomes.push((pc, taken));
+
+                    if let Some(Symbol::Condition(condition)) = &result {
+                        let constraint = Constraint { condition: condition.clone(), holds: taken };
+                        run.path.push(Branch { pc, taken, constraint });
+                    }
+                }
+
+                result
+            }
+            Operation::Input => {
+                let value = computer.mem().get(computer.written()[0]);
+                run.input.push(value);
+                Some(Symbol::Linear(Linear::input(run.input.len() - 1)))
+            }
+            Operation::Output => {
+                let (value, symbol) = &args[0];
+                run.output.push(*value);
+
+                if let Some(symbol) = symbol {
+                    run.symbolic_outputs.push((run.path.len(), symbol.clone(), *value));
+                }
+
+                None
+            }
+            Operation::JumpIf(condition) => {
+                let (value, symbol) = &args[0];
+                let taken = condition.holds(*value);
+
+                if !constant[0] {
+                    run.outcomes.push((pc, taken));
+                }
+
+                // Whichever way a jump tests its value, the branch is flipped by flipping whether
+                // the value is zero, or negative.
+                let constraint = match (symbol, condition) {
+                    (Some(symbol), isa::Condition::NonZero | isa::Condition::Zero) => {
+                        Some(symbol.nonzero(*value != 0))
+                    }
+                    (Some(Symbol::Linear(expr)), isa::Condition::Negative) => Some(Constraint {
+                        condition: Condition { expr: expr.clone(), relation: Relation::LessThan },
+                        holds: *value < 0,
+                    }),
+                    _ => None,
+                };
+
+                if let Some(constraint) = constraint {
+                    run.path.push(Branch { pc, taken, constraint });
+                }
+
+                None
+            }
+            Operation::AdjustRelativeBase | Operation::Halt => None,
+        };
+
+        if let Some(dst) = dst {
+            match result {
+                Some(Symbol::Linear(linear)) if linear.is_constant() => shadow.remove(&dst),
+                Some(symbol) => shadow.insert(dst, symbol),
+                None => shadow.remove(&dst),
+            };
+        }
+    }
+
+    run
+}
+
+/// The symbol for the result of a computation, if it still depends on the input.
+fn compute(compute: Compute, args: &[(i32, Option<Symbol>)]) -> Option<Symbol> {
+    if args.iter().all(|(_, symbol)| symbol.is_none()) {
+        return None;
+    }
+
+    let linear = |i: usize| {
+        let (value, symbol) = &args[i];
+        symbol.as_ref().map_or_else(|| Linear::constant(*value), |symbol| symbol.linear(*value))
+    };
+
+    let (a, b) = (linear(0), linear(1));
+
+    // `x * a + y * b + c`.
+    let combine = |[x, y, c]: [i64; 3]| {
+        let linear = Linear { terms: BTreeMap::new(), constant: c };
+        Some(Symbol::Linear(linear.add_scaled(&a, x)?.add_scaled(&b, y)?))
+    };
+
+    let constant = |linear: &Linear| Some(linear.constant).filter(|_| linear.is_constant());
+
+    // `a < b` or `a == b`, as a condition on `a - b`.
+    let compare = |relation| Some(Symbol::Condition(Condition { expr: a.add_scaled(&b, -1)?, relation }));
+
+    let symbol = match compute {
+        Compute::LessThan => compare(Relation::LessThan)?,
+        Compute::Equals => compare(Relation::Equal)?,
+        Compute::Add => combine([1, 1, 0])?,
+        Compute::Affine([x, y, c]) => combine([i64::from(x), i64::from(y), i64::from(c)])?,
+
+        // A product is only linear if one of its factors is a constant.
+        Compute::Multiply => match (constant(&a), constant(&b)) {
+            (Some(a), _) => combine([0, a, 0])?,
+            (_, Some(b)) => combine([b, 0, 0])?,
+            _ => return None,
+        },
+    };
+
+    match &symbol {
+        Symbol::Linear(linear) if linear.is_constant() => None,
+        Symbol::Condition(condition) if condition.expr.is_constant() => None,
+        _ => Some(symbol),
+    }
+}
+
+/// Finds an input that satisfies all the constraints by changing a single value of `input` that
+/// appears in the last constraint.
+fn solve(constraints: &[Constraint], input: &[i32]) -> Option<Vec<i32>> {
+    let target = constraints.last()?;
+
+    target.condition.expr.terms.keys().find_map(|&var| {
+        let value = solve_for(var, constraints, input)?;
+
+        let mut solution = input.to_vec();
+        if solution.len() <= var {
+            solution.resize(var + 1, 0);
+        }
+        solution[var] = value;
+
+        Some(solution)
+    })
+}
+
+/// Finds the value of input `var` closest to its current value that satisfies the constraints,
+/// with the other input values fixed.
+fn solve_for(var: usize, constraints: &[Constraint], input: &[i32]) -> Option<i32> {
+    let (mut lo, mut hi) = (i64::from(i32::MIN), i64::from(i32::MAX));
+    let mut equal = None;
+    let mut excluded = HashSet::new();
+
+    for constraint in constraints {
+        let (a, c) = constraint.condition.expr.substitute(var, input)?;
+
+        // The constraint is now `a * var + c` compared with zero.
+        if a == 0 {
+            let holds = match constraint.condition.relation {
+                Relation::LessThan => c < 0,
+                Relation::Equal => c == 0,
+            };
+
+            if holds != constraint.holds {
+                return None;
+            }
+
+            continue;
+        }
+
+        match (constraint.condition.relation, constraint.holds) {
+            // a * var < -c, so a * var <= -c - 1.
+            (Relation::LessThan, true) if a > 0 => hi = hi.min(floor_div(-c - 1, a)),
+            (Relation::LessThan, true) => lo = lo.max(ceil_div(c + 1, -a)),
+            // a * var >= -c.
+            (Relation::LessThan, false) if a > 0 => lo = lo.max(ceil_div(-c, a)),
+            (Relation::LessThan, false) => hi = hi.min(floor_div(c, -a)),
+            (Relation::Equal, holds) => {
+                if -c % a != 0 {
+                    if holds {
+                        return None;
+                    }
+                    continue;
+                }
+
+                let value = -c / a;
+                if holds {
+                    if equal.is_some_and(|equal| equal != value) {
+                        return None;
+                    }
+                    equal = Some(value);
+                } else {
+                    excluded.insert(value);
+                }
+            }
+        }
+    }
+
+    if lo > hi {
+        return None;
+    }
+
+    let allowed = |value: i64| lo <= value && value <= hi && !excluded.contains(&value);
+
+    let value = match equal {
+        Some(value) => Some(value).filter(|&value| allowed(value)),
+        None => {
+            let current = i64::from(input.get(var).copied().unwrap_or(0)).clamp(lo, hi);
+
+            (0..=excluded.len() as i64)
+                .flat_map(|distance| vec![current - distance, current + distance])
+                .find(|&value| allowed(value))
+        }
+    };
+
+    value.and_then(|value| i32::try_from(value).ok())
+}
+
+fn floor_div(a: i64, b: i64) -> i64 {
+    a.div_euclid(b)
+}
+
+fn ceil_div(a: i64, b: i64) -> i64 {
+    -(-a).div_euclid(b)
+}
+
+/// Limits on the exploration.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The limits of each run.
+    pub limits: Limits,
+
+    /// The number of runs to try before giving up.
+    pub max_runs: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options { limits: Limits { max_instructions: Some(100_000), ..Limits::default() }, max_runs: 1000 }
+    }
+}
+
+/// An input, and the output that the program produced for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCase {
+    pub input: Vec<i32>,
+    pub output: Vec<i32>,
+}
+
+/// The result of [`cover`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Coverage {
+    /// The inputs that reached new branches, in the order they were found.
+    pub cases: Vec<TestCase>,
+
+    /// The address of each comparison and conditional jump that ran, and which of its outcomes
+    /// were covered: whether the comparison was false or true, or the jump was taken or not.
+    pub branches: BTreeMap<usize, [bool; 2]>,
+}
+
+impl Coverage {
+    /// Whether both outcomes of every branch were covered.
+    pub fn is_complete(&self) -> bool {
+        self.branches.values().all(|outcomes| outcomes[0] && outcomes[1])
+    }
+
+    /// The branches and outcomes that weren't covered.
+    pub fn uncovered(&self) -> Vec<(usize, bool)> {
+        self.branches
+            .iter()
+            .flat_map(|(&pc, outcomes)| [false, true].iter().filter(move |&&outcome| !outcomes[outcome as usize]).map(move |&outcome| (pc, outcome)))
+            .collect()
+    }
+
+    fn covers(&self, pc: usize, outcome: bool) -> bool {
+        self.branches.get(&pc).is_some_and(|outcomes| outcomes[outcome as usize])
+    }
+}
+
+/// Explores the branches of a program, starting with `seed` as the input. Input that the program
+/// reads after `seed` runs out is 0.
+pub fn cover(program: &[i32], seed: &[i32], options: &Options) -> Coverage {
+    explore(program, seed, options, None).0
+}
+
+/// Searches for an input that makes the program output `target`, starting with `seed`.
+pub fn find_output(program: &[i32], target: i32, seed: &[i32], options: &Options) -> Option<Vec<i32>> {
+    explore(program, seed, options, Some(target)).1
+}
+
+fn explore(program: &[i32], seed: &[i32], options: &Options, target: Option<i32>) -> (Coverage, Option<Vec<i32>>) {
+    let mut coverage = Coverage::default();
+    let mut queue = VecDeque::from(vec![seed.to_vec()]);
+    let mut tried = HashSet::new();
+
+    while let Some(input) = queue.pop_front() {
+        if tried.len() == options.max_runs {
+            break;
+        }
+
+        if !tried.insert(input.clone()) {
+            continue;
+        }
+
+        let run = run(program, &input, &options.limits);
+
+        if target.is_some_and(|target| run.output.contains(&target)) {
+            return (coverage, Some(run.input));
+        }
+
+        let mut new = false;
+        for &(pc, outcome) in &run.outcomes {
+            let outcomes = coverage.branches.entry(pc).or_insert([false; 2]);
+            new |= !outcomes[outcome as usize];
+            outcomes[outcome as usize] = true;
+        }
+
+        if new {
+            coverage.cases.push(TestCase { input: run.input.clone(), output: run.output.clone() });
+        }
+
+        let constraints = run.path.iter().map(|branch| branch.constraint.clone()).collect::<Vec<_>>();
+
+        // Outputs that can be solved for directly are tried first.
+        if let Some(target) = target {
+            for (len, symbol, _) in &run.symbolic_outputs {
+                let wanted = match symbol {
+                    Symbol::Linear(expr) => Constraint {
+                        condition: Condition {
+                            expr: match expr.add_scaled(&Linear::constant(target), -1) {
+                                Some(expr) => expr,
+                                None => continue,
+                            },
+                            relation: Relation::Equal,
+                        },
+                        holds: true,
+                    },
+                    Symbol::Condition(condition) if target == 0 || target == 1 => {
+                        Constraint { condition: condition.clone(), holds: target == 1 }
+                    }
+                    Symbol::Condition(_) => continue,
+                };
+
+                let mut path = constraints[..*len].to_vec();
+                path.push(wanted);
+
+                if let Some(input) = solve(&path, &run.input) {
+                    queue.push_front(input);
+                }
+            }
+        }
+
+        for (i, branch) in run.path.iter().enumerate() {
+            if coverage.covers(branch.pc, !branch.taken) {
+                continue;
+            }
+
+            let mut path = constraints[..i].to_vec();
+            path.push(branch.constraint.negate());
+
+            if let Some(input) = solve(&path, &run.input) {
+                queue.push_back(input);
+            }
+        }
+    }
+
+    (coverage, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::program::{self, COMPARE_TO_8};
+    use crate::{isa, Intcode};
+
+    use super::{cover, find_output, Options};
+
+    fn parse(program: &str) -> Vec<i32> {
+        program::parse(program).unwrap()
+    }
+
+    fn execute(program: &[i32], input: &[i32]) -> Vec<i32> {
+        Intcode::new(program.to_vec(), input.to_vec()).execute()
+    }
+
+    #[test]
+    fn covers_larger_example() {
+        let program = parse(COMPARE_TO_8);
+        let coverage = cover(&program, &[0], &Options::default());
+
+        assert!(coverage.is_complete(), "{:?}", coverage.uncovered());
+
+        let mut outputs = coverage.cases.iter().map(|case| case.output.clone()).collect::<Vec<_>>();
+        outputs.sort();
+        assert_eq!(outputs, vec![vec![999], vec![1000], vec![1001]]);
+
+        for case in &coverage.cases {
+            assert_eq!(execute(&program, &case.input), case.output);
+        }
+
+        // The comparison with 8 at address 2, and the less than comparison at address 9.
+        assert_eq!(coverage.branches[&2], [true, true]);
+        assert_eq!(coverage.branches[&9], [true, true]);
+    }
+
+    #[test]
+    fn covers_comparisons_without_jumps() {
+        // The examples from day 5 that output whether the input is equal to or less than 8.
+        for example in &["3,9,8,9,10,9,4,9,99,-1,8", "3,9,7,9,10,9,4,9,99,-1,8", "3,3,1108,-1,8,3,4,3,99", "3,3,1107,-1,8,3,4,3,99"] {
+            let coverage = cover(&parse(example), &[], &Options::default());
+
+            assert!(coverage.is_complete(), "{}", example);
+            assert_eq!(coverage.cases.len(), 2, "{}", example);
+        }
+    }
+
+    #[test]
+    fn finds_outputs() {
+        let program = parse(COMPARE_TO_8);
+
+        for &target in &[999, 1000, 1001] {
+            let input = find_output(&program, target, &[0], &Options::default()).unwrap();
+            assert_eq!(execute(&program, &input), vec![target]);
+        }
+
+        assert_eq!(find_output(&program, 1000, &[0], &Options::default()), Some(vec![8]));
+        assert_eq!(find_output(&program, 5, &[0], &Options::default()), None);
+    }
+
+    #[test]
+    fn solves_outputs_directly() {
+        // Outputs the input plus 5, times 3.
+        let program = parse("3,0,1001,0,5,0,1002,0,3,0,4,0,99");
+        assert_eq!(find_output(&program, 42, &[], &Options::default()), Some(vec![9]));
+
+        // There's no integer solution.
+        assert_eq!(find_output(&program, 43, &[], &Options { max_runs: 10, ..Options::default() }), None);
+    }
+
+    #[test]
+    fn multiple_inputs() {
+        // Reads a and b, and outputs whether a + 2 * b is 20.
+        let program = parse("3,100,3,101,1002,101,2,102,1,100,102,103,1008,103,20,104,4,104,99");
+
+        let input = find_output(&program, 1, &[], &Options::default()).unwrap();
+        assert_eq!(input.len(), 2);
+        assert_eq!(input[0] + 2 * input[1], 20);
+
+        let coverage = cover(&program, &[3, 4], &Options::default());
+        assert!(coverage.is_complete());
+        assert_eq!(coverage.cases[0].input, vec![3, 4]);
+    }
+
+    #[test]
+    fn loops_on_input() {
+        // Counts down from the input, outputting each value, and outputs -1 if the input isn't
+        // positive.
+        let program = parse("3,100,1007,100,1,101,1005,101,22,4,100,1001,100,-1,100,1005,100,9,99,0,0,0,104,-1,99");
+
+        let coverage = cover(&program, &[], &Options::default());
+        assert!(coverage.is_complete(), "{:?}", coverage.uncovered());
+
+        assert_eq!(find_output(&program, -1, &[2], &Options::default()).map(|input| input[0] <= 0), Some(true));
+        assert_eq!(
+            find_output(&program, 3, &[0], &Options::default()).map(|input| execute(&program, &input).contains(&3)),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn uses_the_instruction_table() {
+        let _examples = isa::registry::register_examples();
+        let program = parse(isa::registry::EXAMPLE);
+
+        let coverage = cover(&program, &[20], &Options::default());
+        assert!(coverage.is_complete(), "{:?}", coverage.uncovered());
+        assert_eq!(coverage.cases.len(), 2);
+
+        assert_eq!(find_output(&program, 1, &[20], &Options::default()), Some(vec![7]));
+
+        // Outputs 10 minus the input.
+        let program = parse("3,0,42,10,0,0,4,0,99,0,10");
+        assert_eq!(find_output(&program, 4, &[], &Options::default()), Some(vec![6]));
+    }
+
+    #[test]
+    fn reports_unreachable_branches() {
+        // Multiplies the input by 0 and compares the result with 1, which can never be true.
+        let program = parse("3,20,1002,20,0,21,1008,21,1,22,4,22,99");
+        let coverage = cover(&program, &[], &Options::default());
+
+        assert!(!coverage.is_complete());
+        assert_eq!(coverage.uncovered(), vec![(6, true)]);
+        assert_eq!(coverage.cases.len(), 1);
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+
+/// A loop that a program entered and can never leave.
+///
+/// The loop was detected because the machine returned to a state that it had already been in,
+/// so it would repeat the same instructions forever.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Cycle {
+    /// The lowest address of an instruction in the loop.
+    pub first: usize,
+
+    /// The highest address of an instruction in the loop.
+    pub last: usize,
+
+    /// The number of instructions executed by one iteration of the loop.
+    pub period: u64,
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "infinite loop between addresses {} and {}, repeating every {} instructions",
+            self.first, self.last, self.period
+        )
+    }
+}
+
+/// Looks for a state of the machine after a jump that repeats, with Brent's algorithm.
+///
+/// Only one state is remembered at a time, so detection uses constant memory however long the
+/// program runs. The remembered state moves after 1, 2, 4, ... more jumps, so a loop is found
+/// within a few iterations once the remembered state is inside it and the loop has fewer jumps
+/// than the machine made before.
+#[derive(Debug, Clone)]
+pub(crate) struct CycleDetector {
+    /// The number of instructions executed since detection was enabled.
+    steps: u64,
+
+    /// The hash of the remembered state, and the step it was seen at.
+    checkpoint: Option<(u64, u64)>,
+
+    /// The number of jumps since the checkpoint was taken.
+    visits: u64,
+
+    /// The number of jumps after which the checkpoint moves.
+    power: u64,
+}
+
+impl Default for CycleDetector {
+    fn default() -> Self {
+        CycleDetector { steps: 0, checkpoint: None, visits: 0, power: 1 }
+    }
+}
+
+impl CycleDetector {
+    pub(crate) fn tick(&mut self) {
+        self.steps += 1;
+    }
+
+    /// Records a state after a jump. If it has the same hash as the remembered state, returns the
+    /// number of instructions executed since, which is the period of the loop unless the hashes
+    /// collide.
+    pub(crate) fn visit(&mut self, state: u64) -> Option<u64> {
+        if let Some((hash, step)) = self.checkpoint {
+            if hash == state {
+                // If the states only collide, looking for a loop starts over from here.
+                self.checkpoint = Some((state, self.steps));
+                self.visits = 0;
+                return Some(self.steps - step);
+            }
+        }
+
+        if self.checkpoint.is_none() || self.visits == self.power {
+            self.checkpoint = Some((state, self.steps));
+            self.visits = 0;
+            self.power *= 2;
+        }
+
+        self.visits += 1;
+        None
+    }
+
+    /// Makes `state` the remembered state, as if it had just been seen.
+    #[cfg(test)]
+    pub(crate) fn remember(&mut self, state: u64) {
+        self.checkpoint = Some((state, self.steps));
+        self.visits = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CycleDetector;
+
+    #[test]
+    fn finds_period() {
+        // A state after every jump, with 10 states before a loop of 7.
+        let states = (0..).map(|jump: u64| if jump < 10 { jump + 100 } else { (jump - 10) % 7 });
+
+        let mut detector = CycleDetector::default();
+        let period = states.take(1000).find_map(|state| {
+            detector.tick();
+            detector.visit(state)
+        });
+
+        assert_eq!(period, Some(7));
+    }
+
+    #[test]
+    fn uses_constant_memory() {
+        let mut detector = CycleDetector::default();
+
+        for state in 0..100_000 {
+            detector.tick();
+            assert_eq!(detector.visit(state), None);
+        }
+
+        // Only a recent state is remembered.
+        assert!(detector.checkpoint.unwrap().0 > 50_000);
+    }
+}
@@ -0,0 +1,382 @@
+//! Virtual devices attached to an Intcode computer.
+//!
+//! A [`Console`] wires devices to the computer in two ways:
+//!
+//! - Through the input and output streams. The input device supplies values for input
+//!   instructions, and the output device receives every value that is output.
+//! - Through reserved memory addresses. Before each instruction, a mapped device's current value
+//!   is stored at its address, and every write to the address is passed to the device.
+//!
+//! Devices are shared with the host through `Rc<RefCell<_>>`, so the host can inspect them while
+//! or after the program runs.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use crate::{ExecutionError, Intcode, Limits, Status};
+
+pub trait Device {
+    /// Produces the device's next value, or `None` if it has nothing to offer yet.
+    fn read(&mut self) -> Option<i32> {
+        None
+    }
+
+    /// Receives a value from the program.
+    fn write(&mut self, _value: i32) {}
+
+    /// Called after every instruction that the computer executes.
+    fn tick(&mut self) {}
+}
+
+/// A display that shows output as ASCII text.
+#[derive(Debug, Default)]
+pub struct TextDisplay {
+    text: String,
+}
+
+impl TextDisplay {
+    pub fn new() -> Self {
+        TextDisplay::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Device for TextDisplay {
+    fn write(&mut self, value: i32) {
+        let c = u8::try_from(value).map(char::from).unwrap_or(char::REPLACEMENT_CHARACTER);
+        self.text.push(c);
+    }
+}
+
+pub const EMPTY: i32 = 0;
+pub const WALL: i32 = 1;
+pub const BLOCK: i32 = 2;
+pub const PADDLE: i32 = 3;
+pub const BALL: i32 = 4;
+
+/// A screen of tiles, drawn by outputting the X position, the Y position and the tile.
+///
+/// Drawing to (-1, 0) sets the score instead of a tile.
+#[derive(Debug, Default)]
+pub struct TileScreen {
+    tiles: HashMap<(i32, i32), i32>,
+    score: Option<i32>,
+    pending: Vec<i32>,
+}
+
+impl TileScreen {
+    pub fn new() -> Self {
+        TileScreen::default()
+    }
+
+    pub fn tile(&self, x: i32, y: i32) -> i32 {
+        self.tiles.get(&(x, y)).copied().unwrap_or(EMPTY)
+    }
+
+    pub fn score(&self) -> Option<i32> {
+        self.score
+    }
+
+    /// The number of tiles of the given kind on the screen.
+    pub fn count(&self, tile: i32) -> usize {
+        self.tiles.values().filter(|&&t| t == tile).count()
+    }
+
+    /// The position of a tile of the given kind, such as the ball.
+    pub fn find(&self, tile: i32) -> Option<(i32, i32)> {
+        self.tiles.iter().find(|&(_, &t)| t == tile).map(|(&position, _)| position)
+    }
+
+    /// Draws the screen from its top-left corner, which is always at (0, 0).
+    pub fn render(&self) -> String {
+        let width = self.tiles.keys().map(|&(x, _)| x + 1).max().unwrap_or(0);
+        let height = self.tiles.keys().map(|&(_, y)| y + 1).max().unwrap_or(0);
+
+        let mut out = String::new();
+
+        for y in 0..height {
+            for x in 0..width {
+                out.push(match self.tile(x, y) {
+                    WALL => '#',
+                    BLOCK => '=',
+                    PADDLE => '-',
+                    BALL => 'o',
+                    _ => ' ',
+                });
+            }
+
+            out.push('\n');
+        }
+
+        out
+    }
+}
+
+impl Device for TileScreen {
+    fn write(&mut self, value: i32) {
+        self.pending.push(value);
+
+        if let [x, y, tile] = self.pending[..] {
+            if (x, y) == (-1, 0) {
+                self.score = Some(tile);
+            } else {
+                self.tiles.insert((x, y), tile);
+            }
+
+            self.pending.clear();
+        }
+    }
+}
+
+/// A joystick that is tilted left (-1), right (1) or held neutral (0).
+#[derive(Debug, Default)]
+pub struct Joystick {
+    position: i32,
+}
+
+impl Joystick {
+    pub fn new() -> Self {
+        Joystick::default()
+    }
+
+    pub fn set(&mut self, position: i32) {
+        self.position = position;
+    }
+}
+
+impl Device for Joystick {
+    fn read(&mut self) -> Option<i32> {
+        Some(self.position)
+    }
+}
+
+/// Counts the instructions that have been executed.
+#[derive(Debug, Default)]
+pub struct Clock {
+    ticks: i32,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Clock::default()
+    }
+
+    pub fn ticks(&self) -> i32 {
+        self.ticks
+    }
+}
+
+impl Device for Clock {
+    fn read(&mut self) -> Option<i32> {
+        Some(self.ticks)
+    }
+
+    fn tick(&mut self) {
+        self.ticks += 1;
+    }
+}
+
+type SharedDevice = Rc<RefCell<dyn Device>>;
+
+/// An Intcode computer with devices attached.
+pub struct Console {
+    computer: Intcode,
+    input: Option<SharedDevice>,
+    output: Option<SharedDevice>,
+    mapped: Vec<(usize, SharedDevice)>,
+
+    /// Every attached device, once.
+    devices: Vec<SharedDevice>,
+}
+
+impl Console {
+    pub fn new(computer: Intcode) -> Self {
+        Console {
+            computer,
+            input: None,
+            output: None,
+            mapped: vec![],
+            devices: vec![],
+        }
+    }
+
+    pub fn computer(&self) -> &Intcode {
+        &self.computer
+    }
+
+    pub fn computer_mut(&mut self) -> &mut Intcode {
+        &mut self.computer
+    }
+
+    /// Supplies input instructions with values read from the device.
+    pub fn attach_input(&mut self, device: SharedDevice) {
+        self.input = Some(self.attach(device));
+    }
+
+    /// Sends every value that is output to the device.
+    pub fn attach_output(&mut self, device: SharedDevice) {
+        self.output = Some(self.attach(device));
+    }
+
+    /// Maps the device to the cell at `addr`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the address is outside of the program's memory.
+    pub fn map(&mut self, addr: usize, device: SharedDevice) {
+        assert!(addr < self.computer.mem().len(), "address {} is out of range", addr);
+
+        let device = self.attach(device);
+        self.mapped.push((addr, device));
+    }
+
+    fn attach(&mut self, device: SharedDevice) -> SharedDevice {
+        if !self.devices.iter().any(|attached| Rc::ptr_eq(attached, &device)) {
+            self.devices.push(Rc::clone(&device));
+        }
+
+        device
+    }
+
+    /// Runs the program until it halts, or needs input that the input device can't supply.
+    pub fn run(&mut self, limits: &Limits) -> Result<Status, ExecutionError> {
+        let mut steps = 0;
+
+        loop {
+            limits.check(steps)?;
+
+            for (addr, device) in &self.mapped {
+                if let Some(value) = device.borrow_mut().read() {
+                    self.computer.mem_mut()[*addr] = value;
+                }
+            }
+
+            match self.computer.step(limits)? {
+                None => (),
+                Some(Status::NeedsInput) => {
+                    let value = self.input.as_ref().and_then(|device| device.borrow_mut().read());
+
+                    match value {
+                        Some(value) => {
+                            self.computer.push_input(value);
+                            continue;
+                        }
+                        None => return Ok(Status::NeedsInput),
+                    }
+                }
+                Some(Status::Halted) => return Ok(Status::Halted),
+            }
+
+            steps += 1;
+
+            for (addr, device) in &self.mapped {
+                if self.computer.written().contains(addr) {
+                    device.borrow_mut().write(self.computer.mem()[*addr]);
+                }
+            }
+
+            if let Some(output) = &self.output {
+                for value in self.computer.take_output() {
+                    output.borrow_mut().write(value);
+                }
+            }
+
+            for device in &self.devices {
+                device.borrow_mut().tick();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::{program, Intcode, Limits, Status};
+
+    use super::{Clock, Console, Joystick, TextDisplay, TileScreen, BLOCK, PADDLE, WALL};
+
+    #[test]
+    fn text_display_on_output() {
+        let display = Rc::new(RefCell::new(TextDisplay::new()));
+
+        let mut console = Console::new(Intcode::load("104,72,104,73,104,10,99", vec![]));
+        console.attach_output(display.clone());
+
+        assert_eq!(console.run(&Limits::default()), Ok(Status::Halted));
+        assert_eq!(display.borrow().text(), "HI\n");
+    }
+
+    #[test]
+    fn text_display_in_memory() {
+        let display = Rc::new(RefCell::new(TextDisplay::new()));
+
+        let mut console = Console::new(Intcode::load("1101,79,0,9,1101,75,0,9,99,0", vec![]));
+        console.map(9, display.clone());
+
+        console.run(&Limits::default()).unwrap();
+        assert_eq!(display.borrow().text(), "OK");
+    }
+
+    #[test]
+    fn clock_in_memory() {
+        // Outputs the clock, wastes an instruction, then outputs the clock again.
+        let clock = Rc::new(RefCell::new(Clock::new()));
+
+        let mut console = Console::new(Intcode::load("4,10,1101,0,0,11,4,10,99,0,0,0", vec![]));
+        console.map(10, clock.clone());
+
+        assert_eq!(console.run(&Limits::default()), Ok(Status::Halted));
+        assert_eq!(console.computer().output(), &[0, 2]);
+        assert_eq!(clock.borrow().ticks(), 3);
+    }
+
+    #[test]
+    fn arcade() {
+        let program = program::parse(
+            "
+            104,0, 104,0, 104,1,    # wall at (0, 0)
+            104,1, 104,0, 104,2,    # block at (1, 0)
+            104,2, 104,0, 104,1,    # wall at (2, 0)
+            3,100,                  # read the joystick
+            1001,100,1,101,         # move the paddle
+            4,101, 104,1, 104,3,    # paddle at (1 + joystick, 1)
+            104,-1, 104,0, 104,42,  # score
+            99
+            ",
+        )
+        .unwrap();
+
+        let screen = Rc::new(RefCell::new(TileScreen::new()));
+        let joystick = Rc::new(RefCell::new(Joystick::new()));
+        joystick.borrow_mut().set(1);
+
+        let mut console = Console::new(Intcode::new(program, vec![]));
+        console.attach_input(joystick.clone());
+        console.attach_output(screen.clone());
+
+        assert_eq!(console.run(&Limits::default()), Ok(Status::Halted));
+
+        let screen = screen.borrow();
+        assert_eq!(screen.render(), "#=#\n  -\n");
+        assert_eq!(screen.count(WALL), 2);
+        assert_eq!(screen.count(BLOCK), 1);
+        assert_eq!(screen.find(PADDLE), Some((2, 1)));
+        assert_eq!(screen.score(), Some(42));
+    }
+
+    #[test]
+    fn needs_input_without_input_device() {
+        let mut console = Console::new(Intcode::load("3,0,99", vec![]));
+        assert_eq!(console.run(&Limits::default()), Ok(Status::NeedsInput));
+
+        console.computer_mut().push_input(5);
+        assert_eq!(console.run(&Limits::default()), Ok(Status::Halted));
+    }
+}
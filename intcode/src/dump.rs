@@ -0,0 +1,144 @@
+//! Inspecting memory after a run.
+
+use std::fmt::{self, Write};
+use std::ops::Range;
+
+use crate::Intcode;
+
+/// The number of cells on each line of a dump.
+const CELLS_PER_LINE: usize = 8;
+
+/// A cell whose value differs between two memories. A missing value means that the cell didn't
+/// exist, because memory grew during the run.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub addr: usize,
+    pub old: Option<i32>,
+    pub new: Option<i32>,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = |value: Option<i32>| value.map_or_else(|| String::from("-"), |v| v.to_string());
+
+        write!(f, "{:>6}: {} -> {}", self.addr, value(self.old), value(self.new))
+    }
+}
+
+/// The cells that changed between two memories, such as a program and the memory left after
+/// running it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn diff(before: &[i32], after: &[i32]) -> Diff {
+    let changes = (0..before.len().max(after.len()))
+        .map(|addr| Change {
+            addr,
+            old: before.get(addr).copied(),
+            new: after.get(addr).copied(),
+        })
+        .filter(|change| change.old != change.new)
+        .collect();
+
+    Diff { changes }
+}
+
+/// Formats a range of the machine's memory in columns, like a hexdump.
+///
+/// Each line starts with the address of its first cell and ends with a column that marks each
+/// cell as code (`c`) if it has been executed as part of an instruction, or data (`.`) otherwise.
+/// The range is clamped to the end of memory.
+pub fn dump(computer: &Intcode, range: Range<usize>) -> String {
+    let mem = computer.mem();
+    let range = range.start.min(mem.len())..range.end.min(mem.len());
+
+    let width = range.clone().map(|addr| mem[addr].to_string().len()).max().unwrap_or(1);
+    let addr_width = range.end.saturating_sub(1).to_string().len().max(4);
+
+    let mut out = String::new();
+
+    for start in range.clone().step_by(CELLS_PER_LINE) {
+        let end = (start + CELLS_PER_LINE).min(range.end);
+
+        write!(out, "{:0addr_width$}:", start, addr_width = addr_width).unwrap();
+
+        for addr in start..end {
+            write!(out, " {:>width$}", mem[addr], width = width).unwrap();
+        }
+
+        for _ in end..start + CELLS_PER_LINE {
+            write!(out, " {:>width$}", "", width = width).unwrap();
+        }
+
+        let kinds = (start..end)
+            .map(|addr| if computer.is_executed(addr) { 'c' } else { '.' })
+            .collect::<String>();
+
+        writeln!(out, "  |{}|", kinds).unwrap();
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{program, Intcode};
+
+    use super::{diff, dump, Change};
+
+    #[test]
+    fn diff_changed_cells() {
+        let program = program::parse("1,1,1,4,99,5,6,0,99").unwrap();
+        let mut computer = Intcode::new(program.clone(), vec![]);
+        computer.execute();
+
+        let diff = diff(&program, &computer.mem().to_vec());
+
+        assert_eq!(
+            diff.changes,
+            vec![
+                Change { addr: 0, old: Some(1), new: Some(30) },
+                Change { addr: 4, old: Some(99), new: Some(2) },
+            ]
+        );
+        assert_eq!(diff.to_string(), "     0: 1 -> 30\n     4: 99 -> 2\n");
+    }
+
+    #[test]
+    fn diff_grown_memory() {
+        let diff = diff(&[1101, 1, 2, 6, 99], &[1101, 1, 2, 6, 99, 0, 3]);
+
+        assert_eq!(diff.to_string(), "     5: - -> 0\n     6: - -> 3\n");
+    }
+
+    #[test]
+    fn dump_annotates_code() {
+        let mut computer = Intcode::load("3,9,8,9,10,9,4,9,99,-1,8", vec![8]);
+        computer.execute();
+
+        assert_eq!(
+            dump(&computer, 0..100),
+            indoc!("
+                0000:  3  9  8  9 10  9  4  9  |cccccccc|
+                0008: 99  1  8                 |c..|
+            ")
+            .trim_start()
+        );
+
+        assert_eq!(dump(&computer, 4..7), "0004: 10  9  4                 |ccc|\n");
+    }
+}
@@ -0,0 +1,87 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::{isa, Cycle, LimitExceeded, Violation};
+
+/// An error that stopped a running program.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExecutionError {
+    Limit(LimitExceeded),
+
+    /// The instruction at `addr` has an opcode that is neither standard nor registered as an
+    /// extension.
+    UnknownOpcode { instr: i32, addr: usize },
+
+    /// The input instruction at `addr` ran while the input queue was empty.
+    MissingInput { addr: usize },
+
+    /// A parameter of the instruction at `addr` has a mode that doesn't exist, or is written to
+    /// in immediate mode. `param` is the index of the parameter, starting from 0.
+    InvalidMode { instr: i32, addr: usize, param: usize },
+
+    /// A parameter of the instruction at `addr` points to a negative address.
+    NegativeAddress { addr: usize, target: i32 },
+
+    /// The jump at `addr` has a negative target.
+    InvalidJumpTarget { addr: usize, target: i32 },
+
+    /// The result of the instruction at `addr`, or an address or relative base it computes,
+    /// doesn't fit in a cell.
+    Overflow { addr: usize },
+
+    /// The program entered an infinite loop. Only reported when cycle detection is enabled.
+    Cycle(Cycle),
+
+    /// An instruction violated a protected region whose behavior is to trap. Only reported when
+    /// memory protection is enabled.
+    Protection(Violation),
+}
+
+impl From<LimitExceeded> for ExecutionError {
+    fn from(limit: LimitExceeded) -> Self {
+        ExecutionError::Limit(limit)
+    }
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExecutionError::Limit(limit) => write!(f, "{}", limit),
+            ExecutionError::UnknownOpcode { instr, addr } => {
+                let opcode = isa::decode(*instr).opcode;
+                write!(f, "unknown opcode {} in instruction {} at address {}", opcode, instr, addr)
+            }
+            ExecutionError::MissingInput { addr } => {
+                write!(f, "no input left for the instruction at address {}", addr)
+            }
+            ExecutionError::InvalidMode { instr, addr, param } => {
+                write!(f, "invalid mode for parameter {} of instruction {} at address {}", param, instr, addr)
+            }
+            ExecutionError::NegativeAddress { addr, target } => {
+                write!(f, "the instruction at address {} accessed negative address {}", addr, target)
+            }
+            ExecutionError::InvalidJumpTarget { addr, target } => {
+                write!(f, "the jump at address {} has negative target {}", addr, target)
+            }
+            ExecutionError::Overflow { addr } => write!(f, "the instruction at address {} overflowed", addr),
+            ExecutionError::Cycle(cycle) => write!(f, "{}", cycle),
+            ExecutionError::Protection(violation) => write!(f, "{}", violation),
+        }
+    }
+}
+
+impl Error for ExecutionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExecutionError::Limit(limit) => Some(limit),
+            ExecutionError::Protection(violation) => Some(violation),
+            ExecutionError::UnknownOpcode { .. }
+            | ExecutionError::MissingInput { .. }
+            | ExecutionError::InvalidMode { .. }
+            | ExecutionError::NegativeAddress { .. }
+            | ExecutionError::InvalidJumpTarget { .. }
+            | ExecutionError::Overflow { .. }
+            | ExecutionError::Cycle(_) => None,
+        }
+    }
+}
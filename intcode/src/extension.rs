@@ -0,0 +1,104 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::isa;
+
+/// How an instruction uses one of its parameters.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Param {
+    /// The parameter is read according to its mode.
+    Read,
+
+    /// The parameter is the address of a cell that the instruction writes to.
+    Write,
+}
+
+/// What the machine should do after a custom instruction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Effect {
+    /// Continue with the next instruction.
+    Continue,
+
+    /// Continue at the given address.
+    Jump(usize),
+
+    /// Stop the machine with an exit code.
+    Halt(i32),
+}
+
+type Handler = dyn FnMut(&[i32], &mut [i32]) -> Effect;
+
+/// A custom instruction.
+pub(crate) struct CustomOpcode {
+    pub(crate) params: Vec<Param>,
+    handler: RefCell<Box<Handler>>,
+}
+
+impl CustomOpcode {
+    /// Calls the handler with the values of the read parameters. The handler stores the values
+    /// for the write parameters, in order, into `results`.
+    pub(crate) fn call(&self, args: &[i32], results: &mut [i32]) -> Effect {
+        (self.handler.borrow_mut())(args, results)
+    }
+}
+
+/// A registry of instructions that extend the standard instruction set.
+#[derive(Default, Clone)]
+pub struct Extensions {
+    opcodes: HashMap<i32, Rc<CustomOpcode>>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Extensions::default()
+    }
+
+    /// Registers a handler for `opcode`, which takes parameters used as described by `params`.
+    ///
+    /// The handler is called with the values of the read parameters and a slice to store the
+    /// values for the write parameters in. Parameter modes work just like they do for the
+    /// standard instructions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the opcode is not two digits, is part of the standard instruction set, or has
+    /// already been registered.
+    pub fn register<F>(&mut self, opcode: i32, params: &[Param], handler: F)
+    where
+        F: FnMut(&[i32], &mut [i32]) -> Effect + 'static,
+    {
+        assert!(0 < opcode && opcode < 100, "opcode {} is not two digits", opcode);
+        assert!(isa::lookup(opcode).is_none(), "opcode {} is a standard instruction", opcode);
+        assert!(!self.opcodes.contains_key(&opcode), "opcode {} is already registered", opcode);
+
+        let custom = CustomOpcode {
+            params: params.to_vec(),
+            handler: RefCell::new(Box::new(handler)),
+        };
+
+        self.opcodes.insert(opcode, Rc::new(custom));
+    }
+
+    /// How the instruction registered for `opcode` uses its parameters, like
+    /// [`isa::Instruction::params`] does for standard instructions.
+    pub fn params(&self, opcode: i32) -> Option<&[Param]> {
+        self.opcodes.get(&opcode).map(|custom| &custom.params[..])
+    }
+
+    pub(crate) fn get(&self, opcode: i32) -> Option<Rc<CustomOpcode>> {
+        self.opcodes.get(&opcode).cloned()
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut opcodes = self.opcodes.iter().collect::<Vec<_>>();
+        opcodes.sort_by_key(|&(&opcode, _)| opcode);
+
+        f.debug_map()
+            .entries(opcodes.into_iter().map(|(opcode, custom)| (opcode, &custom.params)))
+            .finish()
+    }
+}
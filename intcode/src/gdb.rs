@@ -0,0 +1,748 @@
+//! A GDB remote serial protocol server that exposes an Intcode computer as a debugging target.
+//!
+//! The target has two 64-bit registers: `pc` (register 0) and the relative base `rb` (register
+//! 1). Memory is byte-addressed, with cell `n` stored little-endian as the four bytes starting at
+//! byte address `4 * n`. The program counter and breakpoints use byte addresses too.
+//!
+//! Supported packets:
+//!
+//! - `?`, `g`, `G`, `p` and `P` for the stop reason and registers.
+//! - `m` and `M` to read and write memory. Memory past the end of the program reads as zero, and
+//!   writes a little past the end grow it. A packet accesses at most half of the packet size.
+//! - `Z0` and `z0` to insert and remove software breakpoints.
+//! - `s` and `c`, optionally with an address to resume at, to step and continue. A continue can
+//!   be interrupted with `^C`.
+//! - `qRcmd` for the monitor commands `input <values...>`, which queues input, and `output`,
+//!   which takes the output produced so far.
+//! - `qSupported`, `qXfer:features:read` for the target description, `qAttached`, `H`, `D` and `k`.
+//!
+//! Anything else gets an empty reply, meaning that it's not supported.
+//!
+//! Stop replies use signals: `S05` (SIGTRAP) after a step or at a breakpoint, `S02` (SIGINT) when
+//! interrupted, and `S15` (SIGTTIN) when the program needs input. Errors stop the program before
+//! the instruction runs, with `S04` (SIGILL) for an instruction that can't be decoded, `S0b`
+//! (SIGSEGV) for a bad address or jump target, `S08` (SIGFPE) for an overflow, and `S18`
+//! (SIGXCPU) for a limit or an infinite loop. A halted program replies with `W` and its exit code.
+
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use crate::{ExecutionError, Intcode, Limits, Status};
+
+/// The number of bytes in a cell.
+const CELL_SIZE: usize = 4;
+
+/// How many instructions a continue runs between checks for an interrupt.
+const INTERRUPT_CHECK_INTERVAL: u64 = 10_000;
+
+/// The largest packet that the debugger may send or receive, advertised in `qSupported`.
+const PACKET_SIZE: usize = 0x4000;
+
+/// The most bytes that `m` and `M` can access at once. Each byte is two hex digits.
+const MAX_TRANSFER: usize = PACKET_SIZE / 2;
+
+/// How many cells past the end of memory a write can grow it by.
+const MAX_GROWTH: usize = 0x10000;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.intcode.core">
+    <reg name="pc" bitsize="64" type="code_ptr" regnum="0"/>
+    <reg name="rb" bitsize="64" type="int64" regnum="1"/>
+  </feature>
+</target>
+"#;
+
+const SIGINT: u8 = 0x02;
+const SIGILL: u8 = 0x04;
+const SIGTRAP: u8 = 0x05;
+const SIGFPE: u8 = 0x08;
+const SIGSEGV: u8 = 0x0b;
+const SIGTTIN: u8 = 0x15;
+const SIGXCPU: u8 = 0x18;
+
+/// A packet received from the debugger.
+#[derive(Debug, PartialEq, Eq)]
+enum Packet {
+    Command(String),
+
+    /// The debugger sent `^C` outside of a packet.
+    Interrupt,
+}
+
+/// The framing layer of the protocol: packets, checksums and acknowledgements.
+struct Connection {
+    stream: TcpStream,
+}
+
+impl Connection {
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+
+        match self.stream.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+
+    /// Reads the next packet, acknowledging it. Returns `None` once the debugger disconnects.
+    fn read_packet(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            match self.read_byte()? {
+                None => return Ok(None),
+                Some(0x03) => return Ok(Some(Packet::Interrupt)),
+                Some(b'$') => (),
+                // Acknowledgements of our packets, and anything between packets.
+                Some(_) => continue,
+            }
+
+            let mut data = vec![];
+            loop {
+                match self.read_byte()? {
+                    None => return Ok(None),
+                    Some(b'#') => break,
+                    Some(byte) => data.push(byte),
+                }
+            }
+
+            let mut checksum = [0; 2];
+            self.stream.read_exact(&mut checksum)?;
+
+            let valid = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|checksum| u8::from_str_radix(checksum, 16).ok())
+                .is_some_and(|checksum| checksum == Self::checksum(&data));
+
+            if valid {
+                self.stream.write_all(b"+")?;
+                return Ok(Some(Packet::Command(String::from_utf8_lossy(&data).into_owned())));
+            }
+
+            self.stream.write_all(b"-")?;
+        }
+    }
+
+    /// Sends a packet, resending it until the debugger acknowledges it.
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        let packet = format!("${}#{:02x}", data, Self::checksum(data.as_bytes()));
+
+        loop {
+            self.stream.write_all(packet.as_bytes())?;
+
+            match self.read_byte()? {
+                Some(b'+') | None => return Ok(()),
+                _ => continue,
+            }
+        }
+    }
+
+    /// Checks for a `^C` without blocking.
+    fn interrupted(&mut self) -> io::Result<bool> {
+        self.stream.set_nonblocking(true)?;
+
+        let mut byte = [0];
+        let result = match self.stream.peek(&mut byte) {
+            Ok(1) if byte[0] == 0x03 => self.stream.read(&mut byte).map(|_| true),
+            Ok(_) => Ok(false),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        };
+
+        self.stream.set_nonblocking(false)?;
+        result
+    }
+
+    fn checksum(data: &[u8]) -> u8 {
+        data.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+    }
+}
+
+/// Serves an Intcode computer to a debugger.
+pub struct GdbStub {
+    computer: Intcode,
+
+    /// The cell addresses of the breakpoints.
+    breakpoints: BTreeSet<usize>,
+
+    /// Set once the program has halted.
+    halted: bool,
+}
+
+impl GdbStub {
+    pub fn new(computer: Intcode) -> Self {
+        GdbStub { computer, breakpoints: BTreeSet::new(), halted: false }
+    }
+
+    pub fn computer(&self) -> &Intcode {
+        &self.computer
+    }
+
+    pub fn into_computer(self) -> Intcode {
+        self.computer
+    }
+
+    /// Waits for a debugger to connect to `addr` and serves it until it disconnects.
+    pub fn listen<A: ToSocketAddrs>(&mut self, addr: A) -> io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        let (stream, _) = listener.accept()?;
+        self.serve(stream)
+    }
+
+    /// Serves a connected debugger until it detaches, kills the target or disconnects.
+    pub fn serve(&mut self, stream: TcpStream) -> io::Result<()> {
+        stream.set_nodelay(true)?;
+        let mut conn = Connection { stream };
+
+        while let Some(packet) = conn.read_packet()? {
+            let command = match packet {
+                Packet::Command(command) => command,
+                Packet::Interrupt => continue,
+            };
+
+            match &command[..] {
+                "D" => {
+                    conn.send("OK")?;
+                    return Ok(());
+                }
+                "k" => return Ok(()),
+                _ => (),
+            }
+
+            let reply = match self.handle(&command, &mut conn)? {
+                Some(reply) => reply,
+                None => String::from("E01"),
+            };
+
+            conn.send(&reply)?;
+        }
+
+        Ok(())
+    }
+
+    /// Handles a command. Returns `None` if the command is malformed.
+    fn handle(&mut self, command: &str, conn: &mut Connection) -> io::Result<Option<String>> {
+        let (kind, args) = command.split_at(command.chars().next().map_or(0, char::len_utf8));
+
+        let reply = match kind {
+            "?" => Some(self.stop_reply(SIGTRAP)),
+            "g" => Some(format!("{}{}", encode_register(self.register(0)), encode_register(self.register(1)))),
+            "G" => (|| {
+                let pc = decode_register(args.get(..16)?)?;
+                let rb = decode_register(args.get(16..32)?)?;
+                self.set_register(0, pc)?;
+                self.set_register(1, rb)?;
+                Some(String::from("OK"))
+            })(),
+            "p" => usize::from_str_radix(args, 16).ok().filter(|&n| n < 2).map(|n| encode_register(self.register(n))),
+            "P" => (|| {
+                let (n, value) = split_pair(args, '=')?;
+                self.set_register(usize::from_str_radix(n, 16).ok()?, decode_register(value)?)?;
+                Some(String::from("OK"))
+            })(),
+            "m" => (|| {
+                let (addr, len) = split_pair(args, ',')?;
+                let bytes = self.read_bytes(parse_hex(addr)?, parse_hex(len)?)?;
+                Some(encode_hex(&bytes))
+            })(),
+            "M" => (|| {
+                let (range, data) = split_pair(args, ':')?;
+                let (addr, len) = split_pair(range, ',')?;
+                let bytes = decode_hex(data)?;
+
+                if bytes.len() != parse_hex(len)? {
+                    return None;
+                }
+
+                self.write_bytes(parse_hex(addr)?, &bytes)?;
+                Some(String::from("OK"))
+            })(),
+            "Z" | "z" => match args.split(',').collect::<Vec<_>>()[..] {
+                ["0", addr, _] => parse_hex(addr).map(|addr| {
+                    if kind == "Z" {
+                        self.breakpoints.insert(addr / CELL_SIZE);
+                    } else {
+                        self.breakpoints.remove(&(addr / CELL_SIZE));
+                    }
+
+                    String::from("OK")
+                }),
+                _ => Some(String::new()),
+            },
+            "s" | "c" => {
+                if !args.is_empty() {
+                    match parse_hex(args) {
+                        Some(addr) => self.computer.set_pc(addr / CELL_SIZE),
+                        None => return Ok(None),
+                    }
+
+                    self.halted = false;
+                }
+
+                let signal = if kind == "s" { self.step() } else { self.resume(conn)? };
+                Some(self.stop_reply(signal))
+            }
+            "H" => Some(String::from("OK")),
+            "q" => Some(self.query(args)),
+            _ => Some(String::new()),
+        };
+
+        Ok(reply)
+    }
+
+    fn query(&mut self, query: &str) -> String {
+        if query.starts_with("Supported") {
+            return format!("PacketSize={:x};qXfer:features:read+", PACKET_SIZE);
+        }
+
+        if query == "Attached" {
+            return String::from("1");
+        }
+
+        if let Some(range) = query.strip_prefix("Xfer:features:read:target.xml:") {
+            let (offset, len) = match split_pair(range, ',').and_then(|(o, l)| Some((parse_hex(o)?, parse_hex(l)?))) {
+                Some(range) => range,
+                None => return String::from("E01"),
+            };
+
+            let rest = TARGET_XML.get(offset.min(TARGET_XML.len())..).unwrap_or("");
+            return if rest.len() > len { format!("m{}", &rest[..len]) } else { format!("l{}", rest) };
+        }
+
+        if let Some(command) = query.strip_prefix("Rcmd,") {
+            return match decode_hex(command).and_then(|bytes| String::from_utf8(bytes).ok()) {
+                Some(command) => self.monitor(&command),
+                None => String::from("E01"),
+            };
+        }
+
+        String::new()
+    }
+
+    /// Runs a monitor command.
+    fn monitor(&mut self, command: &str) -> String {
+        let mut words = command.split_whitespace();
+
+        match words.next() {
+            Some("input") => {
+                let values = words.map(str::parse).collect::<Result<Vec<i32>, _>>();
+
+                match values {
+                    Ok(values) => {
+                        for value in values {
+                            self.computer.push_input(value);
+                        }
+
+                        String::from("OK")
+                    }
+                    Err(_) => String::from("E01"),
+                }
+            }
+            Some("output") => {
+                let text = self.computer.take_output().iter().map(|value| format!("{}\n", value)).collect::<String>();
+                encode_hex(text.as_bytes())
+            }
+            _ => encode_hex(b"unknown monitor command\n"),
+        }
+    }
+
+    fn stop_reply(&self, signal: u8) -> String {
+        if self.halted {
+            format!("W{:02x}", self.computer.exit_code().unwrap_or(0) as u8)
+        } else {
+            format!("S{:02x}", signal)
+        }
+    }
+
+    /// Executes one instruction and returns the signal to report.
+    fn step(&mut self) -> u8 {
+        if self.halted {
+            return SIGTRAP;
+        }
+
+        match self.computer.step(&Limits::default()) {
+            Ok(None) => SIGTRAP,
+            Ok(Some(Status::NeedsInput)) => SIGTTIN,
+            Ok(Some(Status::Halted)) => {
+                self.halted = true;
+                SIGTRAP
+            }
+            Err(e) => signal(e),
+        }
+    }
+
+    /// Runs until a breakpoint is reached, the program stops, or the debugger interrupts.
+    ///
+    /// A breakpoint at the instruction that the run starts at doesn't stop it, so continuing from
+    /// a breakpoint moves on.
+    fn resume(&mut self, conn: &mut Connection) -> io::Result<u8> {
+        let mut steps = 0;
+
+        loop {
+            let signal = self.step();
+            if signal != SIGTRAP || self.halted {
+                return Ok(signal);
+            }
+
+            steps += 1;
+
+            if self.breakpoints.contains(&self.computer.pc()) {
+                return Ok(SIGTRAP);
+            }
+
+            if steps % INTERRUPT_CHECK_INTERVAL == 0 && conn.interrupted()? {
+                return Ok(SIGINT);
+            }
+        }
+    }
+
+    fn register(&self, n: usize) -> i64 {
+        match n {
+            0 => (self.computer.pc() * CELL_SIZE) as i64,
+            _ => i64::from(self.computer.relative_base()),
+        }
+    }
+
+    fn set_register(&mut self, n: usize, value: i64) -> Option<()> {
+        match n {
+            0 => self.computer.set_pc(usize::try_from(value).ok()? / CELL_SIZE),
+            1 => self.computer.set_relative_base(i32::try_from(value).ok()?),
+            _ => return None,
+        }
+
+        Some(())
+    }
+
+    /// Reads `len` bytes starting at `addr`. Returns `None` if the range is too long or wraps
+    /// around.
+    fn read_bytes(&self, addr: usize, len: usize) -> Option<Vec<u8>> {
+        if len > MAX_TRANSFER {
+            return None;
+        }
+
+        let end = addr.checked_add(len)?;
+        let bytes = (addr..end)
+            .map(|byte| self.computer.mem().get(byte / CELL_SIZE).to_le_bytes()[byte % CELL_SIZE])
+            .collect();
+
+        Some(bytes)
+    }
+
+    /// Writes `bytes` starting at `addr`. Returns `None`, without writing anything, if there are
+    /// too many bytes or they would grow memory too far.
+    fn write_bytes(&mut self, addr: usize, bytes: &[u8]) -> Option<()> {
+        if bytes.len() > MAX_TRANSFER {
+            return None;
+        }
+
+        let end = addr.checked_add(bytes.len())?;
+        if end / CELL_SIZE > self.computer.mem().len() + MAX_GROWTH {
+            return None;
+        }
+
+        for (byte, &value) in (addr..end).zip(bytes) {
+            let cell = byte / CELL_SIZE;
+
+            let mut cell_bytes = self.computer.mem().get(cell).to_le_bytes();
+            cell_bytes[byte % CELL_SIZE] = value;
+            self.computer.mem_mut().set(cell, i32::from_le_bytes(cell_bytes));
+        }
+
+        Some(())
+    }
+}
+
+/// The signal that reports an error.
+fn signal(error: ExecutionError) -> u8 {
+    match error {
+        ExecutionError::UnknownOpcode { .. } | ExecutionError::InvalidMode { .. } => SIGILL,
+        ExecutionError::NegativeAddress { .. }
+        | ExecutionError::InvalidJumpTarget { .. }
+        | ExecutionError::Protection(_) => SIGSEGV,
+        ExecutionError::Overflow { .. } => SIGFPE,
+        ExecutionError::MissingInput { .. } => SIGTTIN,
+        ExecutionError::Limit(_) | ExecutionError::Cycle(_) => SIGXCPU,
+    }
+}
+
+fn split_pair(s: &str, separator: char) -> Option<(&str, &str)> {
+    let mut parts = s.splitn(2, separator);
+    Some((parts.next()?, parts.next()?))
+}
+
+fn parse_hex(s: &str) -> Option<usize> {
+    usize::from_str_radix(s, 16).ok()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// Registers are sent as little-endian bytes.
+fn encode_register(value: i64) -> String {
+    encode_hex(&value.to_le_bytes())
+}
+
+fn decode_register(s: &str) -> Option<i64> {
+    let bytes = decode_hex(s)?;
+    Some(i64::from_le_bytes(<[u8; 8]>::try_from(&bytes[..]).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread::{self, JoinHandle};
+
+    use crate::Intcode;
+
+    use super::{encode_hex, Connection, GdbStub};
+
+    /// A scripted debugger.
+    struct Client {
+        stream: TcpStream,
+    }
+
+    impl Client {
+        fn send_raw(&mut self, data: &[u8]) {
+            self.stream.write_all(data).unwrap();
+        }
+
+        fn read_byte(&mut self) -> u8 {
+            let mut byte = [0];
+            self.stream.read_exact(&mut byte).unwrap();
+            byte[0]
+        }
+
+        /// Reads a reply packet and acknowledges it.
+        fn reply(&mut self) -> String {
+            assert_eq!(self.read_byte(), b'$');
+
+            let mut data = vec![];
+            loop {
+                match self.read_byte() {
+                    b'#' => break,
+                    byte => data.push(byte),
+                }
+            }
+
+            let checksum = [self.read_byte(), self.read_byte()];
+            let checksum = u8::from_str_radix(std::str::from_utf8(&checksum).unwrap(), 16).unwrap();
+            assert_eq!(checksum, Connection::checksum(&data));
+
+            self.send_raw(b"+");
+            String::from_utf8(data).unwrap()
+        }
+
+        fn send(&mut self, command: &str) {
+            let packet = format!("${}#{:02x}", command, Connection::checksum(command.as_bytes()));
+            self.send_raw(packet.as_bytes());
+            assert_eq!(self.read_byte(), b'+');
+        }
+
+        fn command(&mut self, command: &str) -> String {
+            self.send(command);
+            self.reply()
+        }
+
+        fn monitor(&mut self, command: &str) -> String {
+            self.command(&format!("qRcmd,{}", encode_hex(command.as_bytes())))
+        }
+    }
+
+    /// What's left of the computer once the debugger detaches.
+    struct Remains {
+        mem: Vec<i32>,
+        relative_base: i32,
+        output: Vec<i32>,
+    }
+
+    /// Serves a program on a loopback port, and connects to it.
+    fn connect(program: &'static str) -> (Client, JoinHandle<Remains>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let mut stub = GdbStub::new(Intcode::load(program, vec![]));
+            let (stream, _) = listener.accept().unwrap();
+            stub.serve(stream).unwrap();
+
+            let computer = stub.into_computer();
+            Remains {
+                mem: computer.mem().to_vec(),
+                relative_base: computer.relative_base(),
+                output: computer.output().to_vec(),
+            }
+        });
+
+        let client = Client { stream: TcpStream::connect(addr).unwrap() };
+        (client, server)
+    }
+
+    fn unhex(s: &str) -> String {
+        String::from_utf8(super::decode_hex(s).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn registers_and_memory() {
+        let (mut client, server) = connect("109,-3,1101,2,3,9,99,0,0,0");
+
+        assert_eq!(client.command("qSupported:multiprocess+"), "PacketSize=4000;qXfer:features:read+");
+        assert_eq!(client.command("?"), "S05");
+        assert_eq!(client.command("g"), "00000000000000000000000000000000");
+
+        // 109 and -3.
+        assert_eq!(client.command("m0,8"), "6d000000fdffffff");
+        assert_eq!(client.command("m2,3"), "0000fd");
+        assert_eq!(client.command("m1000,4"), "00000000");
+
+        assert_eq!(client.command("s"), "S05");
+        assert_eq!(client.command("g"), "0800000000000000fdffffffffffffff");
+        assert_eq!(client.command("p1"), "fdffffffffffffff");
+
+        assert_eq!(client.command("M1c,4:2a000000"), "OK");
+        assert_eq!(client.command("m1c,4"), "2a000000");
+        assert_eq!(client.command("M29,1:01"), "OK");
+
+        assert_eq!(client.command("P1=0500000000000000"), "OK");
+        assert_eq!(client.command("p1"), "0500000000000000");
+        assert_eq!(client.command("p2"), "E01");
+
+        assert_eq!(client.command("D"), "OK");
+
+        let remains = server.join().unwrap();
+        assert_eq!(remains.mem[7], 42);
+        assert_eq!(remains.mem[10], 256);
+        assert_eq!(remains.relative_base, 5);
+    }
+
+    #[test]
+    fn breakpoints() {
+        // Outputs 1, 2 and 3.
+        let (mut client, server) = connect("104,1,104,2,104,3,99");
+
+        assert_eq!(client.command("Z0,8,4"), "OK");
+        assert_eq!(client.command("Z0,10,4"), "OK");
+
+        assert_eq!(client.command("c"), "S05");
+        assert_eq!(client.command("p0"), "0800000000000000");
+        assert_eq!(unhex(&client.monitor("output")), "1\n");
+
+        assert_eq!(client.command("z0,10,4"), "OK");
+        assert_eq!(client.command("c"), "W00");
+        assert_eq!(unhex(&client.monitor("output")), "2\n3\n");
+
+        // Resuming at an address runs the program again from there.
+        assert_eq!(client.command("c10"), "W00");
+        assert_eq!(unhex(&client.monitor("output")), "3\n");
+
+        assert_eq!(client.command("D"), "OK");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn single_step() {
+        let (mut client, server) = connect("104,1,104,2,99");
+
+        assert_eq!(client.command("s"), "S05");
+        assert_eq!(client.command("p0"), "0800000000000000");
+        assert_eq!(client.command("s"), "S05");
+        assert_eq!(client.command("s"), "W00");
+
+        client.send("k");
+        assert_eq!(server.join().unwrap().output, vec![1, 2]);
+    }
+
+    #[test]
+    fn input() {
+        let (mut client, server) = connect("3,0,4,0,99");
+
+        assert_eq!(client.command("c"), "S15");
+        assert_eq!(client.command("p0"), "0000000000000000");
+
+        assert_eq!(client.monitor("input 42"), "OK");
+        assert_eq!(client.command("c"), "W00");
+        assert_eq!(unhex(&client.monitor("output")), "42\n");
+
+        assert_eq!(client.command("D"), "OK");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn interrupt() {
+        let (mut client, server) = connect("1105,1,0");
+
+        client.send("c");
+        client.send_raw(&[0x03]);
+        assert_eq!(client.reply(), "S02");
+
+        assert_eq!(client.command("D"), "OK");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn errors() {
+        let (mut client, server) = connect("42");
+
+        assert_eq!(client.command("c"), "S04");
+        assert_eq!(client.command("m0"), "E01");
+        assert_eq!(client.command("vMustReplyEmpty"), "");
+
+        // Ranges that wrap around, are too long or grow memory too far.
+        assert_eq!(client.command("mffffffffffffffff,8"), "E01");
+        assert_eq!(client.command("m0,7fffffff"), "E01");
+        assert_eq!(client.command("m0,2001"), "E01");
+        assert_eq!(client.command("m0,2000").len(), 0x4000);
+        assert_eq!(client.command("Mffffffffffffffff,2:0000"), "E01");
+        assert_eq!(client.command("M4000000000000000,1:00"), "E01");
+        assert_eq!(client.command("M40008,4:01000000"), "E01");
+        assert_eq!(client.command("M10,4:01000000"), "OK");
+
+        // Instructions that stop the machine: a negative relative address, mode 3, an immediate
+        // write, a negative jump target and an overflow.
+        assert_eq!(client.command("M0,10:cc000000000000006300000000000000"), "OK");
+        assert_eq!(client.command("P1=ffffffffffffffff"), "OK");
+        assert_eq!(client.command("s0"), "S0b");
+        assert_eq!(client.command("p0"), "0000000000000000");
+
+        assert_eq!(client.command("M0,4:2d010000"), "OK");
+        assert_eq!(client.command("s0"), "S04");
+        assert_eq!(client.command("M0,10:5d2b0000010000000100000000000000"), "OK");
+        assert_eq!(client.command("c0"), "S04");
+        assert_eq!(client.command("M0,c:5104000001000000ffffffff"), "OK");
+        assert_eq!(client.command("s0"), "S0b");
+        assert_eq!(client.command("M0,10:4d040000ffffff7f0100000000000000"), "OK");
+        assert_eq!(client.command("c0"), "S08");
+
+        // A packet with a bad checksum is rejected.
+        client.send_raw(b"$g#00");
+        assert_eq!(client.read_byte(), b'-');
+
+        assert_eq!(client.command("D"), "OK");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn target_description() {
+        let (mut client, server) = connect("99");
+
+        let first = client.command("qXfer:features:read:target.xml:0,20");
+        assert!(first.starts_with("m<?xml"), "{}", first);
+
+        let rest = client.command("qXfer:features:read:target.xml:1f,1000");
+        assert!(rest.starts_with('l') && rest.ends_with("</target>\n"), "{}", rest);
+
+        assert_eq!(client.command("D"), "OK");
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,524 @@
+//! Golden tests: files that describe how a program should behave for some inputs.
+//!
+//! A golden file names a program and lists cases, each with the input to run the program with
+//! and what should happen:
+//!
+//! ```text
+//! # Outputs 1 if the input is equal to 8, and 0 otherwise.
+//! program = 3,9,8,9,10,9,4,9,99,-1,8
+//!
+//! [equal]
+//! input = 8
+//! output = 1
+//! memory[9] = 1
+//!
+//! [not equal]
+//! input = 7
+//! output = 0
+//!
+//! [no input]
+//! error = no input left for the instruction at address 0
+//! ```
+//!
+//! The program is either given inline with `program`, or read from a file with `program-file`,
+//! which is relative to the golden file. `max-instructions` limits how long each case may run,
+//! and defaults to a million instructions.
+//!
+//! A case starts with its name in brackets. `input` is the input queue, which is empty if it's
+//! left out. Only the results that are listed are checked: `output`, the value of a cell with
+//! `memory[address]`, and `error`, which is the message of the error that stops the program. A
+//! case that doesn't list an error expects the program to halt.
+//!
+//! Golden files have the extension `.golden`. [`run_dir`] runs all the files in a directory.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{program, Intcode, Limits};
+
+/// The extension of golden files.
+pub const EXTENSION: &str = "golden";
+
+const DEFAULT_MAX_INSTRUCTIONS: u64 = 1_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A line that is neither a case name nor a `key = value` pair.
+    Syntax,
+    UnknownKey(String),
+    DuplicateKey(String),
+
+    /// A key that belongs to a case appears before the first case, or the other way around.
+    MisplacedKey(String),
+    InvalidValue(String),
+    MissingProgram,
+    Program(program::ParseError),
+
+    /// The golden file or its program file couldn't be read.
+    Unreadable { path: PathBuf, message: String },
+}
+
+/// A problem with a golden file, and the line it's on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecError {
+    pub line: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+
+        match &self.kind {
+            ErrorKind::Syntax => write!(f, "expected `[case name]` or `key = value`"),
+            ErrorKind::UnknownKey(key) => write!(f, "unknown key `{}`", key),
+            ErrorKind::DuplicateKey(key) => write!(f, "`{}` is already set", key),
+            ErrorKind::MisplacedKey(key) => write!(f, "`{}` can't be set here", key),
+            ErrorKind::InvalidValue(value) => write!(f, "invalid value: {:?}", value),
+            ErrorKind::MissingProgram => write!(f, "there is no `program` or `program-file`"),
+            ErrorKind::Program(e) => write!(f, "invalid program: {}", e),
+            ErrorKind::Unreadable { path, message } => {
+                write!(f, "couldn't read {}: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl Error for SpecError {}
+
+/// A case of a golden file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Case {
+    pub name: String,
+    pub input: Vec<i32>,
+    pub output: Option<Vec<i32>>,
+
+    /// Addresses and their expected values.
+    pub memory: Vec<(usize, i32)>,
+
+    /// The expected error message.
+    pub error: Option<String>,
+}
+
+/// A parsed golden file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spec {
+    pub program: Vec<i32>,
+    pub max_instructions: u64,
+    pub cases: Vec<Case>,
+}
+
+impl Spec {
+    /// Reads and parses a golden file.
+    pub fn load(path: &Path) -> Result<Spec, SpecError> {
+        let text = fs::read_to_string(path).map_err(|e| SpecError {
+            line: 0,
+            kind: ErrorKind::Unreadable { path: path.to_owned(), message: e.to_string() },
+        })?;
+
+        Spec::parse(&text, path.parent().unwrap_or_else(|| Path::new(".")))
+    }
+
+    /// Parses a golden file. Program files are looked up relative to `dir`.
+    pub fn parse(text: &str, dir: &Path) -> Result<Spec, SpecError> {
+        let mut program = None;
+        let mut max_instructions = None;
+        let mut cases: Vec<Case> = vec![];
+
+        for (line, text) in (1..).zip(text.lines()) {
+            let error = |kind| SpecError { line, kind };
+
+            let text = text.split('#').next().unwrap().trim();
+            if text.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = text.strip_prefix('[').and_then(|name| name.strip_suffix(']')) {
+                cases.push(Case { name: name.trim().to_owned(), ..Case::default() });
+                continue;
+            }
+
+            let (key, value) = match text.find('=') {
+                Some(i) => (text[..i].trim(), text[i + 1..].trim()),
+                None => return Err(error(ErrorKind::Syntax)),
+            };
+
+            let invalid = || error(ErrorKind::InvalidValue(value.to_owned()));
+            let duplicate = || error(ErrorKind::DuplicateKey(key.to_owned()));
+            let misplaced = || error(ErrorKind::MisplacedKey(key.to_owned()));
+
+            match (key, cases.last_mut()) {
+                ("program", None) | ("program-file", None) if program.is_some() => return Err(duplicate()),
+                ("program", None) => {
+                    program = Some(program::parse(value).map_err(|e| error(ErrorKind::Program(e)))?);
+                }
+                ("program-file", None) => {
+                    let path = dir.join(value);
+                    let text = fs::read_to_string(&path).map_err(|e| {
+                        error(ErrorKind::Unreadable { path: path.clone(), message: e.to_string() })
+                    })?;
+                    program = Some(program::parse(&text).map_err(|e| error(ErrorKind::Program(e)))?);
+                }
+                ("max-instructions", None) if max_instructions.is_some() => return Err(duplicate()),
+                ("max-instructions", None) => max_instructions = Some(value.parse().map_err(|_| invalid())?),
+                ("program", Some(_)) | ("program-file", Some(_)) | ("max-instructions", Some(_)) => {
+                    return Err(misplaced())
+                }
+                (_, None) if is_case_key(key) => return Err(misplaced()),
+                ("input", Some(case)) => case.input = parse_values(value).ok_or_else(invalid)?,
+                ("output", Some(case)) if case.output.is_some() => return Err(duplicate()),
+                ("output", Some(case)) => case.output = Some(parse_values(value).ok_or_else(invalid)?),
+                ("error", Some(case)) if case.error.is_some() => return Err(duplicate()),
+                ("error", Some(case)) => case.error = Some(value.to_owned()),
+                (key, Some(case)) if is_case_key(key) => {
+                    let addr = key["memory[".len()..key.len() - 1]
+                        .trim()
+                        .parse()
+                        .map_err(|_| error(ErrorKind::UnknownKey(key.to_owned())))?;
+
+                    if case.memory.iter().any(|&(a, _)| a == addr) {
+                        return Err(duplicate());
+                    }
+
+                    case.memory.push((addr, value.parse().map_err(|_| invalid())?));
+                }
+                (key, _) => return Err(error(ErrorKind::UnknownKey(key.to_owned()))),
+            }
+        }
+
+        let program = program.ok_or(SpecError { line: 1, kind: ErrorKind::MissingProgram })?;
+
+        Ok(Spec { program, max_instructions: max_instructions.unwrap_or(DEFAULT_MAX_INSTRUCTIONS), cases })
+    }
+
+    /// Runs every case.
+    pub fn run(&self) -> Vec<Outcome> {
+        self.cases.iter().map(|case| self.run_case(case)).collect()
+    }
+
+    fn run_case(&self, case: &Case) -> Outcome {
+        let limits = Limits { max_instructions: Some(self.max_instructions), ..Limits::default() };
+
+        let mut computer = Intcode::new(self.program.clone(), case.input.clone());
+        let result = computer.execute_with_limits(&limits);
+
+        let (output, error) = match result {
+            Ok(output) => (output, None),
+            Err(e) => (computer.take_output(), Some(e.to_string())),
+        };
+
+        let mut mismatches = vec![];
+
+        if let Some(expected) = &case.output {
+            if *expected != output {
+                mismatches.push(Mismatch::Output { expected: expected.clone(), actual: output });
+            }
+        }
+
+        for &(addr, expected) in &case.memory {
+            let actual = computer.mem().get(addr);
+            if actual != expected {
+                mismatches.push(Mismatch::Memory { addr, expected, actual });
+            }
+        }
+
+        if case.error != error {
+            mismatches.push(Mismatch::Error { expected: case.error.clone(), actual: error });
+        }
+
+        Outcome { case: case.name.clone(), mismatches }
+    }
+}
+
+/// Whether the key is set in a case.
+fn is_case_key(key: &str) -> bool {
+    matches!(key, "input" | "output" | "error") || key.starts_with("memory[") && key.ends_with(']')
+}
+
+/// Parses values separated by commas.
+fn parse_values(s: &str) -> Option<Vec<i32>> {
+    if s.is_empty() {
+        return Some(vec![]);
+    }
+
+    s.split(',').map(|value| value.trim().parse().ok()).collect()
+}
+
+/// A difference between what a case expected and what happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    Output { expected: Vec<i32>, actual: Vec<i32> },
+    Memory { addr: usize, expected: i32, actual: i32 },
+
+    /// `None` means that the program halted.
+    Error { expected: Option<String>, actual: Option<String> },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mismatch::Output { expected, actual } => {
+                let index = expected.iter().zip(actual).take_while(|(e, a)| e == a).count();
+
+                writeln!(f, "output differs at index {}", index)?;
+                writeln!(f, "  expected: {}", join(expected))?;
+                write!(f, "  actual:   {}", join(actual))
+            }
+            Mismatch::Memory { addr, expected, actual } => {
+                write!(f, "memory[{}] differs\n  expected: {}\n  actual:   {}", addr, expected, actual)
+            }
+            Mismatch::Error { expected, actual } => {
+                let describe = |error: &Option<String>| match error {
+                    Some(error) => format!("error: {}", error),
+                    None => String::from("halt"),
+                };
+
+                write!(f, "result differs\n  expected: {}\n  actual:   {}", describe(expected), describe(actual))
+            }
+        }
+    }
+}
+
+fn join(values: &[i32]) -> String {
+    if values.is_empty() {
+        return String::from("(nothing)");
+    }
+
+    values.iter().map(i32::to_string).collect::<Vec<_>>().join(",")
+}
+
+/// The result of a case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub case: String,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// The results of all the golden files in a directory.
+#[derive(Debug)]
+pub struct Report {
+    /// Each file, and the outcomes of its cases or why it couldn't be loaded.
+    pub files: Vec<(PathBuf, Result<Vec<Outcome>, SpecError>)>,
+}
+
+impl Report {
+    /// Whether every file was loaded and every case passed.
+    pub fn passed(&self) -> bool {
+        self.files.iter().all(|(_, result)| match result {
+            Ok(outcomes) => outcomes.iter().all(Outcome::passed),
+            Err(_) => false,
+        })
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (mut passed, mut failed) = (0, 0);
+
+        for (path, result) in &self.files {
+            let outcomes = match result {
+                Ok(outcomes) => outcomes,
+                Err(e) => {
+                    writeln!(f, "ERROR {}: {}", path.display(), e)?;
+                    failed += 1;
+                    continue;
+                }
+            };
+
+            for outcome in outcomes {
+                if outcome.passed() {
+                    writeln!(f, "PASS  {} [{}]", path.display(), outcome.case)?;
+                    passed += 1;
+                } else {
+                    writeln!(f, "FAIL  {} [{}]", path.display(), outcome.case)?;
+                    failed += 1;
+
+                    for mismatch in &outcome.mismatches {
+                        for line in mismatch.to_string().lines() {
+                            writeln!(f, "      {}", line)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        write!(f, "{} passed, {} failed", passed, failed)
+    }
+}
+
+/// Finds the golden files in a directory and its subdirectories, sorted by path.
+pub fn discover(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            files.extend(discover(&path)?);
+        } else if path.extension().is_some_and(|extension| extension == EXTENSION) {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+/// Runs all the golden files in a directory.
+pub fn run_dir(dir: &Path) -> io::Result<Report> {
+    let files = discover(dir)?
+        .into_iter()
+        .map(|path| {
+            let result = Spec::load(&path).map(|spec| spec.run());
+            (path, result)
+        })
+        .collect();
+
+    Ok(Report { files })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use indoc::indoc;
+
+    use super::{run_dir, Case, ErrorKind, Mismatch, Spec, SpecError};
+
+    fn parse(text: &str) -> Result<Spec, SpecError> {
+        Spec::parse(text, Path::new(env!("CARGO_MANIFEST_DIR")))
+    }
+
+    #[test]
+    fn golden_files() {
+        let report = run_dir(&Path::new(env!("CARGO_MANIFEST_DIR")).join("golden")).unwrap();
+
+        assert!(!report.files.is_empty());
+        assert!(report.passed(), "\n{}", report);
+    }
+
+    #[test]
+    fn parses_cases() {
+        let spec = parse(indoc!("
+            # A comment.
+            program = 3,0,4,0,99  # The echo program.
+            max-instructions = 100
+
+            [echo]
+            input = 1, -2
+            output = 1
+            memory[ 0 ] = 1
+
+            [empty]
+            error = no input left for the instruction at address 0
+        "))
+        .unwrap();
+
+        assert_eq!(spec.program, vec![3, 0, 4, 0, 99]);
+        assert_eq!(spec.max_instructions, 100);
+        assert_eq!(
+            spec.cases,
+            vec![
+                Case {
+                    name: String::from("echo"),
+                    input: vec![1, -2],
+                    output: Some(vec![1]),
+                    memory: vec![(0, 1)],
+                    error: None,
+                },
+                Case {
+                    name: String::from("empty"),
+                    error: Some(String::from("no input left for the instruction at address 0")),
+                    ..Case::default()
+                },
+            ]
+        );
+
+        assert!(spec.run().iter().all(|outcome| outcome.passed()));
+    }
+
+    #[test]
+    fn program_file() {
+        let spec = parse("program-file = ../inputs/day2.txt").unwrap();
+        assert_eq!(&spec.program[..4], &[1, 0, 0, 3]);
+    }
+
+    #[test]
+    fn reports_mismatches() {
+        let spec = parse(indoc!("
+            program = 104,1,104,2,1101,2,3,0,99
+
+            [wrong]
+            output = 1,3
+            memory[0] = 6
+            error = oops
+        "))
+        .unwrap();
+
+        let outcomes = spec.run();
+        assert_eq!(
+            outcomes[0].mismatches,
+            vec![
+                Mismatch::Output { expected: vec![1, 3], actual: vec![1, 2] },
+                Mismatch::Memory { addr: 0, expected: 6, actual: 5 },
+                Mismatch::Error { expected: Some(String::from("oops")), actual: None },
+            ]
+        );
+
+        assert_eq!(
+            outcomes[0].mismatches[0].to_string(),
+            "output differs at index 1\n  expected: 1,3\n  actual:   1,2"
+        );
+        assert_eq!(outcomes[0].mismatches[2].to_string(), "result differs\n  expected: error: oops\n  actual:   halt");
+    }
+
+    #[test]
+    fn limits_runaway_programs() {
+        let spec = parse("program = 1105,1,0\nmax-instructions = 10\n[loop]\n").unwrap();
+
+        assert_eq!(
+            spec.run()[0].mismatches,
+            vec![Mismatch::Error { expected: None, actual: Some(String::from("instruction limit exceeded")) }]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = |text| parse(text).unwrap_err();
+
+        assert_eq!(error("[case]\noutput = 1"), SpecError { line: 1, kind: ErrorKind::MissingProgram });
+        assert_eq!(error("program = 99\nnonsense"), SpecError { line: 2, kind: ErrorKind::Syntax });
+        assert_eq!(
+            error("program = 99\n[case]\ncolor = red"),
+            SpecError { line: 3, kind: ErrorKind::UnknownKey(String::from("color")) }
+        );
+        assert_eq!(
+            error("program = 99\n[case]\noutput = 1\noutput = 2"),
+            SpecError { line: 4, kind: ErrorKind::DuplicateKey(String::from("output")) }
+        );
+        assert_eq!(
+            error("input = 1"),
+            SpecError { line: 1, kind: ErrorKind::MisplacedKey(String::from("input")) }
+        );
+        assert_eq!(
+            error("program = 99\n[case]\nmemory[x] = 1"),
+            SpecError { line: 3, kind: ErrorKind::UnknownKey(String::from("memory[x]")) }
+        );
+        assert_eq!(
+            error("program = 99\n[case]\ninput = 1,a"),
+            SpecError { line: 3, kind: ErrorKind::InvalidValue(String::from("1,a")) }
+        );
+        assert_eq!(error("program = 1,,2").to_string(), "line 1: invalid program: cell 1 is empty");
+        assert!(error("program-file = missing.txt").to_string().starts_with("line 1: couldn't read "));
+    }
+}
@@ -0,0 +1,531 @@
+//! The standard instruction set, as a table.
+//!
+//! The machine decodes and executes instructions by looking them up here, so adding a standard
+//! instruction only takes a new entry in [`INSTRUCTIONS`]. Tools that need to know the shape of
+//! an instruction, such as how many parameters it has, can look it up too.
+//!
+//! Tools that analyze or rewrite programs, like the [optimizer](crate::optimize), work from the
+//! parameters and operation of each instruction instead of its opcode, so they handle new
+//! instructions too.
+//!
+//! Parameters are described with the same [`Param`] as the parameters of custom instructions
+//! registered with [`Extensions`](crate::Extensions), and [`Extensions::params`] answers the same
+//! question for those.
+//!
+//! The first cell of an instruction is split into its opcode and parameter modes by [`decode`],
+//! which the machine and every tool use, so they agree on what an instruction means.
+//!
+//! [`Extensions::params`]: crate::Extensions::params
+
+use std::convert::TryFrom;
+
+pub use crate::Param;
+
+use Param::{Read, Write};
+
+/// How a parameter is interpreted. The discriminant is the mode's digit in an instruction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mode {
+    /// The parameter is the address of its value.
+    Position = 0,
+
+    /// The parameter is its value. Write parameters can't be in this mode.
+    Immediate = 1,
+
+    /// The parameter plus the relative base is the address of its value.
+    Relative = 2,
+}
+
+/// A parameter whose mode isn't valid: either its digit isn't a mode, or it's a write parameter
+/// in immediate mode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct InvalidMode {
+    /// The index of the parameter.
+    pub param: usize,
+
+    /// The digit of the parameter's mode.
+    pub digit: i32,
+}
+
+/// The first cell of an instruction, split into its opcode and parameter modes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    pub opcode: i32,
+    pub modes: Modes,
+}
+
+/// Splits the first cell of an instruction into its opcode and parameter modes.
+pub fn decode(instr: i32) -> Decoded {
+    Decoded { opcode: instr % 100, modes: Modes { digits: instr / 100, param: 0 } }
+}
+
+/// The modes of the parameters of an instruction, in order.
+///
+/// Parameters past the digits of the instruction are in position mode, so the iterator never
+/// ends. The digits of a negative instruction are all invalid modes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Modes {
+    digits: i32,
+    param: usize,
+}
+
+impl Modes {
+    /// The modes of parameters of the given kinds, in order, checking that none writes in
+    /// immediate mode.
+    pub fn checked(self, params: &[Param]) -> impl Iterator<Item = Result<Mode, InvalidMode>> + '_ {
+        let first = self.param;
+
+        self.zip(params).enumerate().map(move |(i, (mode, &param))| match mode? {
+            Mode::Immediate if param == Write => {
+                Err(InvalidMode { param: first + i, digit: Mode::Immediate as i32 })
+            }
+            mode => Ok(mode),
+        })
+    }
+
+    /// The checked modes of parameters of the given kinds, or the first invalid one.
+    pub fn of(self, params: &[Param]) -> Result<Vec<Mode>, InvalidMode> {
+        self.checked(params).collect()
+    }
+}
+
+impl Iterator for Modes {
+    type Item = Result<Mode, InvalidMode>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let digit = self.digits % 10;
+        let param = self.param;
+
+        self.digits /= 10;
+        self.param += 1;
+
+        Some(match digit {
+            0 => Ok(Mode::Position),
+            1 => Ok(Mode::Immediate),
+            2 => Ok(Mode::Relative),
+            digit => Err(InvalidMode { param, digit }),
+        })
+    }
+}
+
+/// A function of the two read parameters `a` and `b` of a computation.
+///
+/// Arithmetic is exact: a result that doesn't fit in a cell is an overflow, which stops the
+/// machine with [`ExecutionError::Overflow`](crate::ExecutionError::Overflow), rather than
+/// wrapping around.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compute {
+    Add,
+    Multiply,
+
+    /// 1 if `a < b`, and 0 otherwise.
+    LessThan,
+
+    /// 1 if `a == b`, and 0 otherwise.
+    Equals,
+
+    /// `x * a + y * b + c`, as `[x, y, c]`.
+    Affine([i32; 3]),
+}
+
+impl Compute {
+    /// The result, or `None` if it overflows.
+    pub fn apply(self, a: i32, b: i32) -> Option<i32> {
+        match self {
+            Compute::Add => a.checked_add(b),
+            Compute::Multiply => a.checked_mul(b),
+            Compute::LessThan => Some((a < b) as i32),
+            Compute::Equals => Some((a == b) as i32),
+            Compute::Affine([x, y, c]) => {
+                let result = i64::from(x) * i64::from(a) + i64::from(y) * i64::from(b) + i64::from(c);
+                i32::try_from(result).ok()
+            }
+        }
+    }
+}
+
+/// What a conditional jump tests its first read parameter for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Condition {
+    NonZero,
+    Zero,
+    Negative,
+}
+
+impl Condition {
+    /// Whether the jump is taken.
+    pub fn holds(self, value: i32) -> bool {
+        match self {
+            Condition::NonZero => value != 0,
+            Condition::Zero => value == 0,
+            Condition::Negative => value < 0,
+        }
+    }
+}
+
+/// What an instruction does with its parameters.
+///
+/// The values of the read parameters are passed in order, and an instruction that has a write
+/// parameter writes to it.
+#[derive(Debug, Copy, Clone)]
+pub enum Operation {
+    /// Writes the result of a computation of the two read parameters.
+    Compute(Compute),
+
+    /// Writes a value from the input queue.
+    Input,
+
+    /// Outputs the read parameter.
+    Output,
+
+    /// Jumps to the second read parameter if the condition holds for the first.
+    JumpIf(Condition),
+
+    /// Adds the read parameter to the relative base.
+    AdjustRelativeBase,
+
+    /// Stops the machine.
+    Halt,
+}
+
+/// A standard instruction.
+#[derive(Debug)]
+pub struct Instruction {
+    pub opcode: i32,
+
+    /// The name of the instruction in disassembly.
+    pub mnemonic: &'static str,
+    pub params: &'static [Param],
+    pub operation: Operation,
+}
+
+impl Instruction {
+    /// The number of cells taken up by the instruction, including the opcode. Unless the
+    /// instruction jumps or halts, this is how far the program counter advances.
+    pub fn size(&self) -> usize {
+        1 + self.params.len()
+    }
+
+    /// The index of the parameter that the instruction writes to, if there is one.
+    pub fn write_param(&self) -> Option<usize> {
+        self.params.iter().position(|&param| param == Write)
+    }
+
+    /// Whether the instruction may continue somewhere other than the next instruction.
+    pub fn jumps(&self) -> bool {
+        matches!(self.operation, Operation::JumpIf(_))
+    }
+
+    /// Whether the machine stops after the instruction.
+    pub fn halts(&self) -> bool {
+        matches!(self.operation, Operation::Halt)
+    }
+}
+
+/// The standard instructions, by opcode.
+pub const INSTRUCTIONS: &[Instruction] = &[
+    Instruction {
+        opcode: 1,
+        mnemonic: "add",
+        params: &[Read, Read, Write],
+        operation: Operation::Compute(Compute::Add),
+    },
+    Instruction {
+        opcode: 2,
+        mnemonic: "mul",
+        params: &[Read, Read, Write],
+        operation: Operation::Compute(Compute::Multiply),
+    },
+    Instruction {
+        opcode: 3,
+        mnemonic: "in",
+        params: &[Write],
+        operation: Operation::Input,
+    },
+    Instruction {
+        opcode: 4,
+        mnemonic: "out",
+        params: &[Read],
+        operation: Operation::Output,
+    },
+    Instruction {
+        opcode: 5,
+        mnemonic: "jnz",
+        params: &[Read, Read],
+        operation: Operation::JumpIf(Condition::NonZero),
+    },
+    Instruction {
+        opcode: 6,
+        mnemonic: "jz",
+        params: &[Read, Read],
+        operation: Operation::JumpIf(Condition::Zero),
+    },
+    Instruction {
+        opcode: 7,
+        mnemonic: "lt",
+        params: &[Read, Read, Write],
+        operation: Operation::Compute(Compute::LessThan),
+    },
+    Instruction {
+        opcode: 8,
+        mnemonic: "eq",
+        params: &[Read, Read, Write],
+        operation: Operation::Compute(Compute::Equals),
+    },
+    Instruction {
+        opcode: 9,
+        mnemonic: "arb",
+        params: &[Read],
+        operation: Operation::AdjustRelativeBase,
+    },
+    Instruction {
+        opcode: 99,
+        mnemonic: "hlt",
+        params: &[],
+        operation: Operation::Halt,
+    },
+];
+
+/// Looks up a standard instruction by its opcode, which is the last two digits of the first cell
+/// of an instruction.
+pub fn lookup(opcode: i32) -> Option<&'static Instruction> {
+    let instruction = INSTRUCTIONS.iter().find(|instruction| instruction.opcode == opcode);
+
+    #[cfg(test)]
+    let instruction = instruction.or_else(|| registry::lookup(opcode));
+
+    instruction
+}
+
+/// Every standard instruction, including ones registered by tests.
+pub(crate) fn all() -> Vec<&'static Instruction> {
+    #[allow(unused_mut)]
+    let mut instructions = INSTRUCTIONS.iter().collect::<Vec<_>>();
+
+    #[cfg(test)]
+    instructions.extend(registry::all());
+
+    instructions
+}
+
+/// Instructions that tests add to the table, to check that tools built on the table handle
+/// instructions they weren't written for.
+#[cfg(test)]
+pub(crate) mod registry {
+    use std::cell::RefCell;
+
+    use super::{Compute, Condition, Instruction, Operation, Read, Write};
+
+    thread_local! {
+        static REGISTERED: RefCell<Vec<&'static Instruction>> = const { RefCell::new(vec![]) };
+    }
+
+    /// Keeps an instruction registered until it's dropped.
+    pub(crate) struct Registered(i32);
+
+    impl Drop for Registered {
+        fn drop(&mut self) {
+            REGISTERED.with(|registered| registered.borrow_mut().retain(|instruction| instruction.opcode != self.0));
+        }
+    }
+
+    /// Adds an instruction to the table on the current thread.
+    pub(crate) fn register(instruction: Instruction) -> Registered {
+        assert!(super::lookup(instruction.opcode).is_none(), "opcode {} is taken", instruction.opcode);
+
+        let opcode = instruction.opcode;
+        REGISTERED.with(|registered| registered.borrow_mut().push(Box::leak(Box::new(instruction))));
+        Registered(opcode)
+    }
+
+    /// A program that uses the example instructions. It outputs 1 if its input is less than 8, and
+    /// 0 otherwise.
+    pub(crate) const EXAMPLE: &str = "3,100,1042,100,8,101,1043,101,12,104,0,99,104,1,99";
+
+    /// Registers two instructions that no tool was written for: `sub` (42), which subtracts its
+    /// second parameter from its first, and `jlz` (43), which jumps if its first parameter is
+    /// negative.
+    pub(crate) fn register_examples() -> [Registered; 2] {
+        [
+            register(Instruction {
+                opcode: 42,
+                mnemonic: "sub",
+                params: &[Read, Read, Write],
+                operation: Operation::Compute(Compute::Affine([1, -1, 0])),
+            }),
+            register(Instruction {
+                opcode: 43,
+                mnemonic: "jlz",
+                params: &[Read, Read],
+                operation: Operation::JumpIf(Condition::Negative),
+            }),
+        ]
+    }
+
+    pub(super) fn lookup(opcode: i32) -> Option<&'static Instruction> {
+        REGISTERED.with(|registered| registered.borrow().iter().copied().find(|instruction| instruction.opcode == opcode))
+    }
+
+    pub(super) fn all() -> Vec<&'static Instruction> {
+        REGISTERED.with(|registered| registered.borrow().clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{program, verify, Intcode};
+
+    use super::{decode, lookup, registry, Compute, InvalidMode, Mode, Operation, Param, INSTRUCTIONS};
+
+    use Param::{Read, Write};
+
+    #[test]
+    fn opcodes_are_unique() {
+        let opcodes = INSTRUCTIONS.iter().map(|instruction| instruction.opcode).collect::<HashSet<_>>();
+        assert_eq!(opcodes.len(), INSTRUCTIONS.len());
+
+        assert!(INSTRUCTIONS.iter().all(|instruction| 0 < instruction.opcode && instruction.opcode < 100));
+    }
+
+    #[test]
+    fn shapes_match_day5() {
+        // The instructions as described by days 2, 5 and 9.
+        let expected: &[(i32, &[Param])] = &[
+            (1, &[Read, Read, Write]),
+            (2, &[Read, Read, Write]),
+            (3, &[Write]),
+            (4, &[Read]),
+            (5, &[Read, Read]),
+            (6, &[Read, Read]),
+            (7, &[Read, Read, Write]),
+            (8, &[Read, Read, Write]),
+            (9, &[Read]),
+            (99, &[]),
+        ];
+
+        assert_eq!(INSTRUCTIONS.len(), expected.len());
+
+        for &(opcode, params) in expected {
+            let instruction = lookup(opcode).unwrap();
+            assert_eq!(instruction.params, params, "opcode {}", opcode);
+            assert_eq!(instruction.size(), params.len() + 1);
+        }
+
+        assert_eq!(lookup(3).unwrap().write_param(), Some(0));
+        assert_eq!(lookup(7).unwrap().write_param(), Some(2));
+        assert_eq!(lookup(4).unwrap().write_param(), None);
+        assert!(lookup(5).unwrap().jumps() && lookup(6).unwrap().jumps());
+        assert!(!lookup(1).unwrap().jumps());
+        assert!(lookup(99).unwrap().halts());
+        assert!(lookup(0).is_none() && lookup(42).is_none());
+    }
+
+    #[test]
+    fn decoding() {
+        let decoded = decode(21002);
+        assert_eq!(decoded.opcode, 2);
+
+        let modes = decoded.modes.take(4).collect::<Result<Vec<_>, _>>();
+        assert_eq!(modes, Ok(vec![Mode::Position, Mode::Immediate, Mode::Relative, Mode::Position]));
+
+        assert_eq!(decode(301).modes.next(), Some(Err(InvalidMode { param: 0, digit: 3 })));
+        assert!(decode(-1101).modes.next().unwrap().is_err());
+
+        // A write parameter can't be immediate.
+        let modes = decode(1101).modes.of(&[Read, Read, Write]);
+        assert_eq!(modes, Ok(vec![Mode::Immediate, Mode::Immediate, Mode::Position]));
+
+        let modes = decode(11101).modes.of(&[Read, Read, Write]);
+        assert_eq!(modes, Err(InvalidMode { param: 2, digit: 1 }));
+        assert_eq!(decode(11101).modes.of(&[Read, Read]), Ok(vec![Mode::Immediate; 2]));
+    }
+
+    #[test]
+    fn computations_match_day5() {
+        // Runs each computation with immediate parameters and outputs the result.
+        for instruction in INSTRUCTIONS {
+            let compute = match instruction.operation {
+                Operation::Compute(compute) => compute,
+                _ => continue,
+            };
+
+            for &(a, b) in &[(3, 4), (4, 4), (5, -4)] {
+                let program = vec![1100 + instruction.opcode, a, b, 7, 4, 7, 99, 0];
+                let output = Intcode::new(program, vec![]).execute();
+
+                let expected = match instruction.opcode {
+                    1 => a + b,
+                    2 => a * b,
+                    7 => (a < b) as i32,
+                    8 => (a == b) as i32,
+                    opcode => panic!("unexpected computation: {}", opcode),
+                };
+
+                assert_eq!(output, vec![expected], "opcode {}", instruction.opcode);
+                assert_eq!(compute.apply(a, b), Some(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!(Compute::Add.apply(i32::MAX, 1), None);
+        assert_eq!(Compute::Add.apply(i32::MIN, -1), None);
+        assert_eq!(Compute::Multiply.apply(65536, 65536), None);
+        assert_eq!(Compute::Multiply.apply(-1, i32::MIN), None);
+        assert_eq!(Compute::Affine([1, -1, 0]).apply(i32::MIN, 1), None);
+
+        // Only the result has to fit.
+        assert_eq!(Compute::Affine([2, -2, 0]).apply(i32::MAX, i32::MAX), Some(0));
+        assert_eq!(Compute::LessThan.apply(i32::MIN, i32::MAX), Some(1));
+    }
+
+    #[test]
+    fn jumps_match_day5() {
+        // Outputs 1 if the jump is taken, and 0 otherwise.
+        for instruction in INSTRUCTIONS.iter().filter(|instruction| instruction.jumps()) {
+            for &cond in &[0, 1, -7] {
+                let program = vec![1100 + instruction.opcode, cond, 7, 104, 0, 99, 0, 104, 1, 99];
+                let output = Intcode::new(program, vec![]).execute();
+
+                let taken = match instruction.opcode {
+                    5 => cond != 0,
+                    6 => cond == 0,
+                    opcode => panic!("unexpected jump: {}", opcode),
+                };
+
+                assert_eq!(output, vec![taken as i32], "opcode {} with {}", instruction.opcode, cond);
+
+                if let Operation::JumpIf(condition) = instruction.operation {
+                    assert_eq!(condition.holds(cond), taken);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn registered_instructions() {
+        let _examples = registry::register_examples();
+
+        assert_eq!(lookup(42).unwrap().mnemonic, "sub");
+        assert!(lookup(43).unwrap().jumps());
+
+        // Outputs 1 if the input is less than 8, and 0 otherwise.
+        let program = program::parse(registry::EXAMPLE).unwrap();
+        assert_eq!(Intcode::new(program.clone(), vec![3]).execute(), vec![1]);
+        assert_eq!(Intcode::new(program.clone(), vec![8]).execute(), vec![0]);
+        assert!(verify::verify(&program).is_ok());
+    }
+
+    #[test]
+    fn day5_diagnostics() {
+        let day5 = program::parse(include_str!("../../inputs/day5.txt")).unwrap();
+
+        let output = Intcode::new(day5.clone(), vec![1]).execute();
+        assert!(output[..output.len() - 1].iter().all(|&code| code == 0));
+
+        assert_eq!(Intcode::new(day5, vec![5]).execute().len(), 1);
+    }
+}
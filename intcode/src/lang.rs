@@ -0,0 +1,401 @@
+//! A small language that compiles to Intcode.
+//!
+//! A program is a list of functions, and runs by calling `main`:
+//!
+//! ```text
+//! // Prints the factorial of each input until the input is 0.
+//! fn factorial(n) {
+//!     if n <= 1 {
+//!         return 1;
+//!     }
+//!
+//!     return n * factorial(n - 1);
+//! }
+//!
+//! fn main() {
+//!     let n = input();
+//!     while n != 0 {
+//!         print(factorial(n));
+//!         n = input();
+//!     }
+//! }
+//! ```
+//!
+//! All values are integers. Expressions support `+`, `-`, `*`, the comparisons `==`, `!=`, `<`,
+//! `>`, `<=` and `>=`, which evaluate to 1 or 0, and unary `-` and `!`. There is no division,
+//! because Intcode has no instruction for it. Conditions are true if they're nonzero.
+//!
+//! Variables are declared with `let` and live until the end of their block. Functions return 0
+//! unless they return a value, and may call themselves. `input()` reads a value and `print(x)`
+//! outputs one.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::program;
+
+mod codegen;
+mod lexer;
+mod parser;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnexpectedCharacter(char),
+    NumberTooLarge(String),
+    UnexpectedToken { found: String, expected: &'static str },
+    UndefinedVariable(String),
+    UndefinedFunction(String),
+    WrongArgumentCount { name: String, expected: usize, found: usize },
+
+    /// A function is defined twice, or has the name of a built-in function.
+    DuplicateFunction(String),
+    MissingMain,
+}
+
+/// A problem with a program, and the line it's on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    pub line: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+
+        match &self.kind {
+            ErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character {:?}", c),
+            ErrorKind::NumberTooLarge(digits) => write!(f, "number is too large: {}", digits),
+            ErrorKind::UnexpectedToken { found, expected } => write!(f, "expected {}, found {}", expected, found),
+            ErrorKind::UndefinedVariable(name) => write!(f, "undefined variable `{}`", name),
+            ErrorKind::UndefinedFunction(name) => write!(f, "undefined function `{}`", name),
+            ErrorKind::WrongArgumentCount { name, expected, found } => {
+                write!(f, "`{}` takes {} arguments but was given {}", name, expected, found)
+            }
+            ErrorKind::DuplicateFunction(name) => write!(f, "function `{}` is already defined", name),
+            ErrorKind::MissingMain => write!(f, "there is no `main` function"),
+        }
+    }
+}
+
+impl Error for CompileError {}
+
+/// Compiles a program to the cells of an Intcode program.
+pub fn compile_to_cells(source: &str) -> Result<Vec<i32>, CompileError> {
+    let tokens = lexer::tokenize(source)?;
+    let functions = parser::parse(tokens)?;
+    codegen::generate(&functions)
+}
+
+/// Compiles a program to Intcode text, which can be run with [`crate::Intcode::load`].
+pub fn compile(source: &str) -> Result<String, CompileError> {
+    compile_to_cells(source).map(|cells| program::serialize(&cells))
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{Intcode, Limits};
+
+    use super::{compile, CompileError, ErrorKind};
+
+    /// Compiles and runs a program.
+    fn run(source: &str, input: Vec<i32>) -> Vec<i32> {
+        let program = compile(source).unwrap_or_else(|e| panic!("{}", e));
+        let limits = Limits { max_instructions: Some(10_000_000), ..Limits::default() };
+
+        Intcode::load(&program, input)
+            .execute_with_limits(&limits)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn error(source: &str) -> CompileError {
+        compile(source).unwrap_err()
+    }
+
+    #[test]
+    fn empty_main() {
+        assert_eq!(run("fn main() {}", vec![]), vec![]);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let source = indoc!("
+            fn main() {
+                print(1 + 2 * 3);
+                print((1 + 2) * 3);
+                print(10 - 4 - 3);
+                print(-5 * -2);
+                print(--7);
+            }
+        ");
+
+        assert_eq!(run(source, vec![]), vec![7, 9, 3, 10, 7]);
+    }
+
+    #[test]
+    fn comparisons() {
+        let source = indoc!("
+            fn main() {
+                let a = input();
+                let b = input();
+                print(a == b);
+                print(a != b);
+                print(a < b);
+                print(a > b);
+                print(a <= b);
+                print(a >= b);
+                print(!a);
+            }
+        ");
+
+        assert_eq!(run(source, vec![1, 2]), vec![0, 1, 1, 0, 1, 0, 0]);
+        assert_eq!(run(source, vec![2, 2]), vec![1, 0, 0, 0, 1, 1, 0]);
+        assert_eq!(run(source, vec![0, -1]), vec![0, 1, 0, 1, 0, 1, 1]);
+    }
+
+    #[test]
+    fn variables_and_scopes() {
+        let source = indoc!("
+            fn main() {
+                let x = 1;
+                let y = x + 1;
+                x = x + y;
+                if 1 {
+                    let x = 100;
+                    print(x);
+                    y = 5;
+                }
+                print(x);
+                print(y);
+            }
+        ");
+
+        assert_eq!(run(source, vec![]), vec![100, 3, 5]);
+    }
+
+    #[test]
+    fn if_else_chain() {
+        let source = indoc!("
+            fn main() {
+                let n = input();
+                if n < 0 {
+                    print(-1);
+                } else if n == 0 {
+                    print(0);
+                } else {
+                    print(1);
+                }
+                print(42);
+            }
+        ");
+
+        assert_eq!(run(source, vec![-7]), vec![-1, 42]);
+        assert_eq!(run(source, vec![0]), vec![0, 42]);
+        assert_eq!(run(source, vec![7]), vec![1, 42]);
+    }
+
+    #[test]
+    fn while_loop() {
+        // Sums the numbers up to the input.
+        let source = indoc!("
+            fn main() {
+                let n = input();
+                let sum = 0;
+                while n > 0 {
+                    sum = sum + n;
+                    n = n - 1;
+                }
+                print(sum);
+            }
+        ");
+
+        assert_eq!(run(source, vec![10]), vec![55]);
+        assert_eq!(run(source, vec![0]), vec![0]);
+    }
+
+    #[test]
+    fn echo_until_zero() {
+        let source = indoc!("
+            fn main() {
+                let x = input();
+                while x {
+                    print(x);
+                    x = input();
+                }
+            }
+        ");
+
+        assert_eq!(run(source, vec![3, 1, 4, 0, 5]), vec![3, 1, 4]);
+    }
+
+    #[test]
+    fn functions() {
+        let source = indoc!("
+            fn add(a, b) {
+                return a + b;
+            }
+
+            fn nothing() {}
+
+            fn early(x) {
+                if x {
+                    return;
+                }
+                return 9;
+            }
+
+            fn main() {
+                print(add(2, 3));
+                print(add(add(1, 2), add(3, 4)));
+                print(nothing());
+                print(early(1));
+                print(early(0));
+                print(later());
+            }
+
+            fn later() {
+                return 7;
+            }
+        ");
+
+        assert_eq!(run(source, vec![]), vec![5, 10, 0, 0, 9, 7]);
+    }
+
+    #[test]
+    fn arguments_are_not_clobbered() {
+        // Each argument is a call, and the locals of the caller survive the calls.
+        let source = indoc!("
+            fn square(x) {
+                let y = x * x;
+                return y;
+            }
+
+            fn sub(a, b) {
+                return a - b;
+            }
+
+            fn main() {
+                let keep = 11;
+                print(sub(square(5), square(3)));
+                print(keep);
+            }
+        ");
+
+        assert_eq!(run(source, vec![]), vec![16, 11]);
+    }
+
+    #[test]
+    fn recursion() {
+        let source = indoc!("
+            fn factorial(n) {
+                if n <= 1 {
+                    return 1;
+                }
+                return n * factorial(n - 1);
+            }
+
+            fn fib(n) {
+                if n < 2 {
+                    return n;
+                }
+                return fib(n - 1) + fib(n - 2);
+            }
+
+            fn is_even(n) {
+                if n == 0 {
+                    return 1;
+                }
+                return is_odd(n - 1);
+            }
+
+            fn is_odd(n) {
+                if n == 0 {
+                    return 0;
+                }
+                return is_even(n - 1);
+            }
+
+            fn main() {
+                print(factorial(input()));
+                print(fib(input()));
+                print(is_even(input()));
+            }
+        ");
+
+        assert_eq!(run(source, vec![5, 10, 7]), vec![120, 55, 0]);
+        assert_eq!(run(source, vec![10, 20, 100]), vec![3_628_800, 6765, 1]);
+    }
+
+    #[test]
+    fn deep_recursion() {
+        let source = indoc!("
+            fn sum_to(n) {
+                if n == 0 {
+                    return 0;
+                }
+                return n + sum_to(n - 1);
+            }
+
+            fn main() {
+                print(sum_to(input()));
+            }
+        ");
+
+        assert_eq!(run(source, vec![2000]), vec![2_001_000]);
+    }
+
+    #[test]
+    fn day5_larger_example() {
+        // Behaves like the larger example from day 5.
+        let source = indoc!("
+            fn main() {
+                let n = input();
+                if n < 8 {
+                    print(999);
+                } else if n == 8 {
+                    print(1000);
+                } else {
+                    print(1001);
+                }
+            }
+        ");
+
+        assert_eq!(run(source, vec![3]), vec![999]);
+        assert_eq!(run(source, vec![8]), vec![1000]);
+        assert_eq!(run(source, vec![99]), vec![1001]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            error("fn main() {\n  print(x);\n}"),
+            CompileError { line: 2, kind: ErrorKind::UndefinedVariable(String::from("x")) }
+        );
+        assert_eq!(
+            error("fn main() {\n  if 1 { let x = 1; }\n  x = 2;\n}"),
+            CompileError { line: 3, kind: ErrorKind::UndefinedVariable(String::from("x")) }
+        );
+        assert_eq!(
+            error("fn main() {\n  f();\n}"),
+            CompileError { line: 2, kind: ErrorKind::UndefinedFunction(String::from("f")) }
+        );
+        assert_eq!(
+            error("fn f(a) {}\nfn main() { f(1, 2); }"),
+            CompileError {
+                line: 2,
+                kind: ErrorKind::WrongArgumentCount { name: String::from("f"), expected: 1, found: 2 },
+            }
+        );
+        assert_eq!(
+            error("fn main() {}\nfn print(x) {}"),
+            CompileError { line: 2, kind: ErrorKind::DuplicateFunction(String::from("print")) }
+        );
+        assert_eq!(error("fn f() {}"), CompileError { line: 1, kind: ErrorKind::MissingMain });
+        assert_eq!(
+            error("fn main() {\n  print(1 / 2);\n}").to_string(),
+            "line 2: unexpected character '/'"
+        );
+    }
+}
@@ -0,0 +1,402 @@
+//! Generates Intcode from the syntax tree.
+//!
+//! The relative base points at the frame of the function that is running. Slot 0 of a frame holds
+//! the address to return to, the next slots hold the arguments, and the rest hold local variables
+//! and temporary values. To call a function, the caller stores the return address and arguments
+//! just past the end of its own frame, moves the relative base there and jumps to the function.
+//! The function leaves its return value in a fixed cell and jumps back, and the caller moves the
+//! relative base back down.
+//!
+//! Frames start right after the program, and memory grows as the stack does.
+
+use std::collections::HashMap;
+
+use super::parser::{BinaryOp, Expr, Function, Stmt, UnaryOp};
+use super::{CompileError, ErrorKind};
+
+/// Functions that are built into the language, and how many arguments they take.
+const BUILTINS: &[(&str, usize)] = &[("input", 0), ("print", 1)];
+
+type Label = usize;
+
+/// A cell of the generated program, which might depend on a label that isn't known yet.
+#[derive(Debug, Copy, Clone)]
+enum Cell {
+    Value(i32),
+
+    /// The value of a label, plus an offset.
+    Label(Label, i32),
+}
+
+/// A parameter of an instruction.
+#[derive(Debug, Copy, Clone)]
+enum Operand {
+    Immediate(Cell),
+
+    /// A cell at a fixed address.
+    Absolute(Cell),
+
+    /// A slot of the current frame.
+    Slot(Cell),
+}
+
+impl Operand {
+    fn slot(slot: i32) -> Self {
+        Operand::Slot(Cell::Value(slot))
+    }
+
+    fn value(n: i32) -> Self {
+        Operand::Immediate(Cell::Value(n))
+    }
+}
+
+/// The function being generated.
+struct Frame {
+    /// The variables in scope, innermost block last.
+    scopes: Vec<HashMap<String, i32>>,
+
+    /// The number of slots used so far. Slots are never reused.
+    size: i32,
+
+    /// Set to the final size of the frame once the function has been generated.
+    size_label: Label,
+    neg_size_label: Label,
+}
+
+impl Frame {
+    fn alloc(&mut self) -> i32 {
+        self.size += 1;
+        self.size - 1
+    }
+
+    fn lookup(&self, name: &str, line: usize) -> Result<i32, CompileError> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+            .ok_or_else(|| CompileError { line, kind: ErrorKind::UndefinedVariable(name.to_owned()) })
+    }
+}
+
+struct Codegen {
+    code: Vec<Cell>,
+    labels: Vec<Option<i32>>,
+
+    /// The label and number of parameters of each function.
+    functions: HashMap<String, (Label, usize)>,
+
+    /// The cell that holds the return value of the last call.
+    ret: Label,
+}
+
+/// Generates a program that calls `main` and halts when it returns.
+pub(super) fn generate(functions: &[Function]) -> Result<Vec<i32>, CompileError> {
+    let mut gen = Codegen { code: vec![], labels: vec![], functions: HashMap::new(), ret: 0 };
+    gen.ret = gen.label();
+
+    for function in functions {
+        let duplicate = gen.functions.contains_key(&function.name)
+            || BUILTINS.iter().any(|&(name, _)| name == function.name);
+
+        if duplicate {
+            return Err(CompileError { line: function.line, kind: ErrorKind::DuplicateFunction(function.name.clone()) });
+        }
+
+        let label = gen.label();
+        gen.functions.insert(function.name.clone(), (label, function.params.len()));
+    }
+
+    let main = match functions.iter().find(|function| function.name == "main") {
+        Some(main) => main,
+        None => {
+            let line = functions.last().map_or(1, |function| function.line);
+            return Err(CompileError { line, kind: ErrorKind::MissingMain });
+        }
+    };
+
+    if !main.params.is_empty() {
+        return Err(CompileError {
+            line: main.line,
+            kind: ErrorKind::WrongArgumentCount { name: String::from("main"), expected: 0, found: main.params.len() },
+        });
+    }
+
+    // Calls main from an empty frame at the start of the stack.
+    let stack = gen.label();
+    let halt = gen.label();
+    gen.emit(9, &[Operand::Immediate(Cell::Label(stack, 0))]);
+    gen.emit(1, &[Operand::Immediate(Cell::Label(halt, 0)), Operand::value(0), Operand::slot(0)]);
+    gen.jump(gen.functions["main"].0);
+    gen.place(halt);
+    gen.emit(99, &[]);
+
+    for function in functions {
+        gen.function(function)?;
+    }
+
+    gen.place(gen.ret);
+    gen.code.push(Cell::Value(0));
+    gen.place(stack);
+
+    Ok(gen.resolve())
+}
+
+impl Codegen {
+    fn label(&mut self) -> Label {
+        self.labels.push(None);
+        self.labels.len() - 1
+    }
+
+    /// Sets a label to the address of the next cell.
+    fn place(&mut self, label: Label) {
+        self.labels[label] = Some(self.code.len() as i32);
+    }
+
+    fn resolve(&self) -> Vec<i32> {
+        self.code
+            .iter()
+            .map(|&cell| match cell {
+                Cell::Value(value) => value,
+                Cell::Label(label, offset) => self.labels[label].expect("label was never placed") + offset,
+            })
+            .collect()
+    }
+
+    fn emit(&mut self, opcode: i32, operands: &[Operand]) {
+        let mut instr = opcode;
+        let mut place = 100;
+        let mut cells = vec![];
+
+        for operand in operands {
+            let (mode, cell) = match *operand {
+                Operand::Absolute(cell) => (0, cell),
+                Operand::Immediate(cell) => (1, cell),
+                Operand::Slot(cell) => (2, cell),
+            };
+
+            instr += mode * place;
+            place *= 10;
+            cells.push(cell);
+        }
+
+        self.code.push(Cell::Value(instr));
+        self.code.extend(cells);
+    }
+
+    fn copy(&mut self, src: Operand, dst: Operand) {
+        self.emit(1, &[src, Operand::value(0), dst]);
+    }
+
+    fn jump(&mut self, target: Label) {
+        self.emit(5, &[Operand::value(1), Operand::Immediate(Cell::Label(target, 0))]);
+    }
+
+    fn jump_if_false(&mut self, cond: Operand, target: Label) {
+        self.emit(6, &[cond, Operand::Immediate(Cell::Label(target, 0))]);
+    }
+
+    /// Jumps to the return address in slot 0.
+    fn ret(&mut self) {
+        self.emit(5, &[Operand::value(1), Operand::slot(0)]);
+    }
+
+    fn function(&mut self, function: &Function) -> Result<(), CompileError> {
+        let (label, _) = self.functions[&function.name];
+        self.place(label);
+
+        let mut frame = Frame {
+            scopes: vec![HashMap::new()],
+            size: 1,
+            size_label: self.label(),
+            neg_size_label: self.label(),
+        };
+
+        for param in &function.params {
+            let slot = frame.alloc();
+            frame.scopes[0].insert(param.clone(), slot);
+        }
+
+        self.block(&mut frame, &function.body)?;
+
+        // Functions that end without returning return 0.
+        self.copy(Operand::value(0), Operand::Absolute(Cell::Label(self.ret, 0)));
+        self.ret();
+
+        self.labels[frame.size_label] = Some(frame.size);
+        self.labels[frame.neg_size_label] = Some(-frame.size);
+
+        Ok(())
+    }
+
+    fn block(&mut self, frame: &mut Frame, stmts: &[Stmt]) -> Result<(), CompileError> {
+        frame.scopes.push(HashMap::new());
+
+        for stmt in stmts {
+            self.stmt(frame, stmt)?;
+        }
+
+        frame.scopes.pop();
+        Ok(())
+    }
+
+    fn stmt(&mut self, frame: &mut Frame, stmt: &Stmt) -> Result<(), CompileError> {
+        match stmt {
+            Stmt::Let { name, value } => {
+                let value = self.expr(frame, value)?;
+                let slot = frame.alloc();
+                self.copy(value, Operand::slot(slot));
+                frame.scopes.last_mut().unwrap().insert(name.clone(), slot);
+            }
+            Stmt::Assign { name, value, line } => {
+                let slot = frame.lookup(name, *line)?;
+                let value = self.expr(frame, value)?;
+                self.copy(value, Operand::slot(slot));
+            }
+            Stmt::If { cond, then, otherwise } => {
+                let otherwise_label = self.label();
+                let end = self.label();
+
+                let cond = self.expr(frame, cond)?;
+                self.jump_if_false(cond, otherwise_label);
+                self.block(frame, then)?;
+                self.jump(end);
+                self.place(otherwise_label);
+                self.block(frame, otherwise)?;
+                self.place(end);
+            }
+            Stmt::While { cond, body } => {
+                let top = self.label();
+                let end = self.label();
+
+                self.place(top);
+                let cond = self.expr(frame, cond)?;
+                self.jump_if_false(cond, end);
+                self.block(frame, body)?;
+                self.jump(top);
+                self.place(end);
+            }
+            Stmt::Return(value) => {
+                let value = match value {
+                    Some(value) => self.expr(frame, value)?,
+                    None => Operand::value(0),
+                };
+
+                self.copy(value, Operand::Absolute(Cell::Label(self.ret, 0)));
+                self.ret();
+            }
+            Stmt::Expr(expr) => {
+                self.expr(frame, expr)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Generates code for an expression, and returns where its value ends up.
+    fn expr(&mut self, frame: &mut Frame, expr: &Expr) -> Result<Operand, CompileError> {
+        let operand = match expr {
+            Expr::Number(n) => Operand::value(*n),
+            Expr::Var { name, line } => Operand::slot(frame.lookup(name, *line)?),
+            Expr::Unary(op, operand) => {
+                let operand = self.expr(frame, operand)?;
+                let result = Operand::slot(frame.alloc());
+
+                match op {
+                    UnaryOp::Neg => self.emit(2, &[operand, Operand::value(-1), result]),
+                    UnaryOp::Not => self.emit(8, &[operand, Operand::value(0), result]),
+                }
+
+                result
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.expr(frame, lhs)?;
+                let rhs = self.expr(frame, rhs)?;
+                let result = Operand::slot(frame.alloc());
+
+                match op {
+                    BinaryOp::Add => self.emit(1, &[lhs, rhs, result]),
+                    BinaryOp::Sub => {
+                        self.emit(2, &[rhs, Operand::value(-1), result]);
+                        self.emit(1, &[lhs, result, result]);
+                    }
+                    BinaryOp::Mul => self.emit(2, &[lhs, rhs, result]),
+                    BinaryOp::Eq => self.emit(8, &[lhs, rhs, result]),
+                    BinaryOp::Ne => {
+                        self.emit(8, &[lhs, rhs, result]);
+                        self.emit(8, &[result, Operand::value(0), result]);
+                    }
+                    BinaryOp::Lt => self.emit(7, &[lhs, rhs, result]),
+                    BinaryOp::Gt => self.emit(7, &[rhs, lhs, result]),
+                    BinaryOp::Le => {
+                        self.emit(7, &[rhs, lhs, result]);
+                        self.emit(8, &[result, Operand::value(0), result]);
+                    }
+                    BinaryOp::Ge => {
+                        self.emit(7, &[lhs, rhs, result]);
+                        self.emit(8, &[result, Operand::value(0), result]);
+                    }
+                }
+
+                result
+            }
+            Expr::Call { name, args, line } => self.call(frame, name, args, *line)?,
+        };
+
+        Ok(operand)
+    }
+
+    fn call(&mut self, frame: &mut Frame, name: &str, args: &[Expr], line: usize) -> Result<Operand, CompileError> {
+        let builtin = BUILTINS.iter().find(|&&(builtin, _)| builtin == name);
+
+        let (label, arity) = match (self.functions.get(name), builtin) {
+            (Some(&(label, arity)), _) => (Some(label), arity),
+            (None, Some(&(_, arity))) => (None, arity),
+            (None, None) => {
+                return Err(CompileError { line, kind: ErrorKind::UndefinedFunction(name.to_owned()) });
+            }
+        };
+
+        if args.len() != arity {
+            return Err(CompileError {
+                line,
+                kind: ErrorKind::WrongArgumentCount { name: name.to_owned(), expected: arity, found: args.len() },
+            });
+        }
+
+        // Every argument is evaluated before any of them is stored, because evaluating an
+        // argument might call a function, which would overwrite the next frame.
+        let args = args.iter().map(|arg| self.expr(frame, arg)).collect::<Result<Vec<_>, _>>()?;
+
+        let label = match label {
+            Some(label) => label,
+            None if name == "input" => {
+                let result = Operand::slot(frame.alloc());
+                self.emit(3, &[result]);
+                return Ok(result);
+            }
+            None => {
+                self.emit(4, &[args[0]]);
+                return Ok(Operand::value(0));
+            }
+        };
+
+        let size = frame.size_label;
+        let next_frame = |offset| Operand::Slot(Cell::Label(size, offset));
+        let back = self.label();
+
+        for (i, &arg) in args.iter().enumerate() {
+            self.copy(arg, next_frame(1 + i as i32));
+        }
+
+        self.copy(Operand::Immediate(Cell::Label(back, 0)), next_frame(0));
+        self.emit(9, &[Operand::Immediate(Cell::Label(size, 0))]);
+        self.jump(label);
+        self.place(back);
+        self.emit(9, &[Operand::Immediate(Cell::Label(frame.neg_size_label, 0))]);
+
+        let result = Operand::slot(frame.alloc());
+        self.copy(Operand::Absolute(Cell::Label(self.ret, 0)), result);
+
+        Ok(result)
+    }
+}
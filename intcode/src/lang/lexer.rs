@@ -0,0 +1,196 @@
+use super::{CompileError, ErrorKind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Token {
+    Number(i32),
+    Ident(String),
+    Fn,
+    Let,
+    If,
+    Else,
+    While,
+    Return,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    Comma,
+    Semicolon,
+    Assign,
+    Plus,
+    Minus,
+    Star,
+    Not,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eof,
+}
+
+impl Token {
+    /// How the token is written, for error messages.
+    pub(super) fn describe(&self) -> String {
+        let text = match self {
+            Token::Number(n) => return n.to_string(),
+            Token::Ident(name) => return format!("`{}`", name),
+            Token::Fn => "fn",
+            Token::Let => "let",
+            Token::If => "if",
+            Token::Else => "else",
+            Token::While => "while",
+            Token::Return => "return",
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBrace => "{",
+            Token::RBrace => "}",
+            Token::Comma => ",",
+            Token::Semicolon => ";",
+            Token::Assign => "=",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Not => "!",
+            Token::Eq => "==",
+            Token::Ne => "!=",
+            Token::Lt => "<",
+            Token::Gt => ">",
+            Token::Le => "<=",
+            Token::Ge => ">=",
+            Token::Eof => return String::from("end of input"),
+        };
+
+        format!("`{}`", text)
+    }
+}
+
+/// Splits source code into tokens, each with the line it's on. The last token is always
+/// [`Token::Eof`].
+pub(super) fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, CompileError> {
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+                continue;
+            }
+            '0'..='9' => {
+                let mut digits = c.to_string();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    digits.push(c);
+                    chars.next();
+                }
+
+                match digits.parse() {
+                    Ok(n) => Token::Number(n),
+                    Err(_) => return Err(CompileError { line, kind: ErrorKind::NumberTooLarge(digits) }),
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek().filter(|&&c| c.is_ascii_alphanumeric() || c == '_') {
+                    word.push(c);
+                    chars.next();
+                }
+
+                match &word[..] {
+                    "fn" => Token::Fn,
+                    "let" => Token::Let,
+                    "if" => Token::If,
+                    "else" => Token::Else,
+                    "while" => Token::While,
+                    "return" => Token::Return,
+                    _ => Token::Ident(word),
+                }
+            }
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            ',' => Token::Comma,
+            ';' => Token::Semicolon,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '=' | '!' | '<' | '>' => {
+                let followed_by_eq = chars.peek() == Some(&'=');
+                if followed_by_eq {
+                    chars.next();
+                }
+
+                match (c, followed_by_eq) {
+                    ('=', false) => Token::Assign,
+                    ('=', true) => Token::Eq,
+                    ('!', false) => Token::Not,
+                    ('!', true) => Token::Ne,
+                    ('<', false) => Token::Lt,
+                    ('<', true) => Token::Le,
+                    ('>', false) => Token::Gt,
+                    ('>', true) => Token::Ge,
+                    _ => unreachable!(),
+                }
+            }
+            c => return Err(CompileError { line, kind: ErrorKind::UnexpectedCharacter(c) }),
+        };
+
+        tokens.push((token, line));
+    }
+
+    tokens.push((Token::Eof, line));
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{CompileError, ErrorKind};
+    use super::{tokenize, Token};
+
+    #[test]
+    fn tokens() {
+        let tokens = tokenize("let x_1 = 42; // comment\nif x_1 <= -3 { }").unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (Token::Let, 1),
+                (Token::Ident(String::from("x_1")), 1),
+                (Token::Assign, 1),
+                (Token::Number(42), 1),
+                (Token::Semicolon, 1),
+                (Token::If, 2),
+                (Token::Ident(String::from("x_1")), 2),
+                (Token::Le, 2),
+                (Token::Minus, 2),
+                (Token::Number(3), 2),
+                (Token::LBrace, 2),
+                (Token::RBrace, 2),
+                (Token::Eof, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            tokenize("\n1 / 2"),
+            Err(CompileError { line: 2, kind: ErrorKind::UnexpectedCharacter('/') })
+        );
+        assert_eq!(
+            tokenize("9999999999"),
+            Err(CompileError { line: 1, kind: ErrorKind::NumberTooLarge(String::from("9999999999")) })
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use super::lexer::Token;
+use super::{CompileError, ErrorKind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Function {
+    pub(super) name: String,
+    pub(super) params: Vec<String>,
+    pub(super) body: Vec<Stmt>,
+    pub(super) line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Stmt {
+    Let { name: String, value: Expr },
+    Assign { name: String, value: Expr, line: usize },
+    If { cond: Expr, then: Vec<Stmt>, otherwise: Vec<Stmt> },
+    While { cond: Expr, body: Vec<Stmt> },
+    Return(Option<Expr>),
+    Expr(Expr),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(super) enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(super) enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Expr {
+    Number(i32),
+    Var { name: String, line: usize },
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call { name: String, args: Vec<Expr>, line: usize },
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+/// Parses the functions of a program.
+pub(super) fn parse(tokens: Vec<(Token, usize)>) -> Result<Vec<Function>, CompileError> {
+    let mut parser = Parser { tokens, pos: 0 };
+    let mut functions = vec![];
+
+    while *parser.peek() != Token::Eof {
+        functions.push(parser.function()?);
+    }
+
+    Ok(functions)
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn line(&self) -> usize {
+        self.tokens[self.pos].1
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].0.clone();
+
+        if token != Token::Eof {
+            self.pos += 1;
+        }
+
+        token
+    }
+
+    fn error(&self, expected: &'static str) -> CompileError {
+        CompileError {
+            line: self.line(),
+            kind: ErrorKind::UnexpectedToken { found: self.peek().describe(), expected },
+        }
+    }
+
+    fn expect(&mut self, token: Token, expected: &'static str) -> Result<(), CompileError> {
+        if *self.peek() == token {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.error(expected))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, CompileError> {
+        match self.peek() {
+            Token::Ident(_) => match self.next() {
+                Token::Ident(name) => Ok(name),
+                _ => unreachable!(),
+            },
+            _ => Err(self.error("a name")),
+        }
+    }
+
+    fn function(&mut self) -> Result<Function, CompileError> {
+        let line = self.line();
+        self.expect(Token::Fn, "`fn`")?;
+        let name = self.ident()?;
+
+        self.expect(Token::LParen, "`(`")?;
+        let mut params = vec![];
+        if *self.peek() != Token::RParen {
+            params.push(self.ident()?);
+            while *self.peek() == Token::Comma {
+                self.next();
+                params.push(self.ident()?);
+            }
+        }
+        self.expect(Token::RParen, "`,` or `)`")?;
+
+        let body = self.block()?;
+
+        Ok(Function { name, params, body, line })
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, CompileError> {
+        self.expect(Token::LBrace, "`{`")?;
+
+        let mut stmts = vec![];
+        while *self.peek() != Token::RBrace {
+            stmts.push(self.stmt()?);
+        }
+
+        self.next();
+        Ok(stmts)
+    }
+
+    fn stmt(&mut self) -> Result<Stmt, CompileError> {
+        let stmt = match self.peek() {
+            Token::Let => {
+                self.next();
+                let name = self.ident()?;
+                self.expect(Token::Assign, "`=`")?;
+                let value = self.expr()?;
+                Stmt::Let { name, value }
+            }
+            Token::Ident(_) if self.tokens[self.pos + 1].0 == Token::Assign => {
+                let line = self.line();
+                let name = self.ident()?;
+                self.next();
+                let value = self.expr()?;
+                Stmt::Assign { name, value, line }
+            }
+            Token::If => return self.if_stmt(),
+            Token::While => {
+                self.next();
+                let cond = self.expr()?;
+                let body = self.block()?;
+                return Ok(Stmt::While { cond, body });
+            }
+            Token::Return => {
+                self.next();
+                let value = if *self.peek() == Token::Semicolon { None } else { Some(self.expr()?) };
+                Stmt::Return(value)
+            }
+            _ => Stmt::Expr(self.expr()?),
+        };
+
+        self.expect(Token::Semicolon, "`;`")?;
+        Ok(stmt)
+    }
+
+    fn if_stmt(&mut self) -> Result<Stmt, CompileError> {
+        self.expect(Token::If, "`if`")?;
+        let cond = self.expr()?;
+        let then = self.block()?;
+
+        let otherwise = if *self.peek() == Token::Else {
+            self.next();
+
+            if *self.peek() == Token::If {
+                vec![self.if_stmt()?]
+            } else {
+                self.block()?
+            }
+        } else {
+            vec![]
+        };
+
+        Ok(Stmt::If { cond, then, otherwise })
+    }
+
+    /// Comparisons don't chain, so `a < b < c` is an error.
+    fn expr(&mut self) -> Result<Expr, CompileError> {
+        let lhs = self.additive()?;
+
+        let op = match self.peek() {
+            Token::Eq => BinaryOp::Eq,
+            Token::Ne => BinaryOp::Ne,
+            Token::Lt => BinaryOp::Lt,
+            Token::Gt => BinaryOp::Gt,
+            Token::Le => BinaryOp::Le,
+            Token::Ge => BinaryOp::Ge,
+            _ => return Ok(lhs),
+        };
+
+        self.next();
+        let rhs = self.additive()?;
+
+        Ok(Expr::Binary(op, Box::new(lhs), Box::new(rhs)))
+    }
+
+    fn additive(&mut self) -> Result<Expr, CompileError> {
+        let mut lhs = self.term()?;
+
+        loop {
+            let op = match self.peek() {
+                Token::Plus => BinaryOp::Add,
+                Token::Minus => BinaryOp::Sub,
+                _ => return Ok(lhs),
+            };
+
+            self.next();
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, CompileError> {
+        let mut lhs = self.unary()?;
+
+        while *self.peek() == Token::Star {
+            self.next();
+            lhs = Expr::Binary(BinaryOp::Mul, Box::new(lhs), Box::new(self.unary()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        let op = match self.peek() {
+            Token::Minus => UnaryOp::Neg,
+            Token::Not => UnaryOp::Not,
+            _ => return self.primary(),
+        };
+
+        self.next();
+        Ok(Expr::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, CompileError> {
+        let line = self.line();
+
+        match self.peek() {
+            Token::Number(n) => {
+                let n = *n;
+                self.next();
+                Ok(Expr::Number(n))
+            }
+            Token::Ident(_) => {
+                let name = self.ident()?;
+
+                if *self.peek() != Token::LParen {
+                    return Ok(Expr::Var { name, line });
+                }
+
+                self.next();
+                let mut args = vec![];
+                if *self.peek() != Token::RParen {
+                    args.push(self.expr()?);
+                    while *self.peek() == Token::Comma {
+                        self.next();
+                        args.push(self.expr()?);
+                    }
+                }
+                self.expect(Token::RParen, "`,` or `)`")?;
+
+                Ok(Expr::Call { name, args, line })
+            }
+            Token::LParen => {
+                self.next();
+                let expr = self.expr()?;
+                self.expect(Token::RParen, "`)`")?;
+                Ok(expr)
+            }
+            _ => Err(self.error("an expression")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::lexer::tokenize;
+    use super::super::{CompileError, ErrorKind};
+    use super::{parse, BinaryOp, Expr, Stmt, UnaryOp};
+
+    fn parse_expr(source: &str) -> Expr {
+        let functions = parse(tokenize(&format!("fn main() {{ {}; }}", source)).unwrap()).unwrap();
+
+        match &functions[0].body[0] {
+            Stmt::Expr(expr) => expr.clone(),
+            stmt => panic!("not an expression: {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        use Expr::{Binary, Number, Unary};
+
+        assert_eq!(
+            parse_expr("1 + 2 * -3 == 4 - 5 - 6"),
+            Binary(
+                BinaryOp::Eq,
+                Box::new(Binary(
+                    BinaryOp::Add,
+                    Box::new(Number(1)),
+                    Box::new(Binary(
+                        BinaryOp::Mul,
+                        Box::new(Number(2)),
+                        Box::new(Unary(UnaryOp::Neg, Box::new(Number(3))))
+                    )),
+                )),
+                Box::new(Binary(
+                    BinaryOp::Sub,
+                    Box::new(Binary(BinaryOp::Sub, Box::new(Number(4)), Box::new(Number(5)))),
+                    Box::new(Number(6)),
+                )),
+            )
+        );
+    }
+
+    #[test]
+    fn else_if() {
+        let functions = parse(tokenize("fn main() { if 1 { } else if 2 { } else { 3; } }").unwrap()).unwrap();
+
+        assert_eq!(
+            functions[0].body,
+            vec![Stmt::If {
+                cond: Expr::Number(1),
+                then: vec![],
+                otherwise: vec![Stmt::If {
+                    cond: Expr::Number(2),
+                    then: vec![],
+                    otherwise: vec![Stmt::Expr(Expr::Number(3))],
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn unexpected_token() {
+        assert_eq!(
+            parse(tokenize("fn main() {\n  let x = 1 < 2 < 3;\n}").unwrap()),
+            Err(CompileError {
+                line: 2,
+                kind: ErrorKind::UnexpectedToken { found: String::from("`<`"), expected: "`;`" },
+            })
+        );
+        assert_eq!(
+            parse(tokenize("fn main() {").unwrap()),
+            Err(CompileError {
+                line: 1,
+                kind: ErrorKind::UnexpectedToken { found: String::from("end of input"), expected: "an expression" },
+            })
+        );
+    }
+}
@@ -0,0 +1,1323 @@
+use std::collections::hash_map::DefaultHasher;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::rc::Rc;
+
+mod cycle;
+mod error;
+mod extension;
+mod limits;
+mod memory;
+mod observer;
+mod protection;
+pub mod concolic;
+pub mod device;
+pub mod dump;
+pub mod gdb;
+pub mod golden;
+pub mod isa;
+pub mod lang;
+pub mod network;
+pub mod optimize;
+pub mod patch;
+pub mod program;
+pub mod robot;
+pub mod search;
+pub mod session;
+pub mod transpile;
+pub mod verify;
+
+pub use cycle::Cycle;
+pub use error::ExecutionError;
+pub use extension::{Effect, Extensions, Param};
+pub use limits::{LimitExceeded, Limits};
+pub use memory::Memory;
+pub use observer::Observer;
+pub use protection::{Access, Behavior, Protection, Region, RegionKind, SelfModification, Violation};
+
+use cycle::CycleDetector;
+use extension::CustomOpcode;
+use isa::{InvalidMode, Mode, Operation};
+use observer::Attached;
+
+/// Whether the machine can keep going after an instruction.
+enum Step {
+    Continue,
+    Halt,
+    NeedsInput,
+}
+
+/// Why a run stopped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Status {
+    Halted,
+
+    /// The program tried to read input while the input queue was empty. The input instruction
+    /// runs again once the run is resumed.
+    NeedsInput,
+}
+
+#[derive(Debug, Clone)]
+pub struct Intcode {
+    mem: Memory,
+    input: VecDeque<i32>,
+    output: Vec<i32>,
+    pc: usize,
+    relative_base: i32,
+    exit_code: Option<i32>,
+    extensions: Extensions,
+
+    /// Whether each cell has been part of an executed instruction. Shared between clones until
+    /// a clone executes a cell for the first time.
+    executed: Rc<Vec<bool>>,
+
+    /// The cells written by the last instruction.
+    written: Vec<usize>,
+
+    /// Set when cycle detection is enabled.
+    cycles: Option<CycleDetector>,
+
+    /// Set when memory protection is enabled.
+    protection: Option<Protection>,
+
+    observer: Option<Attached>,
+}
+
+impl Intcode {
+    /// Loads a text program.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the program can't be parsed. Use [`program::parse`] to handle the error instead.
+    pub fn load(program: &str, input: Vec<i32>) -> Self {
+        let program = program::parse(program).unwrap_or_else(|e| panic!("{}", e));
+
+        Intcode::new(program, input)
+    }
+
+    pub fn new(program: Vec<i32>, input: Vec<i32>) -> Self {
+        Intcode {
+            mem: Memory::new(&program),
+            input: input.into(),
+            output: vec![],
+            pc: 0,
+            relative_base: 0,
+            exit_code: None,
+            extensions: Extensions::default(),
+            executed: Rc::new(vec![]),
+            written: vec![],
+            cycles: None,
+            protection: None,
+            observer: None,
+        }
+    }
+
+    /// Makes the instructions in `extensions` available to the program.
+    pub fn set_extensions(&mut self, extensions: Extensions) {
+        self.extensions = extensions;
+    }
+
+    pub fn mem(&self) -> &Memory {
+        &self.mem
+    }
+
+    pub fn mem_mut(&mut self) -> &mut Memory {
+        &mut self.mem
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Moves the program counter, for example to resume a program somewhere else.
+    pub fn set_pc(&mut self, pc: usize) {
+        self.pc = pc;
+    }
+
+    /// The base address of parameters in relative mode.
+    pub fn relative_base(&self) -> i32 {
+        self.relative_base
+    }
+
+    pub fn set_relative_base(&mut self, relative_base: i32) {
+        self.relative_base = relative_base;
+    }
+
+    /// Enables or disables cycle detection.
+    ///
+    /// When enabled, the whole state of the machine is hashed after every jump. If the machine
+    /// returns to a state it has been in, it's stuck in an infinite loop, and the run stops with
+    /// [`ExecutionError::Cycle`]. The state includes the input queue but not the output, so a
+    /// loop that only outputs values is reported too.
+    ///
+    /// Only one earlier state is remembered at a time, so a loop may run for a few iterations
+    /// before it's found. A matching hash is confirmed by running one more iteration of the loop
+    /// and comparing the whole state, so a collision never stops a program.
+    ///
+    /// This makes every jump considerably slower, so it's meant for debugging.
+    pub fn set_cycle_detection(&mut self, enabled: bool) {
+        self.cycles = if enabled { Some(CycleDetector::default()) } else { None };
+    }
+
+    /// Enables memory protection.
+    ///
+    /// Every instruction is checked against the attributes of the regions in `protection`, and
+    /// writes to cells that have already been executed are recorded in its report of
+    /// [`Protection::self_modifications`].
+    pub fn set_protection(&mut self, protection: Protection) {
+        self.protection = Some(protection);
+    }
+
+    pub fn protection(&self) -> Option<&Protection> {
+        self.protection.as_ref()
+    }
+
+    /// Attaches an observer that is notified of everything the program does, replacing any
+    /// observer that was attached before. The caller can keep a reference to the observer to
+    /// inspect it afterwards.
+    ///
+    /// Clones of the machine share the observer. Without the `observer` feature, which is on by
+    /// default, the observer is never notified.
+    pub fn set_observer<O: Observer + 'static>(&mut self, observer: Rc<RefCell<O>>) {
+        self.observer = Some(Attached(observer));
+    }
+
+    pub fn clear_observer(&mut self) {
+        self.observer = None;
+    }
+
+    /// Adds a value to the end of the input queue.
+    pub fn push_input(&mut self, value: i32) {
+        self.input.push_back(value);
+    }
+
+    /// Output produced by a run that has not finished yet, such as one that was stopped by a
+    /// limit.
+    pub fn output(&self) -> &[i32] {
+        &self.output
+    }
+
+    /// Removes and returns the output produced so far.
+    pub fn take_output(&mut self) -> Vec<i32> {
+        mem::take(&mut self.output)
+    }
+
+    /// Whether the cell at `addr` has been executed as part of an instruction, either as an opcode
+    /// or as a parameter.
+    pub fn is_executed(&self, addr: usize) -> bool {
+        self.executed.get(addr).copied().unwrap_or(false)
+    }
+
+    /// The addresses of the cells written by the last instruction that was executed.
+    pub fn written(&self) -> &[usize] {
+        &self.written
+    }
+
+    /// A hash of everything that determines how the machine will continue: the program counter,
+    /// relative base, memory and pending input.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.pc.hash(&mut hasher);
+        self.relative_base.hash(&mut hasher);
+        self.mem.hash(&mut hasher);
+        self.input.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The exit code passed by a custom instruction that halted the machine.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Runs the program until it halts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the program runs into an error, such as an unknown opcode.
+    pub fn execute(&mut self) -> Vec<i32> {
+        self.execute_with_limits(&Limits::default()).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Runs the program until it halts, a limit is hit or an error occurs.
+    ///
+    /// If a limit is hit, the instruction at the program counter has not been executed yet and
+    /// the output so far is kept, so calling this method again resumes the run. Running out of
+    /// input is an error.
+    pub fn execute_with_limits(&mut self, limits: &Limits) -> Result<Vec<i32>, ExecutionError> {
+        match self.run(limits)? {
+            Status::Halted => Ok(self.take_output()),
+            Status::NeedsInput => Err(ExecutionError::MissingInput { addr: self.pc }),
+        }
+    }
+
+    /// Runs the program until it halts or needs more input than there is in the queue.
+    ///
+    /// Output is kept in the machine until it is taken with [`Intcode::take_output`]. Limits and
+    /// errors behave like they do for [`Intcode::execute_with_limits`].
+    pub fn run(&mut self, limits: &Limits) -> Result<Status, ExecutionError> {
+        let mut steps = 0;
+
+        loop {
+            limits.check(steps)?;
+
+            match self.execute_instruction(limits)? {
+                Step::Continue => steps += 1,
+                Step::Halt => return Ok(Status::Halted),
+                Step::NeedsInput => return Ok(Status::NeedsInput),
+            }
+        }
+    }
+
+    /// Executes the instruction at the program counter, unless the machine has halted or needs
+    /// input that isn't in the queue, in which case the status is returned instead.
+    ///
+    /// Only the memory and output limits are checked.
+    pub fn step(&mut self, limits: &Limits) -> Result<Option<Status>, ExecutionError> {
+        let status = match self.execute_instruction(limits)? {
+            Step::Continue => None,
+            Step::Halt => Some(Status::Halted),
+            Step::NeedsInput => Some(Status::NeedsInput),
+        };
+
+        Ok(status)
+    }
+
+    /// Executes the instruction at the program counter.
+    ///
+    /// Writes happen last, so an instruction that fails a limit check leaves the machine
+    /// untouched.
+    fn execute_instruction(&mut self, limits: &Limits) -> Result<Step, ExecutionError> {
+        self.written.clear();
+
+        let mut pc = self.pc;
+        let instr = self.read(pc);
+
+        // An input instruction without input is tried again when the run resumes, so it isn't
+        // checked or observed until there's input for it.
+        let waiting = isa::lookup(isa::decode(instr).opcode)
+            .is_some_and(|instruction| matches!(instruction.operation, Operation::Input));
+        if waiting && self.input.is_empty() {
+            return Ok(Step::NeedsInput);
+        }
+
+        self.check_protection(Access::Execute, pc)?;
+        self.observe(|observer| observer.before_instruction(pc, instr));
+        pc += 1;
+
+        let isa::Decoded { opcode, modes } = isa::decode(instr);
+        let mut jumped = false;
+
+        let instruction = match isa::lookup(opcode) {
+            Some(instruction) => instruction,
+            None => match self.extensions.get(opcode) {
+                Some(custom) => {
+                    let effect = self.execute_custom(&custom, instr, pc, limits)?;
+                    self.mark_executed(self.pc, 1 + custom.params.len());
+
+                    match effect {
+                        Effect::Continue => pc += custom.params.len(),
+                        Effect::Jump(target) => {
+                            self.observe(|observer| observer.jump_taken(self.pc, target));
+                            pc = target;
+                            jumped = true;
+                        }
+                        Effect::Halt(code) => {
+                            self.observe(|observer| observer.halt(self.pc));
+                            self.exit_code = Some(code);
+                            return Ok(Step::Halt);
+                        }
+                    }
+
+                    return self.finish_instruction(pc, jumped);
+                }
+                None => return Err(ExecutionError::UnknownOpcode { instr, addr: self.pc }),
+            },
+        };
+
+        // The values of the read parameters, in order, and the address of the write parameter.
+        let mut args = [0; 3];
+        let mut reads = 0;
+        let mut dst = None;
+
+        let modes = modes.checked(instruction.params);
+
+        for ((i, param), mode) in instruction.params.iter().enumerate().zip(modes) {
+            let mode = mode.map_err(|invalid| self.invalid_mode(instr, invalid))?;
+
+            match param {
+                Param::Read => {
+                    args[reads] = self.param(pc + i, mode)?;
+                    reads += 1;
+                }
+                Param::Write => dst = Some(self.address(pc + i, mode)?),
+            }
+        }
+
+        pc += instruction.params.len();
+
+        match instruction.operation {
+            Operation::Compute(compute) => {
+                let result = compute.apply(args[0], args[1]).ok_or(ExecutionError::Overflow { addr: self.pc })?;
+                self.write(dst.unwrap(), result, limits)?;
+            }
+            Operation::Input => {
+                let dst = dst.unwrap();
+                self.reserve(dst, limits)?;
+
+                let value = self.input[0];
+                self.write(dst, value, limits)?;
+                self.input.pop_front();
+                self.observe(|observer| observer.input(value));
+            }
+            Operation::Output => {
+                if let Some(max) = limits.max_output {
+                    if self.output.len() >= max {
+                        return Err(LimitExceeded::Output.into());
+                    }
+                }
+
+                self.observe(|observer| observer.output(args[0]));
+                self.output.push(args[0]);
+            }
+            Operation::JumpIf(condition) => {
+                if condition.holds(args[0]) {
+                    pc = usize::try_from(args[1])
+                        .map_err(|_| ExecutionError::InvalidJumpTarget { addr: self.pc, target: args[1] })?;
+                    self.observe(|observer| observer.jump_taken(self.pc, pc));
+                }
+                jumped = true;
+            }
+            Operation::AdjustRelativeBase => {
+                self.relative_base =
+                    self.relative_base.checked_add(args[0]).ok_or(ExecutionError::Overflow { addr: self.pc })?;
+            }
+            Operation::Halt => {
+                self.observe(|observer| observer.halt(self.pc));
+                self.mark_executed(self.pc, instruction.size());
+                return Ok(Step::Halt);
+            }
+        }
+
+        self.mark_executed(self.pc, instruction.size());
+
+        self.finish_instruction(pc, jumped)
+    }
+
+    /// Moves the program counter to `pc` after an instruction has been executed.
+    fn finish_instruction(&mut self, pc: usize, jumped: bool) -> Result<Step, ExecutionError> {
+        self.pc = pc;
+
+        if let Some(cycles) = &mut self.cycles {
+            cycles.tick();
+        }
+
+        if jumped {
+            self.detect_cycle()?;
+        }
+
+        Ok(Step::Continue)
+    }
+
+    /// Checks whether the machine has been in its current state before, if cycle detection is
+    /// enabled.
+    ///
+    /// The loop is confirmed, and its addresses are found, by running one more iteration on a
+    /// clone, so the handlers of any custom instructions in the loop are called again.
+    fn detect_cycle(&mut self) -> Result<(), ExecutionError> {
+        if self.cycles.is_none() {
+            return Ok(());
+        }
+
+        let state = self.state_hash();
+        let period = match self.cycles.as_mut().and_then(|cycles| cycles.visit(state)) {
+            Some(period) => period,
+            None => return Ok(()),
+        };
+
+        let mut replay = self.clone();
+        replay.cycles = None;
+        replay.protection = None;
+        replay.observer = None;
+
+        let (mut first, mut last) = (self.pc, self.pc);
+
+        for _ in 0..period {
+            // A program that stops can't be in a loop, so the hashes only collided.
+            match replay.execute_instruction(&Limits::default()) {
+                Ok(Step::Continue) => (),
+                _ => return Ok(()),
+            }
+
+            first = first.min(replay.pc);
+            last = last.max(replay.pc);
+        }
+
+        let repeats = replay.pc == self.pc
+            && replay.relative_base == self.relative_base
+            && replay.mem == self.mem
+            && replay.input == self.input;
+
+        if !repeats {
+            return Ok(());
+        }
+
+        Err(ExecutionError::Cycle(Cycle { first, last, period }))
+    }
+
+    fn mark_executed(&mut self, addr: usize, len: usize) {
+        if (addr..addr + len).all(|addr| self.is_executed(addr)) {
+            return;
+        }
+
+        let executed = Rc::make_mut(&mut self.executed);
+
+        if executed.len() < addr + len {
+            executed.resize(addr + len, false);
+        }
+
+        for executed in &mut executed[addr..addr + len] {
+            *executed = true;
+        }
+    }
+
+    /// Calls the handler of a custom instruction whose parameters start at `pc` and stores its
+    /// results.
+    ///
+    /// Memory for the results is reserved before the handler is called, so the handler never
+    /// runs for an instruction that fails a limit check.
+    fn execute_custom(
+        &mut self,
+        custom: &CustomOpcode,
+        instr: i32,
+        pc: usize,
+        limits: &Limits,
+    ) -> Result<Effect, ExecutionError> {
+        let mut args = vec![];
+        let mut dsts = vec![];
+
+        let modes = isa::decode(instr).modes.checked(&custom.params);
+
+        for ((i, param), mode) in custom.params.iter().enumerate().zip(modes) {
+            let mode = mode.map_err(|invalid| self.invalid_mode(instr, invalid))?;
+
+            match param {
+                Param::Read => args.push(self.param(pc + i, mode)?),
+                Param::Write => {
+                    let dst = self.address(pc + i, mode)?;
+                    self.check_protection(Access::Write, dst)?;
+                    self.reserve(dst, limits)?;
+                    dsts.push(dst);
+                }
+            }
+        }
+
+        let mut results = vec![0; dsts.len()];
+        let effect = custom.call(&args, &mut results);
+
+        for (dst, value) in dsts.into_iter().zip(results) {
+            self.observe(|observer| observer.memory_write(dst, self.mem[dst], value));
+            self.record_self_modification(dst, value);
+            self.mem[dst] = value;
+            self.written.push(dst);
+        }
+
+        Ok(effect)
+    }
+
+    /// Reads a cell. Memory past the end of the program reads as zero.
+    fn read(&self, addr: usize) -> i32 {
+        self.mem.get(addr)
+    }
+
+    /// The value of the read parameter at `addr`.
+    fn param(&self, addr: usize, mode: Mode) -> Result<i32, ExecutionError> {
+        match mode {
+            Mode::Immediate => Ok(self.read(addr)),
+            mode => {
+                let addr = self.address(addr, mode)?;
+                let value = self.read(addr);
+                self.observe(|observer| observer.memory_read(addr, value));
+                Ok(value)
+            }
+        }
+    }
+
+    /// The address that the parameter at `addr` points to. Immediate parameters are never
+    /// addresses, so they're rejected when the modes are decoded.
+    fn address(&self, addr: usize, mode: Mode) -> Result<usize, ExecutionError> {
+        let value = self.read(addr);
+
+        let target = match mode {
+            Mode::Relative => {
+                self.relative_base.checked_add(value).ok_or(ExecutionError::Overflow { addr: self.pc })?
+            }
+            _ => value,
+        };
+
+        usize::try_from(target).map_err(|_| ExecutionError::NegativeAddress { addr: self.pc, target })
+    }
+
+    /// The error for an invalid mode in `instr`, the instruction at the program counter.
+    fn invalid_mode(&self, instr: i32, invalid: InvalidMode) -> ExecutionError {
+        ExecutionError::InvalidMode { instr, addr: self.pc, param: invalid.param }
+    }
+
+    /// Grows memory so that `addr` can be written, if the memory limit allows it.
+    fn reserve(&mut self, addr: usize, limits: &Limits) -> Result<(), LimitExceeded> {
+        if addr < self.mem.len() {
+            return Ok(());
+        }
+
+        if let Some(max) = limits.max_memory {
+            if addr >= max {
+                return Err(LimitExceeded::Memory);
+            }
+        }
+
+        self.mem.grow(addr + 1);
+
+        Ok(())
+    }
+
+    fn write(&mut self, addr: usize, value: i32, limits: &Limits) -> Result<(), ExecutionError> {
+        self.check_protection(Access::Write, addr)?;
+        self.reserve(addr, limits)?;
+        self.observe(|observer| observer.memory_write(addr, self.read(addr), value));
+        self.record_self_modification(addr, value);
+        self.mem[addr] = value;
+        self.written.push(addr);
+        Ok(())
+    }
+
+    /// Notifies the observer, if one is attached.
+    #[inline]
+    fn observe(&self, event: impl FnOnce(&mut dyn Observer)) {
+        if !cfg!(feature = "observer") {
+            return;
+        }
+
+        if let Some(observer) = &self.observer {
+            event(&mut *observer.0.borrow_mut());
+        }
+    }
+
+    /// Checks an access by the instruction at the program counter against the protected
+    /// regions, if memory protection is enabled.
+    fn check_protection(&self, access: Access, addr: usize) -> Result<(), ExecutionError> {
+        match &self.protection {
+            Some(protection) => protection.check(access, addr, self.pc).map_err(ExecutionError::Protection),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds a write to the report of self-modifications if the cell has been executed. The cells
+    /// of an instruction only count as executed once it has finished executing.
+    fn record_self_modification(&mut self, addr: usize, new: i32) {
+        if self.protection.is_none() || !self.is_executed(addr) {
+            return;
+        }
+
+        let pc = self.pc;
+        let old = self.read(addr);
+        let protection = self.protection.as_mut().unwrap();
+        let region = protection.region(addr).map(|region| region.kind);
+
+        protection.record(SelfModification { pc, addr, old, new, region });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Instant;
+
+    use crate::program;
+
+    use super::{
+        Access, Behavior, Cycle, Effect, ExecutionError, Extensions, Intcode, LimitExceeded, Limits, Observer,
+        Param, Protection, RegionKind, SelfModification, Status, Violation,
+    };
+
+    #[test]
+    fn day2_test_case_1() {
+        let mut computer = Intcode::load("1,0,0,0,99", vec![]);
+        computer.execute();
+        assert_eq!(computer.mem, &[2, 0, 0, 0, 99]);
+    }
+
+    #[test]
+    fn day2_test_case_2() {
+        let mut computer = Intcode::load("2,3,0,3,99", vec![]);
+        computer.execute();
+
+        assert_eq!(computer.mem, &[2, 3, 0, 6, 99]);
+    }
+
+    #[test]
+    fn day2_test_case_3() {
+        let mut computer = Intcode::load("2,4,4,5,99,0", vec![]);
+        computer.execute();
+        assert_eq!(computer.mem, &[2, 4, 4, 5, 99, 9801]);
+    }
+
+    #[test]
+    fn day2_test_case_4() {
+        let mut computer = Intcode::load("1,1,1,4,99,5,6,0,99", vec![]);
+        computer.execute();
+        assert_eq!(computer.mem, &[30, 1, 1, 4, 2, 5, 6, 0, 99]);
+    }
+
+    #[test]
+    fn example1() {
+        let mut computer = Intcode::load("3,0,4,0,99", vec![1337]);
+        let output = computer.execute();
+        assert_eq!(output, vec![1337]);
+    }
+
+    #[test]
+    fn example2() {
+        let mut computer = Intcode::load("1002,4,3,4,33", vec![]);
+        computer.execute();
+        assert_eq!(computer.mem[4], 99);
+    }
+
+    #[test]
+    fn example3() {
+        let mut computer = Intcode::load("1101,100,-1,4,0", vec![]);
+        computer.execute();
+        assert_eq!(computer.mem[4], 99);
+    }
+
+    #[test]
+    fn position_mode_equal() {
+        let program = "3,9,8,9,10,9,4,9,99,-1,8";
+
+        let mut computer = Intcode::load(program, vec![8]);
+        assert_eq!(computer.execute(), vec![1]);
+
+
+        let mut computer = Intcode::load(program, vec![99]);
+        assert_eq!(computer.execute(), vec![0]);
+    }
+
+    #[test]
+    fn position_mode_less_than() {
+        let program = "3,9,7,9,10,9,4,9,99,-1,8";
+
+
+        let mut computer = Intcode::load(program, vec![3]);
+        assert_eq!(computer.execute(), vec![1]);
+
+
+        let mut computer = Intcode::load(program, vec![99]);
+        assert_eq!(computer.execute(), vec![0]);
+    }
+
+    #[test]
+    fn immediate_mode_equal() {
+        let program = "3,3,1108,-1,8,3,4,3,99";
+
+        let mut computer = Intcode::load(program, vec![8]);
+        assert_eq!(computer.execute(), vec![1]);
+
+
+        let mut computer = Intcode::load(program, vec![99]);
+        assert_eq!(computer.execute(), vec![0]);
+    }
+
+    #[test]
+    fn immediate_mode_less_than() {
+        let program = "3,3,1107,-1,8,3,4,3,99";
+
+        let mut computer = Intcode::load(program, vec![3]);
+        assert_eq!(computer.execute(), vec![1]);
+
+
+        let mut computer = Intcode::load(program, vec![99]);
+        assert_eq!(computer.execute(), vec![0]);
+    }
+
+    #[test]
+    fn position_mode_jump() {
+        let program = "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9";
+
+
+        let mut computer = Intcode::load(program, vec![0]);
+        assert_eq!(computer.execute(), vec![0]);
+
+
+        let mut computer = Intcode::load(program, vec![99]);
+        assert_eq!(computer.execute(), vec![1]);
+    }
+
+    #[test]
+    fn immediate_mode_jump() {
+        let program = "3,3,1105,-1,9,1101,0,0,12,4,12,99,1";
+
+
+        let mut computer = Intcode::load(program, vec![0]);
+        assert_eq!(computer.execute(), vec![0]);
+
+
+        let mut computer = Intcode::load(program, vec![99]);
+        assert_eq!(computer.execute(), vec![1]);
+    }
+
+    #[test]
+    fn larger_example() {
+        let program = program::COMPARE_TO_8;
+
+        let mut computer = Intcode::load(program, vec![3]);
+        assert_eq!(computer.execute(), vec![999]);
+
+        let mut computer = Intcode::load(program, vec![8]);
+        assert_eq!(computer.execute(), vec![1000]);
+
+        let mut computer = Intcode::load(program, vec![99]);
+        assert_eq!(computer.execute(), vec![1001]);
+    }
+
+    #[test]
+    fn overflow() {
+        let mut computer = Intcode::load("1101,1,1,9,1002,9,2147483647,9,99,0", vec![]);
+
+        assert_eq!(computer.execute_with_limits(&Limits::default()), Err(ExecutionError::Overflow { addr: 4 }));
+        assert_eq!(computer.mem()[9], 2);
+    }
+
+    #[test]
+    fn invalid_instructions() {
+        let error = |program: &str| Intcode::load(program, vec![]).execute_with_limits(&Limits::default());
+
+        assert_eq!(error("304,0,99"), Err(ExecutionError::InvalidMode { instr: 304, addr: 0, param: 0 }));
+        assert_eq!(
+            error("11101,1,1,0,99"),
+            Err(ExecutionError::InvalidMode { instr: 11101, addr: 0, param: 2 })
+        );
+        assert_eq!(error("4,-3,99"), Err(ExecutionError::NegativeAddress { addr: 0, target: -3 }));
+        assert_eq!(error("104,0,1105,1,-1"), Err(ExecutionError::InvalidJumpTarget { addr: 2, target: -1 }));
+        assert_eq!(error("109,2147483647,109,1,99"), Err(ExecutionError::Overflow { addr: 2 }));
+        assert_eq!(error("109,2147483647,204,1,99"), Err(ExecutionError::Overflow { addr: 2 }));
+
+        // The machine stops before the instruction, so it can be inspected.
+        let mut computer = Intcode::load("109,-5,204,1,99", vec![]);
+        assert_eq!(
+            computer.execute_with_limits(&Limits::default()),
+            Err(ExecutionError::NegativeAddress { addr: 2, target: -4 })
+        );
+        assert_eq!((computer.pc(), computer.relative_base()), (2, -5));
+    }
+
+    #[test]
+    fn instruction_limit() {
+        // Outputs 1 forever.
+        let mut computer = Intcode::load("104,1,1105,1,0", vec![]);
+        let limits = Limits { max_instructions: Some(5), ..Limits::default() };
+
+        assert_eq!(computer.execute_with_limits(&limits), Err(ExecutionError::Limit(LimitExceeded::Instructions)));
+        assert_eq!(computer.pc(), 2);
+        assert_eq!(computer.output(), &[1, 1, 1]);
+
+        // Resuming gets a fresh budget.
+        assert_eq!(computer.execute_with_limits(&limits), Err(ExecutionError::Limit(LimitExceeded::Instructions)));
+        assert_eq!(computer.pc(), 0);
+        assert_eq!(computer.output(), &[1, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn memory_limit() {
+        let program = "1101,1,2,10,99";
+        let limits = Limits { max_memory: Some(10), ..Limits::default() };
+
+        let mut computer = Intcode::load(program, vec![]);
+        assert_eq!(computer.execute_with_limits(&limits), Err(ExecutionError::Limit(LimitExceeded::Memory)));
+        assert_eq!(computer.pc(), 0);
+        assert_eq!(computer.mem(), &[1101, 1, 2, 10, 99]);
+
+        let limits = Limits { max_memory: Some(11), ..Limits::default() };
+        assert_eq!(computer.execute_with_limits(&limits), Ok(vec![]));
+        assert_eq!(computer.mem(), &[1101, 1, 2, 10, 99, 0, 0, 0, 0, 0, 3]);
+    }
+
+    #[test]
+    fn output_limit() {
+        let mut computer = Intcode::load("104,1,104,2,104,3,99", vec![]);
+        let limits = Limits { max_output: Some(2), ..Limits::default() };
+
+        assert_eq!(computer.execute_with_limits(&limits), Err(ExecutionError::Limit(LimitExceeded::Output)));
+        assert_eq!(computer.pc(), 4);
+        assert_eq!(computer.output(), &[1, 2]);
+
+        assert_eq!(computer.execute_with_limits(&Limits::default()), Ok(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn input_is_kept_when_limit_is_hit() {
+        let mut computer = Intcode::load("3,5,4,5,99", vec![42]);
+        let limits = Limits { max_memory: Some(5), ..Limits::default() };
+
+        assert_eq!(computer.execute_with_limits(&limits), Err(ExecutionError::Limit(LimitExceeded::Memory)));
+        assert_eq!(computer.execute(), vec![42]);
+    }
+
+    #[test]
+    fn deadline() {
+        let mut computer = Intcode::load("1105,1,0", vec![]);
+        let limits = Limits { deadline: Some(Instant::now()), ..Limits::default() };
+
+        assert_eq!(computer.execute_with_limits(&limits), Err(ExecutionError::Limit(LimitExceeded::Deadline)));
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let mut computer = Intcode::load("1101,1,2,5,42,0", vec![]);
+
+        assert_eq!(
+            computer.execute_with_limits(&Limits::default()),
+            Err(ExecutionError::UnknownOpcode { instr: 42, addr: 4 })
+        );
+        assert_eq!(
+            ExecutionError::UnknownOpcode { instr: 1042, addr: 4 }.to_string(),
+            "unknown opcode 42 in instruction 1042 at address 4"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown opcode 42")]
+    fn execute_panics_on_unknown_opcode() {
+        Intcode::load("42,99", vec![]).execute();
+    }
+
+    #[test]
+    fn debug_print_opcode() {
+        let printed = Rc::new(RefCell::new(vec![]));
+
+        let mut extensions = Extensions::new();
+        let sink = Rc::clone(&printed);
+        extensions.register(50, &[Param::Read, Param::Read], move |args, _| {
+            sink.borrow_mut().push(args.to_vec());
+            Effect::Continue
+        });
+
+        let mut computer = Intcode::load("1050,7,6,50,6,6,99,3", vec![]);
+        computer.set_extensions(extensions);
+
+        assert_eq!(computer.execute(), vec![]);
+        assert_eq!(*printed.borrow(), vec![vec![3, 6], vec![99, 99]]);
+    }
+
+    #[test]
+    fn halt_with_code_opcode() {
+        let mut extensions = Extensions::new();
+        extensions.register(51, &[Param::Read], |args, _| Effect::Halt(args[0]));
+
+        let mut computer = Intcode::load("104,7,151,3,104,8,99", vec![]);
+        computer.set_extensions(extensions);
+
+        assert_eq!(computer.execute(), vec![7]);
+        assert_eq!(computer.exit_code(), Some(3));
+        assert_eq!(computer.pc(), 2);
+    }
+
+    #[test]
+    fn host_call_opcode() {
+        let mut extensions = Extensions::new();
+        extensions.register(52, &[Param::Read, Param::Write, Param::Write], |args, results| {
+            results[0] = args[0] / 10;
+            results[1] = args[0] % 10;
+            Effect::Continue
+        });
+
+        let mut computer = Intcode::load("152,42,6,7,99,0,0", vec![]);
+        computer.set_extensions(extensions);
+        computer.execute();
+
+        assert_eq!(computer.mem(), &[152, 42, 6, 7, 99, 0, 4, 2]);
+    }
+
+    #[test]
+    fn jump_opcode() {
+        let mut extensions = Extensions::new();
+        extensions.register(53, &[Param::Read], |args, _| Effect::Jump(args[0] as usize));
+
+        let mut computer = Intcode::load("153,4,104,1,104,2,99", vec![]);
+        computer.set_extensions(extensions);
+
+        assert_eq!(computer.execute(), vec![2]);
+    }
+
+    #[test]
+    fn custom_opcode_respects_memory_limit() {
+        let calls = Rc::new(RefCell::new(0));
+
+        let mut extensions = Extensions::new();
+        let counter = Rc::clone(&calls);
+        extensions.register(54, &[Param::Write], move |_, results| {
+            *counter.borrow_mut() += 1;
+            results[0] = 1;
+            Effect::Continue
+        });
+
+        let mut computer = Intcode::load("54,10,99", vec![]);
+        computer.set_extensions(extensions);
+
+        let limits = Limits { max_memory: Some(10), ..Limits::default() };
+        assert_eq!(
+            computer.execute_with_limits(&limits),
+            Err(ExecutionError::Limit(LimitExceeded::Memory))
+        );
+        assert_eq!(*calls.borrow(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "opcode 4 is a standard instruction")]
+    fn cannot_override_standard_opcode() {
+        Extensions::new().register(4, &[Param::Read], |_, _| Effect::Continue);
+    }
+
+    #[test]
+    fn run_until_input_is_needed() {
+        // Outputs the sum of pairs of inputs forever.
+        let mut computer = Intcode::load("3,13,3,14,1,13,14,15,4,15,1105,1,0,0,0,0", vec![1]);
+
+        assert_eq!(computer.run(&Limits::default()), Ok(Status::NeedsInput));
+        assert_eq!(computer.pc(), 2);
+
+        computer.push_input(2);
+        computer.push_input(3);
+        assert_eq!(computer.run(&Limits::default()), Ok(Status::NeedsInput));
+        assert_eq!(computer.take_output(), vec![3]);
+
+        computer.push_input(4);
+        computer.push_input(5);
+        assert_eq!(computer.run(&Limits::default()), Ok(Status::NeedsInput));
+        assert_eq!(computer.output(), &[7]);
+
+        computer.push_input(6);
+        assert_eq!(computer.run(&Limits::default()), Ok(Status::NeedsInput));
+        assert_eq!(computer.take_output(), vec![7, 11]);
+    }
+
+    #[test]
+    fn missing_input() {
+        let mut computer = Intcode::load("3,0,3,0,99", vec![1]);
+
+        assert_eq!(
+            computer.execute_with_limits(&Limits::default()),
+            Err(ExecutionError::MissingInput { addr: 2 })
+        );
+
+        computer.push_input(2);
+        assert_eq!(computer.execute(), vec![]);
+        assert_eq!(computer.mem()[0], 2);
+    }
+
+    #[test]
+    fn relative_base_quine() {
+        let program = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+
+        let mut computer = Intcode::load(program, vec![]);
+        assert_eq!(computer.execute(), program::parse(program).unwrap());
+        assert_eq!(computer.relative_base(), 16);
+    }
+
+    #[test]
+    fn relative_mode_writes() {
+        // Reads an input to 10 + 3, then stores its double at 10 + 4.
+        let mut computer = Intcode::load("109,10,203,3,21202,3,2,4,204,4,99", vec![21]);
+
+        assert_eq!(computer.execute(), vec![42]);
+        assert_eq!(computer.mem()[13], 21);
+        assert_eq!(computer.mem()[14], 42);
+    }
+
+    #[test]
+    fn detects_jump_to_self() {
+        let mut computer = Intcode::load("1105,1,0", vec![]);
+        computer.set_cycle_detection(true);
+
+        assert_eq!(
+            computer.execute_with_limits(&Limits::default()),
+            Err(ExecutionError::Cycle(Cycle { first: 0, last: 0, period: 1 }))
+        );
+    }
+
+    #[test]
+    fn detects_loop() {
+        // Keeps storing 0 at address 9, which doesn't change the state.
+        let mut computer = Intcode::load("1101,0,0,9,104,1,1105,1,0,0", vec![]);
+        computer.set_cycle_detection(true);
+
+        let error = computer.execute_with_limits(&Limits::default()).unwrap_err();
+        assert_eq!(error, ExecutionError::Cycle(Cycle { first: 0, last: 6, period: 3 }));
+        assert_eq!(
+            error.to_string(),
+            "infinite loop between addresses 0 and 6, repeating every 3 instructions"
+        );
+
+        // The machine stopped after the second iteration.
+        assert_eq!(computer.pc(), 0);
+        assert_eq!(computer.output(), &[1, 1]);
+    }
+
+    #[test]
+    fn loop_that_ends_is_not_a_cycle() {
+        // Counts to 5.
+        let mut computer = Intcode::load("1001,12,1,12,1007,12,5,13,1005,13,0,99,0,0", vec![]);
+        computer.set_cycle_detection(true);
+
+        assert_eq!(computer.execute_with_limits(&Limits::default()), Ok(vec![]));
+        assert_eq!(computer.mem()[12], 5);
+    }
+
+    #[test]
+    fn hash_collision_is_not_a_cycle() {
+        // Counts to 5, jumping back every 3 instructions.
+        let mut computer = Intcode::load("1001,12,1,12,1007,12,5,13,1005,13,0,99,0,0", vec![]);
+        computer.set_cycle_detection(true);
+
+        for _ in 0..3 {
+            computer.step(&Limits::default()).unwrap();
+        }
+
+        // Pretend that the state after the next jump has the same hash as the current one.
+        let mut next = computer.clone();
+        for _ in 0..3 {
+            next.step(&Limits::default()).unwrap();
+        }
+        computer.cycles.as_mut().unwrap().remember(next.state_hash());
+
+        assert_eq!(computer.execute_with_limits(&Limits::default()), Ok(vec![]));
+        assert_eq!(computer.mem()[12], 5);
+    }
+
+    #[test]
+    fn long_loop_that_ends_is_not_a_cycle() {
+        // Counts to 100000.
+        let mut computer = Intcode::load("1001,12,1,12,1007,12,100000,13,1005,13,0,99,0,0", vec![]);
+        computer.set_cycle_detection(true);
+
+        assert_eq!(computer.execute_with_limits(&Limits::default()), Ok(vec![]));
+        assert_eq!(computer.mem()[12], 100_000);
+    }
+
+    #[test]
+    fn relative_base_is_part_of_the_state() {
+        let mut computer = Intcode::load("109,1,1105,1,0", vec![]);
+        computer.set_cycle_detection(true);
+
+        let limits = Limits { max_instructions: Some(100), ..Limits::default() };
+        assert_eq!(computer.execute_with_limits(&limits), Err(ExecutionError::Limit(LimitExceeded::Instructions)));
+    }
+
+    #[test]
+    fn traps_writes_to_code() {
+        let mut protection = Protection::new();
+        protection.add_region(0..9, RegionKind::Code, Behavior::Trap);
+        protection.add_region(9..10, RegionKind::Data, Behavior::Trap);
+
+        let mut computer = Intcode::load("1101,1,1,9,1101,1,1,0,99,0", vec![]);
+        computer.set_protection(protection);
+
+        let error = computer.execute_with_limits(&Limits::default()).unwrap_err();
+        let violation = Violation { access: Access::Write, addr: 0, pc: 4, region: RegionKind::Code };
+        assert_eq!(error, ExecutionError::Protection(violation));
+        assert_eq!(error.to_string(), "the instruction at address 4 wrote to address 0 in a code region");
+
+        // The trapping instruction wasn't executed.
+        assert_eq!(computer.pc(), 4);
+        assert_eq!(computer.mem()[0], 1101);
+        assert_eq!(computer.mem()[9], 2);
+    }
+
+    #[test]
+    fn trapped_input_is_not_consumed() {
+        let mut protection = Protection::new();
+        protection.add_region(0..3, RegionKind::Code, Behavior::Trap);
+
+        let mut computer = Intcode::load("3,0,99", vec![5]);
+        computer.set_protection(protection);
+        assert!(computer.execute_with_limits(&Limits::default()).is_err());
+
+        computer.set_protection(Protection::new());
+        computer.execute();
+        assert_eq!(computer.mem()[0], 5);
+    }
+
+    #[test]
+    fn warns_about_violations() {
+        let warnings = Rc::new(RefCell::new(vec![]));
+
+        let mut protection = Protection::new();
+        protection.add_region(0..7, RegionKind::Code, Behavior::Warn);
+        protection.add_region(7..8, RegionKind::Stack, Behavior::Warn);
+        protection.on_warning({
+            let warnings = Rc::clone(&warnings);
+            move |violation| warnings.borrow_mut().push(*violation)
+        });
+
+        // Writes to 5, then jumps to the halt in the stack region.
+        let mut computer = Intcode::load("1101,1,6,5,1105,1,7,99", vec![]);
+        computer.set_protection(protection);
+        assert_eq!(computer.execute(), vec![]);
+
+        assert_eq!(
+            *warnings.borrow(),
+            vec![
+                Violation { access: Access::Write, addr: 5, pc: 0, region: RegionKind::Code },
+                Violation { access: Access::Execute, addr: 7, pc: 7, region: RegionKind::Stack },
+            ]
+        );
+        assert_eq!(computer.mem()[5], 7);
+    }
+
+    #[test]
+    fn warns_once_when_waiting_for_input() {
+        let warnings = Rc::new(RefCell::new(vec![]));
+
+        let mut protection = Protection::new();
+        protection.add_region(0..3, RegionKind::Data, Behavior::Warn);
+        protection.on_warning({
+            let warnings = Rc::clone(&warnings);
+            move |violation| warnings.borrow_mut().push(*violation)
+        });
+
+        let mut computer = Intcode::load("3,5,99", vec![]);
+        computer.set_protection(protection);
+        assert_eq!(computer.run(&Limits::default()), Ok(Status::NeedsInput));
+        assert_eq!(computer.run(&Limits::default()), Ok(Status::NeedsInput));
+        computer.push_input(7);
+        assert_eq!(computer.run(&Limits::default()), Ok(Status::Halted));
+
+        assert_eq!(
+            *warnings.borrow(),
+            vec![
+                Violation { access: Access::Execute, addr: 0, pc: 0, region: RegionKind::Data },
+                Violation { access: Access::Execute, addr: 2, pc: 2, region: RegionKind::Data },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_self_modification() {
+        let mut protection = Protection::new();
+        protection.add_region(0..11, RegionKind::Code, Behavior::Allow);
+
+        // Overwrites the first instruction with a halt, then jumps back to it.
+        let mut computer = Intcode::load("1101,0,0,12,1101,50,49,0,1105,1,0,0,0", vec![]);
+        computer.set_protection(protection);
+        computer.execute();
+
+        let protection = computer.protection().unwrap();
+        assert_eq!(
+            protection.self_modifications(),
+            &[SelfModification { pc: 4, addr: 0, old: 1101, new: 99, region: Some(RegionKind::Code) }]
+        );
+
+        // Without protection, nothing is recorded.
+        let mut computer = Intcode::load("1101,0,0,12,1101,50,49,0,1105,1,0,0,0", vec![]);
+        computer.execute();
+        assert!(computer.protection().is_none());
+    }
+
+    #[test]
+    fn reports_self_modification_outside_regions() {
+        // Doubles the first operand of the add, which has been executed, until it reaches 8. The add
+        // overwrites its own opcode too, but that isn't reported because it happens while the add
+        // is executed for the first time.
+        let mut computer = Intcode::load("1001,1,1,0,1002,1,2,1,1007,1,8,15,1005,15,4,0,99", vec![]);
+        computer.set_protection(Protection::new());
+        computer.execute_with_limits(&Limits { max_instructions: Some(100), ..Limits::default() }).ok();
+
+        let modifications = computer.protection().unwrap().self_modifications();
+        assert!(modifications.iter().all(|modification| modification.region.is_none()));
+        assert_eq!(modifications.iter().map(|modification| modification.addr).collect::<Vec<_>>(), vec![1, 1, 1]);
+    }
+
+    /// Records every event as text.
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl Observer for Recorder {
+        fn before_instruction(&mut self, pc: usize, instr: i32) {
+            self.0.push(format!("{}: {}", pc, instr));
+        }
+
+        fn memory_read(&mut self, addr: usize, value: i32) {
+            self.0.push(format!("read {} = {}", addr, value));
+        }
+
+        fn memory_write(&mut self, addr: usize, old: i32, new: i32) {
+            self.0.push(format!("write {} = {} -> {}", addr, old, new));
+        }
+
+        fn input(&mut self, value: i32) {
+            self.0.push(format!("input {}", value));
+        }
+
+        fn output(&mut self, value: i32) {
+            self.0.push(format!("output {}", value));
+        }
+
+        fn jump_taken(&mut self, from: usize, to: usize) {
+            self.0.push(format!("jump {} -> {}", from, to));
+        }
+
+        fn halt(&mut self, pc: usize) {
+            self.0.push(format!("halt {}", pc));
+        }
+    }
+
+    #[test]
+    fn observes_execution() {
+        let recorder = Rc::new(RefCell::new(Recorder::default()));
+
+        let mut computer = Intcode::load("3,9,8,9,10,9,4,9,99,-1,8", vec![]);
+        computer.set_observer(Rc::clone(&recorder));
+        assert_eq!(computer.run(&Limits::default()), Ok(Status::NeedsInput));
+        computer.push_input(8);
+        assert_eq!(computer.run(&Limits::default()), Ok(Status::Halted));
+
+        assert_eq!(
+            recorder.borrow().0,
+            vec![
+                "0: 3",
+                "write 9 = -1 -> 8",
+                "input 8",
+                "2: 8",
+                "read 9 = 8",
+                "read 10 = 8",
+                "write 9 = 8 -> 1",
+                "6: 4",
+                "read 9 = 1",
+                "output 1",
+                "8: 99",
+                "halt 8",
+            ]
+        );
+    }
+
+    #[test]
+    fn observes_jumps() {
+        let recorder = Rc::new(RefCell::new(Recorder::default()));
+
+        let mut extensions = Extensions::new();
+        extensions.register(53, &[Param::Read], |args, _| Effect::Jump(args[0] as usize));
+        extensions.register(51, &[], |_, _| Effect::Halt(0));
+
+        // The second jump isn't taken.
+        let mut computer = Intcode::load("1105,1,4,99,1106,1,0,153,10,99,51", vec![]);
+        computer.set_extensions(extensions);
+        computer.set_observer(Rc::clone(&recorder));
+        computer.execute();
+
+        assert_eq!(
+            recorder.borrow().0,
+            vec!["0: 1105", "jump 0 -> 4", "4: 1106", "7: 153", "jump 7 -> 10", "10: 51", "halt 10"]
+        );
+    }
+}
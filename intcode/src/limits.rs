@@ -0,0 +1,66 @@
+use std::error::Error;
+use std::fmt;
+use std::time::Instant;
+
+/// Resource limits for a single run of an Intcode program.
+///
+/// Every limit is disabled by default. A run that would exceed a limit stops *before* executing
+/// the offending instruction, so the machine can be inspected or resumed afterwards.
+#[derive(Debug, Default, Clone)]
+pub struct Limits {
+    /// The maximum number of instructions to execute.
+    pub max_instructions: Option<u64>,
+
+    /// The maximum number of cells that memory may grow to.
+    pub max_memory: Option<usize>,
+
+    /// The maximum number of values that may be output.
+    pub max_output: Option<usize>,
+
+    /// The point in time after which no more instructions are executed.
+    pub deadline: Option<Instant>,
+}
+
+impl Limits {
+    /// Checks the limits that apply before every instruction, given the number of instructions
+    /// that have already been executed.
+    pub fn check(&self, steps: u64) -> Result<(), LimitExceeded> {
+        if let Some(max) = self.max_instructions {
+            if steps >= max {
+                return Err(LimitExceeded::Instructions);
+            }
+        }
+
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                return Err(LimitExceeded::Deadline);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The limit that stopped a run.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LimitExceeded {
+    Instructions,
+    Memory,
+    Output,
+    Deadline,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let limit = match self {
+            LimitExceeded::Instructions => "instruction",
+            LimitExceeded::Memory => "memory",
+            LimitExceeded::Output => "output",
+            LimitExceeded::Deadline => "deadline",
+        };
+
+        write!(f, "{} limit exceeded", limit)
+    }
+}
+
+impl Error for LimitExceeded {}
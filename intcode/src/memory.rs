@@ -0,0 +1,183 @@
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use std::rc::Rc;
+
+/// The number of cells in each page.
+const PAGE_SIZE: usize = 64;
+
+type Page = [i32; PAGE_SIZE];
+
+/// The memory of an Intcode computer.
+///
+/// Memory is split into fixed-size pages that are shared between clones until one of them writes
+/// to a page, so cloning memory only copies a list of pointers.
+#[derive(Default, Clone, PartialEq, Eq, Hash)]
+pub struct Memory {
+    pages: Vec<Rc<Page>>,
+    len: usize,
+}
+
+impl Memory {
+    pub fn new(cells: &[i32]) -> Self {
+        let pages = cells
+            .chunks(PAGE_SIZE)
+            .map(|chunk| {
+                let mut page = [0; PAGE_SIZE];
+                page[..chunk.len()].copy_from_slice(chunk);
+                Rc::new(page)
+            })
+            .collect();
+
+        Memory { pages, len: cells.len() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Reads a cell. Cells past the end of memory read as zero.
+    pub fn get(&self, addr: usize) -> i32 {
+        if addr < self.len {
+            self.pages[addr / PAGE_SIZE][addr % PAGE_SIZE]
+        } else {
+            0
+        }
+    }
+
+    /// Writes a cell, growing memory if the cell is past the end.
+    pub fn set(&mut self, addr: usize, value: i32) {
+        if addr >= self.len {
+            self.grow(addr + 1);
+        }
+
+        self[addr] = value;
+    }
+
+    /// Grows memory to `len` cells, filling it with zeroes. Memory never shrinks.
+    pub fn grow(&mut self, len: usize) {
+        if len <= self.len {
+            return;
+        }
+
+        let pages = len.div_ceil(PAGE_SIZE);
+        self.pages.resize_with(pages, || Rc::new([0; PAGE_SIZE]));
+        self.len = len;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = i32> + '_ {
+        self.pages.iter().flat_map(|page| page.iter().copied()).take(self.len)
+    }
+
+    pub fn to_vec(&self) -> Vec<i32> {
+        self.iter().collect()
+    }
+
+    /// The number of pages that are shared with another memory, rather than copied.
+    pub fn shared_pages(&self, other: &Memory) -> usize {
+        self.pages.iter().zip(&other.pages).filter(|(a, b)| Rc::ptr_eq(a, b)).count()
+    }
+}
+
+impl Index<usize> for Memory {
+    type Output = i32;
+
+    fn index(&self, addr: usize) -> &i32 {
+        assert!(addr < self.len, "address {} is out of bounds of memory of length {}", addr, self.len);
+        &self.pages[addr / PAGE_SIZE][addr % PAGE_SIZE]
+    }
+}
+
+impl IndexMut<usize> for Memory {
+    /// Copies the page that contains `addr` if it's shared.
+    fn index_mut(&mut self, addr: usize) -> &mut i32 {
+        assert!(addr < self.len, "address {} is out of bounds of memory of length {}", addr, self.len);
+        &mut Rc::make_mut(&mut self.pages[addr / PAGE_SIZE])[addr % PAGE_SIZE]
+    }
+}
+
+impl PartialEq<[i32]> for Memory {
+    fn eq(&self, other: &[i32]) -> bool {
+        self.len == other.len() && self.iter().eq(other.iter().copied())
+    }
+}
+
+impl<const N: usize> PartialEq<[i32; N]> for Memory {
+    fn eq(&self, other: &[i32; N]) -> bool {
+        *self == other[..]
+    }
+}
+
+impl<const N: usize> PartialEq<&[i32; N]> for Memory {
+    fn eq(&self, other: &&[i32; N]) -> bool {
+        *self == other[..]
+    }
+}
+
+impl PartialEq<Vec<i32>> for Memory {
+    fn eq(&self, other: &Vec<i32>) -> bool {
+        *self == other[..]
+    }
+}
+
+impl fmt::Debug for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Memory, PAGE_SIZE};
+
+    #[test]
+    fn get_and_set() {
+        let mut mem = Memory::new(&[1, 2, 3]);
+
+        assert_eq!(mem.get(1), 2);
+        assert_eq!(mem.get(1000), 0);
+
+        mem.set(1, 5);
+        mem.set(PAGE_SIZE + 1, 7);
+
+        assert_eq!(mem.len(), PAGE_SIZE + 2);
+        assert_eq!(mem[1], 5);
+        assert_eq!(mem[PAGE_SIZE], 0);
+        assert_eq!(mem[PAGE_SIZE + 1], 7);
+        assert_eq!(mem.iter().sum::<i32>(), 1 + 5 + 3 + 7);
+    }
+
+    #[test]
+    fn copy_on_write() {
+        let cells = (0..PAGE_SIZE as i32 * 4).collect::<Vec<_>>();
+        let mem = Memory::new(&cells);
+
+        let mut clone = mem.clone();
+        assert_eq!(clone.shared_pages(&mem), 4);
+
+        clone[PAGE_SIZE * 2] = -1;
+        assert_eq!(clone.shared_pages(&mem), 3);
+        assert_eq!(mem[PAGE_SIZE * 2], PAGE_SIZE as i32 * 2);
+        assert_eq!(clone[PAGE_SIZE * 2], -1);
+    }
+
+    #[test]
+    fn equality() {
+        let mut mem = Memory::new(&[1, 2, 3]);
+        assert_eq!(mem, [1, 2, 3]);
+        assert_eq!(mem, vec![1, 2, 3]);
+
+        mem.grow(4);
+        assert_eq!(mem, Memory::new(&[1, 2, 3, 0]));
+        assert_ne!(mem, Memory::new(&[1, 2, 3]));
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn index_out_of_bounds() {
+        let _ = Memory::new(&[1, 2, 3])[3];
+    }
+}
@@ -0,0 +1,269 @@
+//! A network of Intcode computers that send each other packets.
+//!
+//! Every computer boots with its address as its first input. It sends a packet by outputting the
+//! destination address followed by the packet's X and Y values, and receives packets as X and Y
+//! inputs. When a computer reads input while it has no packets waiting, it reads -1 instead.
+//!
+//! Packets sent to [`NAT_ADDRESS`] are captured by the NAT, which remembers the last one. Once
+//! the network is idle, the NAT sends that packet to address 0 to wake the network up again.
+
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+use crate::{ExecutionError, Intcode, Limits, Status};
+
+/// The address that the NAT listens on.
+pub const NAT_ADDRESS: i32 = 255;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Packet {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Something that the NAT did during a round.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Event {
+    Received(Packet),
+
+    /// The network was idle, so the NAT sent its last packet to address 0.
+    Sent(Packet),
+}
+
+#[derive(Debug)]
+pub enum NetworkError {
+    /// The computer at `addr` stopped with an error.
+    Execution { addr: usize, error: ExecutionError },
+
+    /// The computer at `addr` sent a packet to an address that doesn't exist.
+    UnknownAddress { addr: usize, dest: i32 },
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetworkError::Execution { addr, error } => write!(f, "computer {}: {}", addr, error),
+            NetworkError::UnknownAddress { addr, dest } => {
+                write!(f, "computer {} sent a packet to unknown address {}", addr, dest)
+            }
+        }
+    }
+}
+
+impl Error for NetworkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            NetworkError::Execution { error, .. } => Some(error),
+            NetworkError::UnknownAddress { .. } => None,
+        }
+    }
+}
+
+struct Node {
+    computer: Intcode,
+    queue: VecDeque<Packet>,
+
+    /// Output that doesn't make up a whole packet yet.
+    partial: Vec<i32>,
+
+    /// Whether the computer has halted. A halted computer doesn't run or receive packets.
+    halted: bool,
+}
+
+pub struct Network {
+    nodes: Vec<Node>,
+    nat: Option<Packet>,
+    limits: Limits,
+}
+
+impl Network {
+    /// Boots `size` computers running `program`.
+    pub fn new(program: &[i32], size: usize) -> Self {
+        let nodes = (0..size)
+            .map(|addr| Node {
+                computer: Intcode::new(program.to_vec(), vec![addr as i32]),
+                queue: VecDeque::new(),
+                partial: vec![],
+                halted: false,
+            })
+            .collect();
+
+        Network {
+            nodes,
+            nat: None,
+            limits: Limits::default(),
+        }
+    }
+
+    /// Sets the limits that apply to each computer every time it runs.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Queues a packet for the computer at `dest`, unless it has halted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no computer at `dest`.
+    pub fn send(&mut self, dest: usize, packet: Packet) {
+        let node = &mut self.nodes[dest];
+
+        if !node.halted {
+            node.queue.push_back(packet);
+        }
+    }
+
+    /// The last packet that the NAT received.
+    pub fn nat(&self) -> Option<Packet> {
+        self.nat
+    }
+
+    /// Runs each computer until it needs input that it doesn't have, delivering the packets that
+    /// it sends.
+    ///
+    /// Computers that have halted are skipped, and packets sent to them are dropped. If the queue
+    /// of every other computer was empty at the start of the round and no packets were sent during
+    /// it, the network is idle and the NAT sends its last packet to address 0.
+    pub fn round(&mut self) -> Result<Vec<Event>, NetworkError> {
+        let mut events = vec![];
+        let mut idle = self.nodes.iter().all(|node| node.halted || node.queue.is_empty());
+
+        for addr in 0..self.nodes.len() {
+            let node = &mut self.nodes[addr];
+
+            if node.halted {
+                continue;
+            }
+
+            match node.queue.pop_front() {
+                Some(Packet { x, y }) => {
+                    node.computer.push_input(x);
+                    node.computer.push_input(y);
+                }
+                None => node.computer.push_input(-1),
+            }
+
+            let status = node
+                .computer
+                .run(&self.limits)
+                .map_err(|error| NetworkError::Execution { addr, error })?;
+
+            node.halted = status == Status::Halted;
+
+            node.partial.extend(node.computer.take_output());
+
+            let output = node.partial.len() - node.partial.len() % 3;
+            let packets = node.partial.drain(..output).collect::<Vec<_>>();
+
+            for packet in packets.chunks(3) {
+                idle = false;
+
+                let (dest, packet) = (packet[0], Packet { x: packet[1], y: packet[2] });
+
+                if dest == NAT_ADDRESS {
+                    self.nat = Some(packet);
+                    events.push(Event::Received(packet));
+                    continue;
+                }
+
+                match usize::try_from(dest).ok().and_then(|dest| self.nodes.get_mut(dest)) {
+                    Some(node) if node.halted => (),
+                    Some(node) => node.queue.push_back(packet),
+                    None => return Err(NetworkError::UnknownAddress { addr, dest }),
+                }
+            }
+        }
+
+        if idle {
+            if let Some(packet) = self.nat {
+                self.send(0, packet);
+                events.push(Event::Sent(packet));
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::program;
+
+    use super::{Event, Network, NetworkError, Packet};
+
+    /// A node that adds one to the Y value of every packet it receives and passes it on to the
+    /// next address, or to the NAT if it is the last node.
+    fn relay(size: usize) -> Vec<i32> {
+        let program = format!(
+            "3,41, 3,42, 1008,42,-1,44, 1005,44,2, 3,43, 1001,41,1,45, 1008,45,{},44, \
+             1006,44,28, 1101,255,0,45, 4,45, 4,42, 1001,43,1,43, 4,43, 1105,1,2, \
+             0,0,0,0,0",
+            size
+        );
+
+        program::parse(&program).unwrap()
+    }
+
+    #[test]
+    fn relay_through_nat() {
+        let mut network = Network::new(&relay(4), 4);
+        network.send(0, Packet { x: 5, y: 0 });
+
+        let mut events = vec![];
+
+        while events.len() < 5 {
+            events.extend(network.round().unwrap());
+        }
+
+        assert_eq!(
+            events,
+            vec![
+                Event::Received(Packet { x: 5, y: 4 }),
+                Event::Sent(Packet { x: 5, y: 4 }),
+                Event::Received(Packet { x: 5, y: 8 }),
+                Event::Sent(Packet { x: 5, y: 8 }),
+                Event::Received(Packet { x: 5, y: 12 }),
+            ]
+        );
+        assert_eq!(network.nat(), Some(Packet { x: 5, y: 12 }));
+    }
+
+    #[test]
+    fn idle_without_nat_packet() {
+        let mut network = Network::new(&relay(2), 2);
+
+        for _ in 0..3 {
+            assert_eq!(network.round().unwrap(), vec![]);
+        }
+    }
+
+    #[test]
+    fn halted_node_is_skipped() {
+        // Address 0 halts right after booting. The others send (0, 1, 2) and (255, 5, 6), then
+        // read input forever.
+        let program = "3,100,1006,100,22, 104,0,104,1,104,2, 104,255,104,5,104,6, 3,101,1105,1,17,99";
+        let program = program::parse(program).unwrap();
+        let mut network = Network::new(&program, 2);
+
+        assert_eq!(network.round().unwrap(), vec![Event::Received(Packet { x: 5, y: 6 })]);
+        assert!(network.nodes[0].halted);
+
+        // The packet for the halted computer doesn't keep the network busy.
+        assert_eq!(network.round().unwrap(), vec![Event::Sent(Packet { x: 5, y: 6 })]);
+        assert_eq!(network.round().unwrap(), vec![Event::Sent(Packet { x: 5, y: 6 })]);
+    }
+
+    #[test]
+    fn unknown_address() {
+        // Sends (7, 1, 2) right after booting.
+        let program = program::parse("3,0,104,7,104,1,104,2,99").unwrap();
+        let mut network = Network::new(&program, 2);
+
+        match network.round() {
+            Err(NetworkError::UnknownAddress { addr: 0, dest: 7 }) => (),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+/// Callbacks for observing a running program, for tools such as tracers, profilers and coverage.
+///
+/// Every method does nothing by default, so an observer only implements the events it cares
+/// about. Attach one with [`Intcode::set_observer`](crate::Intcode::set_observer). When no
+/// observer is attached, each event costs a single branch.
+#[allow(unused_variables)]
+pub trait Observer {
+    /// Called before the machine executes the instruction `instr` at `pc`.
+    fn before_instruction(&mut self, pc: usize, instr: i32) {}
+
+    /// Called when a parameter in position or relative mode reads the cell at `addr`. Reading the
+    /// instruction itself and immediate parameters isn't reported.
+    fn memory_read(&mut self, addr: usize, value: i32) {}
+
+    /// Called when the cell at `addr` is written, before `old` is replaced by `new`.
+    fn memory_write(&mut self, addr: usize, old: i32, new: i32) {}
+
+    /// Called when an input instruction takes a value from the input queue.
+    fn input(&mut self, value: i32) {}
+
+    /// Called when the program outputs a value.
+    fn output(&mut self, value: i32) {}
+
+    /// Called when the instruction at `from` jumps to `to`. Conditional jumps whose condition
+    /// doesn't hold aren't reported.
+    fn jump_taken(&mut self, from: usize, to: usize) {}
+
+    /// Called when the instruction at `pc` halts the machine.
+    fn halt(&mut self, pc: usize) {}
+}
+
+/// An attached observer.
+#[derive(Clone)]
+pub(crate) struct Attached(pub(crate) Rc<RefCell<dyn Observer>>);
+
+impl fmt::Debug for Attached {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Observer")
+    }
+}
@@ -0,0 +1,834 @@
+//! An offline optimizer that rewrites programs into smaller or faster equivalents.
+//!
+//! The optimizer only handles programs whose control flow and memory accesses can be worked out
+//! without running them:
+//!
+//! - Every reachable instruction decodes to a standard opcode with valid modes.
+//! - No instruction reads or writes a cell of a reachable instruction.
+//! - No jump target is read from a cell that the program writes.
+//! - Nothing uses the relative base.
+//!
+//! Other programs are left alone, and [`optimize`] explains why.
+//!
+//! Given those guarantees, the optimizer:
+//!
+//! - Turns reads of cells that are never written into immediates. An instruction whose
+//!   parameters are all immediates, and that runs before anything else reads or writes the cell
+//!   it writes, is removed, and its result is stored in that cell of the program instead. Jumps
+//!   whose condition is known become unconditional, or are removed.
+//! - Threads jumps to unconditional jumps straight to the final target, and removes jumps to the
+//!   next instruction.
+//! - Removes unreachable instructions and cells that are no longer used, moving the rest of the
+//!   program down.
+//!
+//! The optimized program produces the same output for the same input, but its memory is laid out
+//! differently.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
+use crate::isa::{self, Mode, Operation};
+use crate::{ExecutionError, Intcode, Limits, Param};
+
+/// Why a program can't be optimized.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OptimizeError {
+    /// The reachable instruction at `addr` has an unknown opcode, an invalid mode, a negative
+    /// address or jump target, or runs past the end of the program.
+    Undecodable { addr: usize },
+
+    /// The instruction at `addr` starts inside another reachable instruction.
+    Overlap { addr: usize },
+
+    /// The instruction at `addr` reads or writes a cell of a reachable instruction.
+    SelfModifying { addr: usize },
+
+    /// The jump at `addr` reads its target from a cell that the program writes.
+    DynamicJump { addr: usize },
+
+    /// The instruction at `addr` uses the relative base.
+    Relative { addr: usize },
+}
+
+impl fmt::Display for OptimizeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptimizeError::Undecodable { addr } => {
+                write!(f, "the instruction at address {} can't be decoded", addr)
+            }
+            OptimizeError::Overlap { addr } => {
+                write!(f, "the instruction at address {} overlaps another instruction", addr)
+            }
+            OptimizeError::SelfModifying { addr } => {
+                write!(f, "the instruction at address {} accesses the program's code", addr)
+            }
+            OptimizeError::DynamicJump { addr } => {
+                write!(f, "the jump at address {} has a target that changes", addr)
+            }
+            OptimizeError::Relative { addr } => {
+                write!(f, "the instruction at address {} uses the relative base", addr)
+            }
+        }
+    }
+}
+
+impl Error for OptimizeError {}
+
+/// An optimized program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Optimized {
+    pub program: Vec<i32>,
+
+    /// The number of instructions that were simplified by constant folding.
+    pub folded: usize,
+
+    /// The number of jumps that were redirected past other jumps.
+    pub threaded: usize,
+
+    /// The number of cells that were removed.
+    pub removed: usize,
+}
+
+/// The parameters of a conditional jump.
+#[derive(Debug, Copy, Clone)]
+struct Jump {
+    condition: isa::Condition,
+
+    /// The indices of the condition and target parameters.
+    cond: usize,
+    target: usize,
+}
+
+/// A decoded instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Instruction {
+    opcode: i32,
+    modes: Vec<Mode>,
+    params: Vec<i32>,
+}
+
+impl Instruction {
+    fn decode(program: &[i32], addr: usize) -> Result<Self, OptimizeError> {
+        let undecodable = OptimizeError::Undecodable { addr };
+
+        let instr = *program.get(addr).ok_or(undecodable)?;
+        let isa::Decoded { opcode, modes } = isa::decode(instr);
+
+        let spec = match isa::lookup(opcode) {
+            Some(spec) if instr >= 0 => spec,
+            _ => return Err(undecodable),
+        };
+
+        let params = program.get(addr + 1..addr + spec.size()).ok_or(undecodable)?.to_vec();
+        let modes = modes.of(spec.params).map_err(|_| undecodable)?;
+
+        if matches!(spec.operation, Operation::AdjustRelativeBase) || modes.contains(&Mode::Relative) {
+            return Err(OptimizeError::Relative { addr });
+        }
+
+        let instruction = Instruction { opcode, modes, params };
+
+        let negative_address = (0..instruction.params.len())
+            .any(|i| instruction.modes[i] == Mode::Position && instruction.params[i] < 0);
+
+        if negative_address || instruction.target().is_some_and(|target| target < 0) {
+            return Err(undecodable);
+        }
+
+        Ok(instruction)
+    }
+
+    fn encode(&self) -> Vec<i32> {
+        let mut instr = self.opcode;
+        let mut place = 100;
+
+        for &mode in &self.modes {
+            instr += mode as i32 * place;
+            place *= 10;
+        }
+
+        let mut cells = vec![instr];
+        cells.extend(&self.params);
+        cells
+    }
+
+    fn spec(&self) -> &'static isa::Instruction {
+        isa::lookup(self.opcode).unwrap()
+    }
+
+    fn len(&self) -> usize {
+        1 + self.params.len()
+    }
+
+    /// The address that the instruction writes to.
+    fn dst(&self) -> Option<usize> {
+        self.spec().write_param().map(|i| self.params[i] as usize)
+    }
+
+    /// The indices of the parameters that are read.
+    fn reads(&self) -> Vec<usize> {
+        (0..self.params.len()).filter(|&i| self.spec().params[i] == Param::Read).collect()
+    }
+
+    fn jump(&self) -> Option<Jump> {
+        match self.spec().operation {
+            Operation::JumpIf(condition) => {
+                let reads = self.reads();
+                Some(Jump { condition, cond: reads[0], target: reads[1] })
+            }
+            _ => None,
+        }
+    }
+
+    /// The target of a jump whose target is immediate.
+    fn target(&self) -> Option<i32> {
+        match self.jump() {
+            Some(Jump { target, .. }) if self.modes[target] == Mode::Immediate => Some(self.params[target]),
+            _ => None,
+        }
+    }
+
+    /// Whether a jump is taken, if its condition is immediate.
+    fn taken(&self) -> Option<bool> {
+        match self.jump() {
+            Some(Jump { condition, cond, .. }) if self.modes[cond] == Mode::Immediate => {
+                Some(condition.holds(self.params[cond]))
+            }
+            _ => None,
+        }
+    }
+
+    /// The value of a read parameter, if it's immediate or read from a cell that is never
+    /// written.
+    fn constant(&self, i: usize, program: &[i32], written: &HashSet<usize>) -> Option<i32> {
+        let cell = self.params[i] as usize;
+
+        if self.modes[i] == Mode::Immediate {
+            Some(self.params[i])
+        } else if !written.contains(&cell) {
+            Some(program.get(cell).copied().unwrap_or(0))
+        } else {
+            None
+        }
+    }
+
+    /// The addresses that may run after this instruction, which is at `addr`. The target of a
+    /// jump whose target is read from a written cell is unknown, so it's left out.
+    fn successors(&self, addr: usize, program: &[i32], written: &HashSet<usize>) -> Vec<usize> {
+        let next = addr + self.len();
+
+        if self.spec().halts() {
+            return vec![];
+        }
+
+        match self.jump() {
+            Some(Jump { condition, cond, target }) => {
+                let taken = self.constant(cond, program, written).map(|value| condition.holds(value));
+                let target = self.constant(target, program, written);
+
+                let mut successors = vec![];
+
+                if taken != Some(true) {
+                    successors.push(next);
+                }
+
+                if let (Some(target), true) = (target, taken != Some(false)) {
+                    successors.push(target as usize);
+                }
+
+                successors
+            }
+            None => vec![next],
+        }
+    }
+}
+
+/// Optimizes a program, or explains why it can't be optimized.
+pub fn optimize(program: &[i32]) -> Result<Optimized, OptimizeError> {
+    let (mut instructions, written) = analyze(program)?;
+    let original = program;
+    let mut program = program.to_vec();
+
+    // Jumps that are never taken, and other instructions that do nothing. They're removed, but
+    // still fall through to the next instruction until then.
+    let mut noops = BTreeSet::new();
+
+    let folded = fold(&mut program, &mut instructions, &written, &mut noops);
+    let threaded = thread(&mut instructions, &mut noops);
+
+    let reachable = reachable(&program, &instructions, &written, &noops);
+    instructions.retain(|addr, _| reachable.contains(addr) && !noops.contains(addr));
+
+    let optimized = relocate(&program, &instructions);
+
+    Ok(Optimized {
+        removed: original.len() - optimized.len(),
+        program: optimized,
+        folded,
+        threaded,
+    })
+}
+
+/// Finds the reachable instructions and the cells they write, and checks that the program can be
+/// optimized.
+///
+/// Reads of cells that are never written always see the value in the program, so they can decide
+/// which way a jump goes. Which cells are written depends on which instructions are reachable,
+/// though, so the analysis is repeated until the written cells stop changing.
+fn analyze(program: &[i32]) -> Result<(BTreeMap<usize, Instruction>, HashSet<usize>), OptimizeError> {
+    let mut written = HashSet::new();
+
+    let (instructions, failures) = loop {
+        let (instructions, failures) = decode_reachable(program, &written);
+        let dsts = instructions.values().filter_map(Instruction::dst).collect::<HashSet<_>>();
+
+        if dsts.is_subset(&written) {
+            break (instructions, failures);
+        }
+
+        written.extend(dsts);
+    };
+
+    let mut end = 0;
+    for (&addr, instruction) in &instructions {
+        if addr < end {
+            return Err(OptimizeError::Overlap { addr });
+        }
+
+        end = addr + instruction.len();
+    }
+
+    let code = instructions
+        .iter()
+        .flat_map(|(&addr, instruction)| addr..addr + instruction.len())
+        .chain(failures.keys().copied())
+        .collect::<HashSet<_>>();
+
+    for (&addr, instruction) in &instructions {
+        let accessed = (0..instruction.params.len())
+            .filter(|&i| instruction.modes[i] == Mode::Position)
+            .map(|i| instruction.params[i] as usize);
+
+        for cell in accessed {
+            if code.contains(&cell) {
+                return Err(OptimizeError::SelfModifying { addr });
+            }
+        }
+
+        if let Some(Jump { target, .. }) = instruction.jump() {
+            if instruction.modes[target] == Mode::Position && written.contains(&(instruction.params[target] as usize)) {
+                return Err(OptimizeError::DynamicJump { addr });
+            }
+        }
+    }
+
+    // A reachable instruction that can't be decoded might have been written by the program, so
+    // it's only reported once the program is known not to modify itself.
+    match failures.values().next() {
+        Some(&error) => Err(error),
+        None => Ok((instructions, written)),
+    }
+}
+
+/// Decodes every instruction that is reachable from address 0, given the cells that the program
+/// writes. Also returns the reachable addresses that can't be decoded.
+fn decode_reachable(
+    program: &[i32],
+    written: &HashSet<usize>,
+) -> (BTreeMap<usize, Instruction>, BTreeMap<usize, OptimizeError>) {
+    let mut instructions = BTreeMap::new();
+    let mut failures = BTreeMap::new();
+    let mut queue = vec![0];
+
+    while let Some(addr) = queue.pop() {
+        if instructions.contains_key(&addr) || failures.contains_key(&addr) {
+            continue;
+        }
+
+        match Instruction::decode(program, addr) {
+            Ok(instruction) => {
+                queue.extend(instruction.successors(addr, program, written));
+                instructions.insert(addr, instruction);
+            }
+            Err(e) => {
+                failures.insert(addr, e);
+            }
+        }
+    }
+
+    (instructions, failures)
+}
+
+/// The instructions that run first, in order, up to and including the first jump or halt.
+fn entry(instructions: &BTreeMap<usize, Instruction>) -> Vec<usize> {
+    let mut entry = vec![];
+    let mut addr = 0;
+
+    while let Some(instruction) = instructions.get(&addr) {
+        entry.push(addr);
+
+        if instruction.spec().jumps() || instruction.spec().halts() {
+            break;
+        }
+
+        addr += instruction.len();
+    }
+
+    entry
+}
+
+/// The instructions whose constant results can be stored in the program instead: ones that run
+/// first, before any other instruction reads the cell they write, and that are the only
+/// instruction to write it. Whenever they run again, the cell already holds their result.
+fn hoistable(program: &[i32], instructions: &BTreeMap<usize, Instruction>) -> HashSet<usize> {
+    let mut writers = BTreeMap::<usize, usize>::new();
+    for dst in instructions.values().filter_map(Instruction::dst) {
+        *writers.entry(dst).or_insert(0) += 1;
+    }
+
+    let mut read = HashSet::new();
+    let mut hoistable = HashSet::new();
+
+    for addr in entry(instructions) {
+        let instruction = &instructions[&addr];
+
+        if let Some(dst) = instruction.dst() {
+            if dst < program.len() && writers[&dst] == 1 && !read.contains(&dst) {
+                hoistable.insert(addr);
+            }
+        }
+
+        read.extend(
+            instruction.reads().into_iter().filter(|&i| instruction.modes[i] == Mode::Position).map(|i| instruction.params[i] as usize),
+        );
+    }
+
+    hoistable
+}
+
+/// Replaces reads of cells that are never written with immediates, and stores the results of
+/// hoistable instructions whose parameters are all immediates in the program. Returns the number
+/// of instructions that changed.
+fn fold(
+    program: &mut [i32],
+    instructions: &mut BTreeMap<usize, Instruction>,
+    written: &HashSet<usize>,
+    noops: &mut BTreeSet<usize>,
+) -> usize {
+    let hoistable = hoistable(program, instructions);
+    let mut folded = 0;
+
+    for (&addr, instruction) in instructions.iter_mut() {
+        let original = instruction.clone();
+
+        for i in instruction.reads() {
+            let cell = instruction.params[i] as usize;
+
+            if instruction.modes[i] == Mode::Position && !written.contains(&cell) {
+                instruction.modes[i] = Mode::Immediate;
+                instruction.params[i] = program.get(cell).copied().unwrap_or(0);
+            }
+        }
+
+        let reads = instruction.reads();
+
+        if let Operation::Compute(compute) = instruction.spec().operation {
+            if hoistable.contains(&addr) && reads.iter().all(|&i| instruction.modes[i] == Mode::Immediate) {
+                let (a, b) = (instruction.params[reads[0]], instruction.params[reads[1]]);
+
+                // A computation that overflows is left for the machine to report.
+                if let Some(result) = compute.apply(a, b) {
+                    program[instruction.dst().unwrap()] = result;
+                    noops.insert(addr);
+                }
+            }
+        }
+
+        // A jump that is always taken is left as it is, since its condition is now immediate.
+        if instruction.taken() == Some(false) {
+            noops.insert(addr);
+        }
+
+        if *instruction != original || noops.contains(&addr) {
+            folded += 1;
+        }
+    }
+
+    folded
+}
+
+/// The first instruction at or after `addr` that isn't a no-op.
+fn resolve(instructions: &BTreeMap<usize, Instruction>, noops: &BTreeSet<usize>, mut addr: usize) -> usize {
+    while noops.contains(&addr) {
+        addr += instructions[&addr].len();
+    }
+
+    addr
+}
+
+/// Redirects jumps to unconditional jumps to their final target, and turns jumps to the next
+/// instruction into no-ops. Returns the number of redirected jumps.
+fn thread(instructions: &mut BTreeMap<usize, Instruction>, noops: &mut BTreeSet<usize>) -> usize {
+    let mut threaded = 0;
+    let addrs = instructions.keys().copied().collect::<Vec<_>>();
+
+    for addr in addrs {
+        if noops.contains(&addr) {
+            continue;
+        }
+
+        let instruction = &instructions[&addr];
+        let target = match instruction.target() {
+            Some(target) => target as usize,
+            None => continue,
+        };
+
+        let mut seen = HashSet::new();
+        seen.insert(addr);
+
+        let mut end = resolve(instructions, noops, target);
+        while let Some(next) = instructions.get(&end) {
+            if next.taken() != Some(true) || !seen.insert(end) {
+                break;
+            }
+
+            end = resolve(instructions, noops, next.target().unwrap() as usize);
+        }
+
+        if end != target {
+            let instruction = instructions.get_mut(&addr).unwrap();
+            let param = instruction.jump().unwrap().target;
+            instruction.params[param] = end as i32;
+            threaded += 1;
+        }
+
+        if end == resolve(instructions, noops, addr + instructions[&addr].len()) {
+            noops.insert(addr);
+        }
+    }
+
+    threaded
+}
+
+/// The instructions that are reachable from address 0 after optimization.
+fn reachable(
+    program: &[i32],
+    instructions: &BTreeMap<usize, Instruction>,
+    written: &HashSet<usize>,
+    noops: &BTreeSet<usize>,
+) -> HashSet<usize> {
+    let mut reachable = HashSet::new();
+    let mut queue = vec![0];
+
+    while let Some(addr) = queue.pop() {
+        if !reachable.insert(addr) {
+            continue;
+        }
+
+        let instruction = &instructions[&addr];
+
+        if noops.contains(&addr) {
+            queue.push(addr + instruction.len());
+        } else {
+            queue.extend(instruction.successors(addr, program, written));
+        }
+    }
+
+    reachable
+}
+
+/// Lays out the remaining instructions and the cells they access, in their original order,
+/// without the cells in between.
+fn relocate(program: &[i32], instructions: &BTreeMap<usize, Instruction>) -> Vec<i32> {
+    let mut kept = vec![false; program.len()];
+
+    for (&addr, instruction) in instructions {
+        for cell in &mut kept[addr..addr + instruction.len()] {
+            *cell = true;
+        }
+
+        for (i, &param) in instruction.params.iter().enumerate() {
+            if instruction.modes[i] == Mode::Position {
+                if let Some(cell) = kept.get_mut(param as usize) {
+                    *cell = true;
+                }
+            }
+        }
+    }
+
+    // The number of removed cells before each address.
+    let mut removed = Vec::with_capacity(program.len() + 1);
+    removed.push(0);
+    for &kept in &kept {
+        removed.push(removed.last().unwrap() + usize::from(!kept));
+    }
+
+    let relocate = |addr: i32| {
+        let addr = addr as usize;
+        (addr - removed[addr.min(program.len())]) as i32
+    };
+
+    let mut optimized = vec![];
+    let mut addr = 0;
+
+    while addr < program.len() {
+        if let Some(instruction) = instructions.get(&addr) {
+            let mut instruction = instruction.clone();
+
+            let target = instruction.jump().map(|jump| jump.target);
+
+            for i in 0..instruction.params.len() {
+                if instruction.modes[i] == Mode::Position || target == Some(i) {
+                    instruction.params[i] = relocate(instruction.params[i]);
+                }
+            }
+
+            optimized.extend(instruction.encode());
+            addr += instruction.len();
+        } else {
+            if kept[addr] {
+                optimized.push(program[addr]);
+            }
+
+            addr += 1;
+        }
+    }
+
+    optimized
+}
+
+/// Two programs that produced different results for the same input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub input: Vec<i32>,
+    pub original: Result<Vec<i32>, ExecutionError>,
+    pub optimized: Result<Vec<i32>, ExecutionError>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "programs disagree on input {:?}: the original gave {:?}, the optimized program gave {:?}",
+            self.input, self.original, self.optimized
+        )
+    }
+}
+
+impl Error for Mismatch {}
+
+/// Checks that two programs produce the same output for each set of inputs.
+///
+/// Programs that both fail agree if they fail with the same kind of error, because the optimized
+/// program reports different addresses. The optimized program executes fewer instructions, so an
+/// instruction limit might only stop the original.
+pub fn check_equivalence(
+    original: &[i32],
+    optimized: &[i32],
+    inputs: &[Vec<i32>],
+    limits: &Limits,
+) -> Result<(), Mismatch> {
+    for input in inputs {
+        let run = |program: &[i32]| Intcode::new(program.to_vec(), input.clone()).execute_with_limits(limits);
+
+        let mismatch = Mismatch {
+            input: input.clone(),
+            original: run(original),
+            optimized: run(optimized),
+        };
+
+        let agree = match (&mismatch.original, &mismatch.optimized) {
+            (Ok(a), Ok(b)) => a == b,
+            (Err(a), Err(b)) => mem::discriminant(a) == mem::discriminant(b),
+            _ => false,
+        };
+
+        if !agree {
+            return Err(mismatch);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{isa, program, Intcode, Limits};
+
+    use super::{check_equivalence, optimize, OptimizeError, Optimized};
+
+    /// A program, and the output that it produces for an input.
+    type Vector = (&'static str, fn(i32) -> i32);
+
+    /// The test vectors for day 5.
+    fn day5_vectors() -> Vec<Vector> {
+        vec![
+            ("3,0,4,0,99", |input| input),
+            ("3,9,8,9,10,9,4,9,99,-1,8", |input| (input == 8) as i32),
+            ("3,9,7,9,10,9,4,9,99,-1,8", |input| (input < 8) as i32),
+            ("3,3,1108,-1,8,3,4,3,99", |input| (input == 8) as i32),
+            ("3,3,1107,-1,8,3,4,3,99", |input| (input < 8) as i32),
+            ("3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9", |input| (input != 0) as i32),
+            ("3,3,1105,-1,9,1101,0,0,12,4,12,99,1", |input| (input != 0) as i32),
+            (program::COMPARE_TO_8, |input| 1000 + input.cmp(&8) as i32),
+        ]
+    }
+
+    /// Inputs below, equal to and above 8, and zero and nonzero ones for the jumps.
+    const DAY5_INPUTS: &[i32] = &[-100, -1, 0, 3, 5, 7, 8, 9, 1337];
+
+    fn optimized(program: &str) -> Optimized {
+        optimize(&program::parse(program).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn day5_vectors_are_equivalent() {
+        let inputs = DAY5_INPUTS.iter().map(|&input| vec![input]).collect::<Vec<_>>();
+        let mut left_alone = 0;
+
+        for (program, expected) in day5_vectors() {
+            let program = program::parse(program).unwrap();
+
+            // Half of the vectors store their input over their own code, so they're left alone.
+            let optimized = match optimize(&program) {
+                Ok(optimized) => {
+                    // The rest read constants from data cells, which are folded away.
+                    assert!(optimized.removed > 0, "{:?} was not shrunk", program);
+                    assert!(optimized.program.len() < program.len(), "{:?}", program);
+                    optimized.program
+                }
+                Err(OptimizeError::SelfModifying { .. }) => {
+                    left_alone += 1;
+                    program.clone()
+                }
+                Err(e) => panic!("{}", e),
+            };
+
+            check_equivalence(&program, &optimized, &inputs, &Limits::default())
+                .unwrap_or_else(|e| panic!("{}", e));
+
+            for &input in DAY5_INPUTS {
+                let output = Intcode::new(optimized.clone(), vec![input]).execute();
+                assert_eq!(output, vec![expected(input)], "{:?} with {}", program, input);
+            }
+        }
+
+        assert_eq!(left_alone, 4);
+    }
+
+    #[test]
+    fn folds_constant_reads() {
+        let optimized = optimized("3,9,8,9,10,9,4,9,99,-1,8");
+
+        assert_eq!(optimized.program, [3, 9, 1008, 9, 8, 9, 4, 9, 99, -1]);
+        assert_eq!(optimized.folded, 1);
+        assert_eq!(optimized.removed, 1);
+    }
+
+    #[test]
+    fn folds_immediates() {
+        // The product is stored in the cell that is output instead.
+        let optimized = optimized("1102,6,7,7,4,7,99,0");
+        assert_eq!(optimized.program, [4, 3, 99, 42]);
+        assert_eq!(optimized.folded, 1);
+
+        // The cell is output before it's written, so the instruction has to stay.
+        let program = "4,9,1102,6,7,9,4,9,99,0";
+        let optimized = self::optimized(program);
+        assert_eq!(optimized.program, program::parse(program).unwrap());
+        assert_eq!(optimized.folded, 0);
+
+        // So does one that writes a cell that another instruction writes too.
+        let optimized = self::optimized("1102,6,7,11,3,11,4,11,99,0,0,0");
+        assert_eq!(optimized.folded, 0);
+    }
+
+    #[test]
+    fn folds_constant_conditions() {
+        // The first jump is never taken, and the second one always is.
+        let optimized = optimized("1005,12,100,1006,12,9,104,2,99,104,1,99,0");
+
+        assert_eq!(optimized.program, [1106, 0, 3, 104, 1, 99]);
+        assert_eq!(optimized.folded, 2);
+    }
+
+    #[test]
+    fn threads_jumps() {
+        // If the input is nonzero, jumps to a jump to a jump to an instruction that outputs it.
+        let program = "3,15,1005,15,11,99,4,15,99,0,0,1105,1,16,0,0,1105,1,6";
+        let optimized = optimized(program);
+
+        assert_eq!(optimized.program, [3, 9, 1005, 9, 6, 99, 4, 9, 99, 0]);
+        assert_eq!(optimized.threaded, 2);
+
+        check_equivalence(
+            &program::parse(program).unwrap(),
+            &optimized.program,
+            &[vec![0], vec![1]],
+            &Limits::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn removes_jumps_to_the_next_instruction() {
+        assert_eq!(optimized("1105,1,7,104,1,99,0,1105,1,3").program, [104, 1, 99]);
+    }
+
+    #[test]
+    fn removes_dead_code() {
+        let optimized = optimized(program::COMPARE_TO_8);
+
+        // The two 98s are never used.
+        assert_eq!(optimized.removed, 2);
+    }
+
+    #[test]
+    fn leaves_self_modifying_programs_alone() {
+        let day5 = program::parse(include_str!("../../inputs/day5.txt")).unwrap();
+        assert_eq!(optimize(&day5), Err(OptimizeError::SelfModifying { addr: 2 }));
+
+        let program = program::parse("1002,4,3,4,33").unwrap();
+        assert_eq!(optimize(&program), Err(OptimizeError::SelfModifying { addr: 0 }));
+    }
+
+    #[test]
+    fn leaves_dynamic_jumps_alone() {
+        let program = program::parse("3,7,1105,1,8,0,0,0,105,1,7,99").unwrap();
+        assert_eq!(optimize(&program), Err(OptimizeError::DynamicJump { addr: 8 }));
+    }
+
+    #[test]
+    fn leaves_unsupported_programs_alone() {
+        assert_eq!(optimize(&[109, 1, 99]), Err(OptimizeError::Relative { addr: 0 }));
+        assert_eq!(optimize(&[1101, 1, 2]), Err(OptimizeError::Undecodable { addr: 0 }));
+        assert_eq!(optimize(&[42]), Err(OptimizeError::Undecodable { addr: 0 }));
+    }
+
+    #[test]
+    fn uses_the_instruction_table() {
+        let _examples = isa::registry::register_examples();
+
+        let program = program::parse(isa::registry::EXAMPLE).unwrap();
+        let optimized = optimize(&program).unwrap();
+        check_equivalence(&program, &optimized.program, &[vec![3], vec![8], vec![-5]], &Limits::default()).unwrap();
+
+        // Subtracts immediates, and jumps on an immediate negative value.
+        let optimized = self::optimized("1142,9,2,13,1143,-1,10,104,0,99,4,13,99,0");
+        assert_eq!(optimized.program, [1143, -1, 3, 4, 6, 99, 7]);
+        assert_eq!(optimized.folded, 1);
+    }
+
+    #[test]
+    fn reports_mismatch() {
+        let original = program::parse("3,0,4,0,99").unwrap();
+        let wrong = program::parse("3,0,104,0,99").unwrap();
+
+        let mismatch = check_equivalence(&original, &wrong, &[vec![0], vec![1]], &Limits::default()).unwrap_err();
+        assert_eq!(mismatch.input, vec![1]);
+        assert_eq!(mismatch.original, Ok(vec![1]));
+        assert_eq!(mismatch.optimized, Ok(vec![0]));
+    }
+}
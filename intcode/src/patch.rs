@@ -0,0 +1,329 @@
+//! Patch files: changes to the cells of a program that are made before it runs.
+//!
+//! A patch file is a list of `address = value` lines, which are always applied, followed by
+//! named patch sets, which are only applied when they're asked for:
+//!
+//! ```text
+//! # Applied whenever the file is used.
+//! 0 = 1
+//!
+//! [1202 program alarm]
+//! 1 = 12
+//! 2 = 2
+//! ```
+//!
+//! `#` starts a comment that runs to the end of the line. A patch may only change a cell that
+//! exists in the program. Patching the opcode of an instruction is allowed, but it's reported as
+//! a [`Warning`], since it's more likely to be a mistake than patching a parameter.
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::isa::{self, Mode};
+
+/// A problem with a patch file, or with applying it to a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// A line that is neither a set name nor an `address = value` pair.
+    Syntax { line: usize },
+    InvalidAddress { line: usize, text: String },
+    InvalidValue { line: usize, text: String },
+
+    /// An address is patched twice in the same set.
+    DuplicateAddress { line: usize, addr: usize },
+    DuplicateSet { line: usize, name: String },
+    UnknownSet(String),
+
+    /// A patch is past the end of the program.
+    OutOfRange { line: usize, addr: usize, len: usize },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchError::Syntax { line } => write!(f, "line {}: expected `[set name]` or `address = value`", line),
+            PatchError::InvalidAddress { line, text } => write!(f, "line {}: invalid address: {:?}", line, text),
+            PatchError::InvalidValue { line, text } => write!(f, "line {}: invalid value: {:?}", line, text),
+            PatchError::DuplicateAddress { line, addr } => {
+                write!(f, "line {}: address {} is already patched", line, addr)
+            }
+            PatchError::DuplicateSet { line, name } => write!(f, "line {}: set `{}` is already defined", line, name),
+            PatchError::UnknownSet(name) => write!(f, "there is no patch set named `{}`", name),
+            PatchError::OutOfRange { line, addr, len } => {
+                write!(f, "line {}: address {} is past the end of the program, which has {} cells", line, addr, len)
+            }
+        }
+    }
+}
+
+impl Error for PatchError {}
+
+/// A change to a single cell.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Patch {
+    pub addr: usize,
+    pub value: i32,
+
+    /// The line of the patch file that the patch is on.
+    pub line: usize,
+}
+
+/// A patch that changes the opcode of an instruction that the program runs when it starts.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub patch: Patch,
+
+    /// The cell that was replaced.
+    pub instr: i32,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}: address {} is the opcode of an instruction ({} replaced by {})",
+            self.patch.line, self.patch.addr, self.instr, self.patch.value
+        )
+    }
+}
+
+/// A parsed patch file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PatchFile {
+    /// The patches that are always applied.
+    pub base: Vec<Patch>,
+
+    /// The named patch sets, in the order they're defined.
+    pub sets: Vec<(String, Vec<Patch>)>,
+}
+
+impl PatchFile {
+    pub fn read_file<P: AsRef<Path>>(path: P) -> Result<PatchFile, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        Ok(PatchFile::parse(&text)?)
+    }
+
+    pub fn parse(text: &str) -> Result<PatchFile, PatchError> {
+        let mut file = PatchFile::default();
+
+        for (line, text) in (1..).zip(text.lines()) {
+            let text = text.split('#').next().unwrap().trim();
+            if text.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = text.strip_prefix('[').and_then(|name| name.strip_suffix(']')) {
+                let name = name.trim().to_owned();
+
+                if file.set(&name).is_some() {
+                    return Err(PatchError::DuplicateSet { line, name });
+                }
+
+                file.sets.push((name, vec![]));
+                continue;
+            }
+
+            let (addr, value) = match text.find('=') {
+                Some(i) => (text[..i].trim(), text[i + 1..].trim()),
+                None => return Err(PatchError::Syntax { line }),
+            };
+
+            let addr = addr.parse().map_err(|_| PatchError::InvalidAddress { line, text: addr.to_owned() })?;
+            let value = value.parse().map_err(|_| PatchError::InvalidValue { line, text: value.to_owned() })?;
+
+            let patches = match file.sets.last_mut() {
+                Some((_, patches)) => patches,
+                None => &mut file.base,
+            };
+
+            if patches.iter().any(|patch| patch.addr == addr) {
+                return Err(PatchError::DuplicateAddress { line, addr });
+            }
+
+            patches.push(Patch { addr, value, line });
+        }
+
+        Ok(file)
+    }
+
+    /// The patches of a named set.
+    pub fn set(&self, name: &str) -> Option<&[Patch]> {
+        self.sets.iter().find(|(set, _)| set == name).map(|(_, patches)| &patches[..])
+    }
+
+    /// The patches that apply when the set `set` is used: the base patches followed by the
+    /// patches in the set, which win if they patch the same address.
+    pub fn patches(&self, set: Option<&str>) -> Result<Vec<Patch>, PatchError> {
+        let mut patches = self.base.clone();
+
+        if let Some(name) = set {
+            let set = self.set(name).ok_or_else(|| PatchError::UnknownSet(name.to_owned()))?;
+            patches.extend_from_slice(set);
+        }
+
+        Ok(patches)
+    }
+
+    /// Applies the base patches and the patches of `set` to a program.
+    ///
+    /// The program is left untouched if any patch is past its end. Otherwise, the returned
+    /// warnings list the patches that changed the opcode of an instruction.
+    pub fn apply(&self, program: &mut [i32], set: Option<&str>) -> Result<Vec<Warning>, PatchError> {
+        let patches = self.patches(set)?;
+
+        if let Some(patch) = patches.iter().find(|patch| patch.addr >= program.len()) {
+            return Err(PatchError::OutOfRange { line: patch.line, addr: patch.addr, len: program.len() });
+        }
+
+        let opcodes = opcode_addresses(program);
+
+        let warnings = patches
+            .iter()
+            .filter(|patch| opcodes.contains(&patch.addr) && program[patch.addr] != patch.value)
+            .map(|&patch| Warning { patch, instr: program[patch.addr] })
+            .collect();
+
+        for patch in patches {
+            program[patch.addr] = patch.value;
+        }
+
+        Ok(warnings)
+    }
+}
+
+/// The addresses of the opcodes of the instructions that run when the program starts, found by
+/// following the program from address 0 until it halts, jumps somewhere that depends on memory,
+/// or reaches a cell that isn't a standard instruction. Both sides of conditional jumps with
+/// immediate targets are followed.
+fn opcode_addresses(program: &[i32]) -> HashSet<usize> {
+    let mut opcodes = HashSet::new();
+    let mut queue = vec![0];
+
+    while let Some(addr) = queue.pop() {
+        let instr = match program.get(addr) {
+            Some(&instr) if instr >= 0 && !opcodes.contains(&addr) => instr,
+            _ => continue,
+        };
+
+        let isa::Decoded { opcode, mut modes } = isa::decode(instr);
+
+        let instruction = match isa::lookup(opcode) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+
+        opcodes.insert(addr);
+
+        if instruction.halts() {
+            continue;
+        }
+
+        queue.push(addr + instruction.size());
+
+        if instruction.jumps() && modes.nth(1) == Some(Ok(Mode::Immediate)) {
+            if let Some(target) = program.get(addr + 2).and_then(|&target| usize::try_from(target).ok()) {
+                queue.push(target);
+            }
+        }
+    }
+
+    opcodes
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::{Patch, PatchError, PatchFile, Warning};
+
+    #[test]
+    fn parses_sets() {
+        let file = PatchFile::parse(indoc!("
+            # Always applied.
+            5 = -1
+
+            [alarm]
+            1 = 12  # noun
+            2 = 2
+
+            [empty]
+        "))
+        .unwrap();
+
+        assert_eq!(file.base, vec![Patch { addr: 5, value: -1, line: 2 }]);
+        assert_eq!(file.set("alarm").unwrap(), &[Patch { addr: 1, value: 12, line: 5 }, Patch { addr: 2, value: 2, line: 6 }]);
+        assert_eq!(file.set("empty").unwrap(), &[]);
+        assert_eq!(file.set("missing"), None);
+
+        assert_eq!(file.patches(None).unwrap().len(), 1);
+        assert_eq!(file.patches(Some("alarm")).unwrap().len(), 3);
+        assert_eq!(file.patches(Some("missing")), Err(PatchError::UnknownSet(String::from("missing"))));
+    }
+
+    #[test]
+    fn applies_day2_alarm() {
+        let mut program = crate::program::parse(include_str!("../../inputs/day2.txt")).unwrap();
+        let file = PatchFile::parse(include_str!("../../inputs/day2.patch")).unwrap();
+
+        assert_eq!(file.apply(&mut program, Some("1202 program alarm")), Ok(vec![]));
+        assert_eq!(&program[..3], &[1, 12, 2]);
+    }
+
+    #[test]
+    fn warns_about_opcodes() {
+        // Address 4 is the opcode of the second instruction, 9 is the target of the jump and 12 is
+        // after the halt.
+        let program = vec![1101, 1, 1, 12, 1105, 1, 9, 4, 12, 99, 0, 0, 0];
+        let file = PatchFile::parse("1 = 5\n4 = 2\n9 = 99\n[jump]\n9 = 4\n12 = 7").unwrap();
+
+        let mut patched = program.clone();
+        assert_eq!(
+            file.apply(&mut patched, None),
+            Ok(vec![Warning { patch: Patch { addr: 4, value: 2, line: 2 }, instr: 1105 }])
+        );
+        assert_eq!(&patched[..5], &[1101, 5, 1, 12, 2]);
+
+        let mut patched = program;
+        let warnings = file.apply(&mut patched, Some("jump")).unwrap();
+        assert_eq!(warnings.iter().map(|warning| warning.patch.addr).collect::<Vec<_>>(), vec![4, 9]);
+        assert_eq!(patched[9], 4);
+        assert_eq!(
+            warnings[0].to_string(),
+            "line 2: address 4 is the opcode of an instruction (1105 replaced by 2)"
+        );
+    }
+
+    #[test]
+    fn validates_addresses() {
+        let file = PatchFile::parse("1 = 2\n10 = 3").unwrap();
+
+        let mut program = vec![1, 0, 0, 0, 99];
+        assert_eq!(file.apply(&mut program, None), Err(PatchError::OutOfRange { line: 2, addr: 10, len: 5 }));
+        assert_eq!(program, vec![1, 0, 0, 0, 99]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(PatchFile::parse("1 = 2\nfoo"), Err(PatchError::Syntax { line: 2 }));
+        assert_eq!(
+            PatchFile::parse("-1 = 2"),
+            Err(PatchError::InvalidAddress { line: 1, text: String::from("-1") })
+        );
+        assert_eq!(
+            PatchFile::parse("1 = x"),
+            Err(PatchError::InvalidValue { line: 1, text: String::from("x") })
+        );
+        assert_eq!(PatchFile::parse("1 = 2\n1 = 3"), Err(PatchError::DuplicateAddress { line: 2, addr: 1 }));
+        assert_eq!(
+            PatchFile::parse("[a]\n[a]"),
+            Err(PatchError::DuplicateSet { line: 2, name: String::from("a") })
+        );
+
+        // The same address may be patched by the base and by a set.
+        assert!(PatchFile::parse("1 = 2\n[a]\n1 = 3").is_ok());
+    }
+}
@@ -0,0 +1,312 @@
+//! Loading and saving Intcode programs.
+//!
+//! Programs are usually stored as text: cells separated by commas, whitespace or both, with `#`
+//! starting a comment that runs to the end of the line. Programs can also be stored in a compact
+//! binary format, which is a header followed by each cell as a zigzag-encoded LEB128 varint.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The header of the binary format. The leading NUL byte makes sure that it can't be mistaken for
+/// a text program.
+pub const BINARY_HEADER: &[u8] = b"\0ICB";
+
+/// A cell of a text program that isn't a valid value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The index that the cell would have had in memory.
+    pub index: usize,
+
+    /// The text of the cell. Empty if there was nothing between two commas.
+    pub text: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.text.is_empty() {
+            write!(f, "cell {} is empty", self.index)
+        } else {
+            write!(f, "cell {} is not a valid value: {:?}", self.index, self.text)
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// A problem with a binary program.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    MissingHeader,
+
+    /// The program ended in the middle of the cell at `index`.
+    Truncated { index: usize },
+
+    /// The cell at `index` doesn't fit in a cell.
+    Overflow { index: usize },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::MissingHeader => write!(f, "missing binary program header"),
+            DecodeError::Truncated { index } => write!(f, "cell {} is truncated", index),
+            DecodeError::Overflow { index } => write!(f, "cell {} is too large", index),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// An error encountered while loading a program from a file.
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(ParseError),
+    Decode(DecodeError),
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+impl From<ParseError> for LoadError {
+    fn from(e: ParseError) -> Self {
+        LoadError::Parse(e)
+    }
+}
+
+impl From<DecodeError> for LoadError {
+    fn from(e: DecodeError) -> Self {
+        LoadError::Decode(e)
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "could not read program: {}", e),
+            LoadError::Parse(e) => write!(f, "could not parse program: {}", e),
+            LoadError::Decode(e) => write!(f, "could not decode program: {}", e),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io(e) => Some(e),
+            LoadError::Parse(e) => Some(e),
+            LoadError::Decode(e) => Some(e),
+        }
+    }
+}
+
+/// Parses a text program.
+pub fn parse(text: &str) -> Result<Vec<i32>, ParseError> {
+    let text = text
+        .lines()
+        .map(|line| line.split('#').next().unwrap())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut cells = vec![];
+
+    if text.trim().is_empty() {
+        return Ok(cells);
+    }
+
+    for chunk in text.split(',') {
+        let mut words = chunk.split_whitespace().peekable();
+
+        if words.peek().is_none() {
+            return Err(ParseError { index: cells.len(), text: String::new() });
+        }
+
+        for word in words {
+            match word.parse() {
+                Ok(cell) => cells.push(cell),
+                Err(_) => return Err(ParseError { index: cells.len(), text: word.to_owned() }),
+            }
+        }
+    }
+
+    Ok(cells)
+}
+
+/// Serializes a program to the canonical text form: cells separated by commas, with no
+/// whitespace.
+pub fn serialize(cells: &[i32]) -> String {
+    cells.iter().map(ToString::to_string).collect::<Vec<_>>().join(",")
+}
+
+/// Encodes a program in the binary format.
+pub fn encode(cells: &[i32]) -> Vec<u8> {
+    let mut bytes = BINARY_HEADER.to_vec();
+
+    for &cell in cells {
+        let mut zigzag = ((cell << 1) ^ (cell >> 31)) as u32;
+
+        loop {
+            let byte = (zigzag & 0x7f) as u8;
+            zigzag >>= 7;
+
+            if zigzag == 0 {
+                bytes.push(byte);
+                break;
+            }
+
+            bytes.push(byte | 0x80);
+        }
+    }
+
+    bytes
+}
+
+/// Decodes a program in the binary format.
+pub fn decode(bytes: &[u8]) -> Result<Vec<i32>, DecodeError> {
+    if !bytes.starts_with(BINARY_HEADER) {
+        return Err(DecodeError::MissingHeader);
+    }
+
+    let mut cells = vec![];
+    let mut bytes = bytes[BINARY_HEADER.len()..].iter();
+
+    while bytes.len() > 0 {
+        let index = cells.len();
+        let mut zigzag = 0u32;
+        let mut shift = 0;
+
+        loop {
+            let byte = *bytes.next().ok_or(DecodeError::Truncated { index })?;
+
+            let bits = u32::from(byte & 0x7f);
+            if shift >= 32 || (bits << shift) >> shift != bits {
+                return Err(DecodeError::Overflow { index });
+            }
+
+            zigzag |= bits << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+
+        cells.push((zigzag >> 1) as i32 ^ -((zigzag & 1) as i32));
+    }
+
+    Ok(cells)
+}
+
+/// Reads a program from a file, detecting whether it is in the text or the binary format.
+pub fn read_file<P: AsRef<Path>>(path: P) -> Result<Vec<i32>, LoadError> {
+    let bytes = fs::read(path)?;
+
+    if bytes.starts_with(BINARY_HEADER) {
+        return Ok(decode(&bytes)?);
+    }
+
+    let text = String::from_utf8(bytes)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    Ok(parse(&text)?)
+}
+
+/// The larger example from day 5, which tests share. It outputs 999 if the input is below 8, 1000
+/// if it's equal to 8 and 1001 if it's above.
+#[cfg(test)]
+pub(crate) const COMPARE_TO_8: &str = include_str!("../../inputs/day5_compare_to_8.txt");
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::{decode, encode, parse, read_file, serialize, DecodeError, LoadError, ParseError};
+
+    #[test]
+    fn canonical() {
+        assert_eq!(parse("1,0,0,0,99"), Ok(vec![1, 0, 0, 0, 99]));
+        assert_eq!(parse("1101,100,-1,4,0\n"), Ok(vec![1101, 100, -1, 4, 0]));
+    }
+
+    #[test]
+    fn comments_and_whitespace() {
+        let program = "
+            # Outputs its input.
+            3,0,   # read
+
+            4,0    # write
+            99
+        ";
+
+        assert_eq!(parse(program), Ok(vec![3, 0, 4, 0, 99]));
+        assert_eq!(parse("1 0 0 0\t99\n\n"), Ok(vec![1, 0, 0, 0, 99]));
+        assert_eq!(parse("# nothing here\n\n"), Ok(vec![]));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse("1,0,x,0,99"), Err(ParseError { index: 2, text: String::from("x") }));
+        assert_eq!(parse("1 0\n0,,99"), Err(ParseError { index: 3, text: String::new() }));
+        assert_eq!(parse("1,2,"), Err(ParseError { index: 2, text: String::new() }));
+        assert_eq!(
+            parse("1,99999999999").unwrap_err().to_string(),
+            r#"cell 1 is not a valid value: "99999999999""#
+        );
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let program = "3,9,8,9,10,9,4,9,99,-1,8";
+        assert_eq!(serialize(&parse(program).unwrap()), program);
+        assert_eq!(serialize(&parse("# comment\n1 0 0 0 99\n").unwrap()), "1,0,0,0,99");
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let cells = vec![0, 1, -1, 63, -64, 64, 1105, i32::MAX, i32::MIN];
+        let bytes = encode(&cells);
+
+        assert_eq!(&bytes[4..7], &[0, 2, 1]);
+        assert_eq!(decode(&bytes), Ok(cells));
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(decode(b"1,2,3"), Err(DecodeError::MissingHeader));
+        assert_eq!(decode(b"\0ICB\x02\x80"), Err(DecodeError::Truncated { index: 1 }));
+        assert_eq!(
+            decode(b"\0ICB\xff\xff\xff\xff\x7f"),
+            Err(DecodeError::Overflow { index: 0 })
+        );
+    }
+
+    #[test]
+    fn read_files() {
+        let dir = env::temp_dir().join(format!("intcode-program-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let text = dir.join("program.txt");
+        fs::write(&text, "3,0,4,0,99\n").unwrap();
+        assert_eq!(read_file(&text).unwrap(), vec![3, 0, 4, 0, 99]);
+
+        let binary = dir.join("program.icb");
+        fs::write(&binary, encode(&[3, 0, 4, 0, 99])).unwrap();
+        assert_eq!(read_file(&binary).unwrap(), vec![3, 0, 4, 0, 99]);
+
+        let invalid = dir.join("invalid.txt");
+        fs::write(&invalid, "3,0,4,O,99\n").unwrap();
+        match read_file(&invalid) {
+            Err(LoadError::Parse(e)) => assert_eq!(e.index, 3),
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,194 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+
+/// What a region of memory is for.
+///
+/// Code may be executed but not written, while data and stacks may be written but not executed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RegionKind {
+    Code,
+    Data,
+    Stack,
+}
+
+impl fmt::Display for RegionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self {
+            RegionKind::Code => "code",
+            RegionKind::Data => "data",
+            RegionKind::Stack => "stack",
+        };
+
+        f.write_str(kind)
+    }
+}
+
+/// What happens when an instruction violates the attributes of a region.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Behavior {
+    /// Stop the machine with [`ExecutionError::Protection`](crate::ExecutionError::Protection)
+    /// before the instruction is executed.
+    Trap,
+
+    /// Pass the violation to the hook set with [`Protection::on_warning`], then execute the
+    /// instruction.
+    Warn,
+
+    /// Execute the instruction.
+    Allow,
+}
+
+/// A region of memory with attributes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub range: Range<usize>,
+    pub kind: RegionKind,
+    pub behavior: Behavior,
+}
+
+/// How an instruction violated the attributes of a region.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Access {
+    /// The instruction wrote to a code region.
+    Write,
+
+    /// The instruction is in a data or stack region.
+    Execute,
+}
+
+/// An instruction that violated the attributes of a region.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub access: Access,
+
+    /// The address that was accessed.
+    pub addr: usize,
+
+    /// The address of the instruction.
+    pub pc: usize,
+    pub region: RegionKind,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.access {
+            Access::Write => write!(
+                f,
+                "the instruction at address {} wrote to address {} in a {} region",
+                self.pc, self.addr, self.region
+            ),
+            Access::Execute => write!(f, "executed address {} in a {} region", self.addr, self.region),
+        }
+    }
+}
+
+impl Error for Violation {}
+
+/// A write to a cell that had already been executed as part of an instruction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SelfModification {
+    /// The address of the instruction that wrote the cell.
+    pub pc: usize,
+    pub addr: usize,
+    pub old: i32,
+    pub new: i32,
+
+    /// The kind of the region that the cell is in, if any.
+    pub region: Option<RegionKind>,
+}
+
+type Hook = dyn FnMut(&Violation);
+
+/// Attributes for regions of memory, and a report of self-modifying writes.
+///
+/// Memory outside of every region has no attributes. If regions overlap, the one that was added
+/// first applies.
+#[derive(Default, Clone)]
+pub struct Protection {
+    regions: Vec<Region>,
+    hook: Option<Rc<RefCell<Box<Hook>>>>,
+    modifications: Vec<SelfModification>,
+}
+
+impl Protection {
+    pub fn new() -> Self {
+        Protection::default()
+    }
+
+    /// Adds a region of memory with the given attributes.
+    pub fn add_region(&mut self, range: Range<usize>, kind: RegionKind, behavior: Behavior) {
+        self.regions.push(Region { range, kind, behavior });
+    }
+
+    /// Sets the hook that is called for violations of regions whose behavior is
+    /// [`Behavior::Warn`]. Violations are ignored if there is no hook.
+    pub fn on_warning<F>(&mut self, hook: F)
+    where
+        F: FnMut(&Violation) + 'static,
+    {
+        self.hook = Some(Rc::new(RefCell::new(Box::new(hook))));
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// Every write to a cell that had been executed before it was written, in order.
+    pub fn self_modifications(&self) -> &[SelfModification] {
+        &self.modifications
+    }
+
+    /// The region that `addr` is in, if any.
+    pub fn region(&self, addr: usize) -> Option<&Region> {
+        self.regions.iter().find(|region| region.range.contains(&addr))
+    }
+
+    /// Checks whether `access` of `addr` by the instruction at `pc` is allowed, calling the hook
+    /// if it should be warned about.
+    pub(crate) fn check(&self, access: Access, addr: usize, pc: usize) -> Result<(), Violation> {
+        let region = match self.region(addr) {
+            Some(region) => region,
+            None => return Ok(()),
+        };
+
+        let violates = match access {
+            Access::Write => region.kind == RegionKind::Code,
+            Access::Execute => region.kind != RegionKind::Code,
+        };
+
+        if !violates {
+            return Ok(());
+        }
+
+        let violation = Violation { access, addr, pc, region: region.kind };
+
+        match region.behavior {
+            Behavior::Trap => return Err(violation),
+            Behavior::Warn => {
+                if let Some(hook) = &self.hook {
+                    (hook.borrow_mut())(&violation);
+                }
+            }
+            Behavior::Allow => (),
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn record(&mut self, modification: SelfModification) {
+        self.modifications.push(modification);
+    }
+}
+
+impl fmt::Debug for Protection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Protection")
+            .field("regions", &self.regions)
+            .field("hook", &self.hook.is_some())
+            .field("modifications", &self.modifications)
+            .finish()
+    }
+}
@@ -0,0 +1,208 @@
+//! A robot on a 2D grid, controlled by an Intcode program.
+//!
+//! Before each move, the program reads the color of the cell under the robot. It then outputs
+//! the color to paint that cell and the direction to turn: 0 for left and 1 for right. The robot
+//! paints the cell, turns, and moves forward one cell.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use grid::{Direction, Location};
+
+use crate::{ExecutionError, Intcode, Limits, Status};
+
+pub const BLACK: i32 = 0;
+pub const WHITE: i32 = 1;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RobotError {
+    Execution(ExecutionError),
+
+    /// The program output a turn that is neither left nor right.
+    InvalidTurn(i32),
+}
+
+impl From<ExecutionError> for RobotError {
+    fn from(e: ExecutionError) -> Self {
+        RobotError::Execution(e)
+    }
+}
+
+impl fmt::Display for RobotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RobotError::Execution(e) => write!(f, "{}", e),
+            RobotError::InvalidTurn(turn) => write!(f, "invalid turn: {}", turn),
+        }
+    }
+}
+
+impl Error for RobotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RobotError::Execution(e) => Some(e),
+            RobotError::InvalidTurn(_) => None,
+        }
+    }
+}
+
+pub struct Robot {
+    computer: Intcode,
+
+    /// The color of every cell that has been painted. Other cells are black.
+    grid: HashMap<Location, i32>,
+    position: Location,
+    heading: Direction,
+}
+
+impl Robot {
+    /// Creates a robot at the origin, facing up, on an all-black grid.
+    pub fn new(computer: Intcode) -> Self {
+        Robot {
+            computer,
+            grid: HashMap::new(),
+            position: Location::default(),
+            heading: Direction::Up,
+        }
+    }
+
+    pub fn position(&self) -> Location {
+        self.position
+    }
+
+    pub fn heading(&self) -> Direction {
+        self.heading
+    }
+
+    pub fn color(&self, location: Location) -> i32 {
+        self.grid.get(&location).copied().unwrap_or(BLACK)
+    }
+
+    /// Paints a cell, such as the starting cell before the robot starts running.
+    pub fn paint(&mut self, location: Location, color: i32) {
+        self.grid.insert(location, color);
+    }
+
+    /// The cells that have been painted at least once, and their colors.
+    pub fn painted(&self) -> &HashMap<Location, i32> {
+        &self.grid
+    }
+
+    /// Runs the program until it halts.
+    pub fn run(&mut self) -> Result<(), RobotError> {
+        let mut output = vec![];
+
+        loop {
+            self.computer.push_input(self.color(self.position));
+
+            let status = self.computer.run(&Limits::default())?;
+            output.extend(self.computer.take_output());
+
+            let moves = output.len() - output.len() % 2;
+            for instruction in output.drain(..moves).collect::<Vec<_>>().chunks(2) {
+                self.paint(self.position, instruction[0]);
+
+                self.heading = match instruction[1] {
+                    0 => self.heading.turn_left(),
+                    1 => self.heading.turn_right(),
+                    turn => return Err(RobotError::InvalidTurn(turn)),
+                };
+
+                self.position = self.position.step(self.heading);
+            }
+
+            if status == Status::Halted {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Draws the painted part of the grid, with white cells as `#` and black cells as `.`. Up is
+    /// at the top.
+    pub fn render(&self) -> String {
+        if self.grid.is_empty() {
+            return String::new();
+        }
+
+        let min_x = self.grid.keys().map(|location| location.x).min().unwrap();
+        let max_x = self.grid.keys().map(|location| location.x).max().unwrap();
+        let min_y = self.grid.keys().map(|location| location.y).min().unwrap();
+        let max_y = self.grid.keys().map(|location| location.y).max().unwrap();
+
+        let mut out = String::new();
+
+        for y in (min_y..=max_y).rev() {
+            for x in min_x..=max_x {
+                out.push(if self.color(Location { x, y }) == WHITE { '#' } else { '.' });
+            }
+
+            out.push('\n');
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use grid::{Direction, Location};
+    use indoc::indoc;
+
+    use crate::Intcode;
+
+    use super::{Robot, RobotError, WHITE};
+
+    /// A program that reads the current color before outputting each of the given moves.
+    fn scripted(moves: &[(i32, i32)]) -> Intcode {
+        let mut program = vec![];
+
+        for &(color, turn) in moves {
+            program.extend(&[3, 0, 104, color, 104, turn]);
+        }
+
+        program.push(99);
+
+        Intcode::new(program, vec![])
+    }
+
+    #[test]
+    fn example() {
+        let mut robot = Robot::new(scripted(&[(1, 0), (0, 0), (1, 0), (1, 0), (0, 1), (1, 0), (1, 0)]));
+        robot.run().unwrap();
+
+        assert_eq!(robot.painted().len(), 6);
+        assert_eq!(robot.position(), Location { x: 0, y: 1 });
+        assert_eq!(robot.heading(), Direction::Left);
+        assert_eq!(
+            robot.render(),
+            indoc!("
+                ..#
+                ..#
+                ##.
+            ")
+            .trim_start()
+        );
+    }
+
+    #[test]
+    fn reads_color() {
+        // Four times, paints the cell with the opposite of its color and turns right.
+        let program = "3,100,1008,100,0,101,4,101,104,1,1001,102,1,102,1007,102,4,103,1005,103,0,99";
+
+        let mut robot = Robot::new(Intcode::load(program, vec![]));
+        robot.paint(Location { x: 0, y: 0 }, WHITE);
+        robot.paint(Location { x: 1, y: 0 }, WHITE);
+        robot.run().unwrap();
+
+        assert_eq!(robot.position(), Location { x: 0, y: 0 });
+        assert_eq!(robot.heading(), Direction::Up);
+        assert_eq!(robot.render(), "..\n##\n");
+    }
+
+    #[test]
+    fn invalid_turn() {
+        let mut robot = Robot::new(scripted(&[(1, 2)]));
+        assert_eq!(robot.run(), Err(RobotError::InvalidTurn(2)));
+    }
+}
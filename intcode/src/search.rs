@@ -0,0 +1,340 @@
+//! Searching the states reachable by a paused Intcode machine.
+//!
+//! Each search node is a machine waiting for input, together with the host's view of the world,
+//! such as the position of a droid. Expanding a node clones the machine once per action, feeds
+//! it the action's input and runs it until it needs input again. Cloning is cheap because memory
+//! pages are shared until they are written to.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::Hash;
+
+use crate::{ExecutionError, Intcode, Limits};
+
+/// Describes a world that is explored through an Intcode program.
+pub trait Explore {
+    /// The host's view of the world.
+    type State: Clone;
+
+    type Action: Clone;
+
+    /// Identifies states that are the same for the purposes of the search, such as the position
+    /// of a droid, or [`Intcode::state_hash`].
+    type Key: Hash + Eq;
+
+    fn actions(&self, state: &Self::State) -> Vec<Self::Action>;
+
+    /// The input that performs an action.
+    fn input(&self, action: &Self::Action) -> Vec<i32>;
+
+    /// Updates the state with the output that the program produced for an action. Returns `None`
+    /// if the action leads nowhere.
+    fn transition(
+        &self,
+        state: &Self::State,
+        action: &Self::Action,
+        output: &[i32],
+    ) -> Option<Self::State>;
+
+    fn key(&self, state: &Self::State, computer: &Intcode) -> Self::Key;
+
+    fn is_goal(&self, state: &Self::State) -> bool;
+
+    /// The cost of taking an action. Ignored by breadth-first search.
+    fn cost(&self, _state: &Self::State, _action: &Self::Action) -> u64 {
+        1
+    }
+
+    /// A lower bound on the cost to reach a goal from a state. Only used by A*.
+    fn heuristic(&self, _state: &Self::State) -> u64 {
+        0
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Strategy {
+    BreadthFirst,
+    Dijkstra,
+    AStar,
+}
+
+/// A node of the search.
+#[derive(Debug, Clone)]
+pub struct Node<S, A> {
+    pub state: S,
+    pub computer: Intcode,
+
+    /// The total cost of the actions taken to reach this node.
+    pub cost: u64,
+
+    /// The actions taken to reach this node from the start.
+    pub path: Vec<A>,
+}
+
+/// The node that reached a goal, if any.
+pub type Found<E> = Option<Node<<E as Explore>::State, <E as Explore>::Action>>;
+
+/// Searches for the cheapest way to reach a goal, starting from a machine that is waiting for
+/// input.
+pub fn search<E: Explore>(
+    explorer: &E,
+    computer: Intcode,
+    start: E::State,
+    strategy: Strategy,
+    limits: &Limits,
+) -> Result<Found<E>, ExecutionError> {
+    let mut goal = None;
+
+    walk(explorer, computer, start, strategy, limits, |node| {
+        if explorer.is_goal(&node.state) {
+            goal = Some(node.clone());
+            false
+        } else {
+            true
+        }
+    })?;
+
+    Ok(goal)
+}
+
+/// Finds the cost of reaching every reachable state, ignoring goals.
+pub fn reachable<E: Explore>(
+    explorer: &E,
+    computer: Intcode,
+    start: E::State,
+    limits: &Limits,
+) -> Result<HashMap<E::Key, u64>, ExecutionError> {
+    let mut costs = HashMap::new();
+
+    walk(explorer, computer, start, Strategy::Dijkstra, limits, |node| {
+        costs.insert(explorer.key(&node.state, &node.computer), node.cost);
+        true
+    })?;
+
+    Ok(costs)
+}
+
+/// Visits each distinct state once, in order of priority, until `visit` returns false.
+fn walk<E, F>(
+    explorer: &E,
+    computer: Intcode,
+    start: E::State,
+    strategy: Strategy,
+    limits: &Limits,
+    mut visit: F,
+) -> Result<(), ExecutionError>
+where
+    E: Explore,
+    F: FnMut(&Node<E::State, E::Action>) -> bool,
+{
+    let priority = |node: &Node<E::State, E::Action>| match strategy {
+        Strategy::BreadthFirst => node.path.len() as u64,
+        Strategy::Dijkstra => node.cost,
+        Strategy::AStar => node.cost + explorer.heuristic(&node.state),
+    };
+
+    let start = Node { state: start, computer, cost: 0, path: vec![] };
+
+    // Nodes are kept out of the heap so that states don't have to be ordered. The sequence number
+    // breaks ties in insertion order, which makes breadth-first search visit nodes level by level.
+    let mut nodes = vec![];
+    let mut queue = BinaryHeap::new();
+    let mut visited = HashSet::new();
+
+    queue.push(Reverse((priority(&start), 0)));
+    nodes.push(Some(start));
+
+    while let Some(Reverse((_, index))) = queue.pop() {
+        let node = nodes[index].take().unwrap();
+
+        if !visited.insert(explorer.key(&node.state, &node.computer)) {
+            continue;
+        }
+
+        if !visit(&node) {
+            break;
+        }
+
+        for action in explorer.actions(&node.state) {
+            let mut computer = node.computer.clone();
+
+            for value in explorer.input(&action) {
+                computer.push_input(value);
+            }
+
+            computer.run(limits)?;
+            let output = computer.take_output();
+
+            let state = match explorer.transition(&node.state, &action, &output) {
+                Some(state) => state,
+                None => continue,
+            };
+
+            if visited.contains(&explorer.key(&state, &computer)) {
+                continue;
+            }
+
+            let mut path = node.path.clone();
+            path.push(action.clone());
+
+            let child = Node {
+                cost: node.cost + explorer.cost(&node.state, &action),
+                state,
+                computer,
+                path,
+            };
+
+            queue.push(Reverse((priority(&child), nodes.len())));
+            nodes.push(Some(child));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Effect, Extensions, Intcode, Limits, Param};
+
+    use super::{reachable, search, Explore, Strategy};
+
+    const MAZE: &[&str] = &[
+        "#######",
+        "#S..#.#",
+        "#.#.#.#",
+        "#.#...#",
+        "#...#G#",
+        "#######",
+    ];
+
+    const GOAL: (i32, i32) = (5, 4);
+
+    /// The change in position for a move command: 1-4 for north, south, west and east. Unlike
+    /// `grid::Location`, `y` grows downwards, like the rows of the maze.
+    fn offset(command: i32) -> (i32, i32) {
+        match command {
+            1 => (0, -1),
+            2 => (0, 1),
+            3 => (-1, 0),
+            4 => (1, 0),
+            _ => unreachable!(),
+        }
+    }
+
+    /// A droid that reads a move command and outputs 0 if it hit a wall, 2 if it reached the
+    /// goal, or 1 otherwise.
+    ///
+    /// The maze itself is a host call, but the droid's position is kept in the machine's memory,
+    /// so each clone has its own.
+    fn droid() -> Intcode {
+        let mut program = vec![
+            3, 100, // read the command
+            60, 101, 102, 100, 103, 101, 102, // move
+            4, 103, // output the status
+            1105, 1, 0,
+        ];
+        program.resize(104, 0);
+        program[101] = 1;
+        program[102] = 1;
+
+        let params = [Param::Read, Param::Read, Param::Read, Param::Write, Param::Write, Param::Write];
+
+        let mut extensions = Extensions::new();
+        extensions.register(60, &params, |args, results| {
+            let (x, y) = (args[0], args[1]);
+            let (dx, dy) = offset(args[2]);
+
+            let (status, x, y) = match MAZE[(y + dy) as usize].as_bytes()[(x + dx) as usize] {
+                b'#' => (0, x, y),
+                b'G' => (2, x + dx, y + dy),
+                _ => (1, x + dx, y + dy),
+            };
+
+            results.copy_from_slice(&[status, x, y]);
+            Effect::Continue
+        });
+
+        let mut computer = Intcode::new(program, vec![]);
+        computer.set_extensions(extensions);
+        computer
+    }
+
+    struct Droid {
+        dedupe_by_machine: bool,
+    }
+
+    impl Explore for Droid {
+        /// The droid's position and the last status it reported.
+        type State = ((i32, i32), i32);
+        type Action = i32;
+        type Key = u64;
+
+        fn actions(&self, _: &Self::State) -> Vec<i32> {
+            vec![1, 2, 3, 4]
+        }
+
+        fn input(&self, &command: &i32) -> Vec<i32> {
+            vec![command]
+        }
+
+        fn transition(&self, &((x, y), _): &Self::State, &command: &i32, output: &[i32]) -> Option<Self::State> {
+            let (dx, dy) = offset(command);
+
+            match *output {
+                [0] => None,
+                [status] => Some(((x + dx, y + dy), status)),
+                _ => panic!("unexpected output: {:?}", output),
+            }
+        }
+
+        fn key(&self, &((x, y), _): &Self::State, computer: &Intcode) -> u64 {
+            if self.dedupe_by_machine {
+                computer.state_hash()
+            } else {
+                (x * 1000 + y) as u64
+            }
+        }
+
+        fn is_goal(&self, &(_, status): &Self::State) -> bool {
+            status == 2
+        }
+
+        fn heuristic(&self, &((x, y), _): &Self::State) -> u64 {
+            ((GOAL.0 - x).abs() + (GOAL.1 - y).abs()) as u64
+        }
+    }
+
+    #[test]
+    fn shortest_path() {
+        let start = ((1, 1), 1);
+
+        for &strategy in &[Strategy::BreadthFirst, Strategy::Dijkstra, Strategy::AStar] {
+            for &dedupe_by_machine in &[false, true] {
+                let droid = droid();
+                let explorer = Droid { dedupe_by_machine };
+
+                let found = search(&explorer, droid.clone(), start, strategy, &Limits::default())
+                    .unwrap()
+                    .unwrap();
+
+                assert_eq!(found.cost, 7, "{:?}", strategy);
+                assert_eq!(found.path, vec![4, 4, 2, 2, 4, 4, 2]);
+                assert_eq!(found.state.0, GOAL);
+                assert_eq!(found.computer.mem()[101], GOAL.0);
+                assert_eq!(found.computer.mem()[102], GOAL.1);
+
+                // Only the page holding the droid's position was copied.
+                assert_eq!(found.computer.mem().shared_pages(droid.mem()), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn reachable_cells() {
+        let explorer = Droid { dedupe_by_machine: false };
+        let costs = reachable(&explorer, droid(), ((1, 1), 1), &Limits::default()).unwrap();
+
+        assert_eq!(costs.len(), 15);
+        assert_eq!(costs.values().max(), Some(&8));
+    }
+}
@@ -0,0 +1,434 @@
+//! Recording the input and output of a run, and replaying it.
+//!
+//! A [`Recorder`] runs a computer like [`Intcode::run`] does, and logs every value that the
+//! program reads or outputs together with the step it happened at: the number of instructions
+//! that were executed before it. The log is a [`Session`], which is saved as text. This is the
+//! log of day 5's program that compares its input with 8, given 8:
+//!
+//! ```text
+//! in 0 8
+//! out 2 1
+//! halt 3
+//! ```
+//!
+//! [`replay`] runs a program with the recorded input, feeding each value only when the program
+//! asks for it, and reports the first event that differs from the log. Replaying a session with
+//! the program that recorded it reproduces the run exactly, so a divergence means that the
+//! program or the machine changed.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{isa, ExecutionError, Intcode, Limits, Status};
+
+/// Something that happened during a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// An input instruction read `value`.
+    Input { step: u64, value: i32 },
+
+    /// An output instruction output `value`.
+    Output { step: u64, value: i32 },
+
+    /// The program halted.
+    Halt { step: u64 },
+
+    /// The run stopped with an error.
+    Error { step: u64, message: String },
+}
+
+impl Event {
+    pub fn step(&self) -> u64 {
+        match *self {
+            Event::Input { step, .. } | Event::Output { step, .. } | Event::Halt { step } | Event::Error { step, .. } => {
+                step
+            }
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Input { step, value } => write!(f, "in {} {}", step, value),
+            Event::Output { step, value } => write!(f, "out {} {}", step, value),
+            Event::Halt { step } => write!(f, "halt {}", step),
+            Event::Error { step, message } => write!(f, "error {} {}", step, message),
+        }
+    }
+}
+
+/// A line of a session log that isn't an event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogError {
+    pub line: usize,
+    pub text: String,
+}
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: not an event: {:?}", self.line, self.text)
+    }
+}
+
+impl Error for LogError {}
+
+/// The events of a run, in order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Session {
+    pub events: Vec<Event>,
+}
+
+impl Session {
+    /// The values that the program read.
+    pub fn inputs(&self) -> impl Iterator<Item = i32> + '_ {
+        self.events.iter().filter_map(|event| match *event {
+            Event::Input { value, .. } => Some(value),
+            _ => None,
+        })
+    }
+
+    /// The values that the program output.
+    pub fn outputs(&self) -> impl Iterator<Item = i32> + '_ {
+        self.events.iter().filter_map(|event| match *event {
+            Event::Output { value, .. } => Some(value),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Session {
+    type Err = LogError;
+
+    /// Parses a session log. Blank lines are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut events = vec![];
+
+        for (line, text) in (1..).zip(s.lines()) {
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let error = || LogError { line, text: text.to_owned() };
+
+            let mut parts = text.trim().splitn(3, ' ');
+            let kind = parts.next().unwrap();
+            let step = parts.next().and_then(|step| step.parse().ok()).ok_or_else(error)?;
+            let rest = parts.next();
+
+            let event = match (kind, rest) {
+                ("in", Some(value)) => Event::Input { step, value: value.parse().map_err(|_| error())? },
+                ("out", Some(value)) => Event::Output { step, value: value.parse().map_err(|_| error())? },
+                ("halt", None) => Event::Halt { step },
+                ("error", Some(message)) => Event::Error { step, message: message.to_owned() },
+                _ => return Err(error()),
+            };
+
+            events.push(event);
+        }
+
+        Ok(Session { events })
+    }
+}
+
+/// Runs a computer and records its input and output.
+#[derive(Debug)]
+pub struct Recorder {
+    computer: Intcode,
+
+    /// The number of instructions executed so far.
+    steps: u64,
+    session: Session,
+}
+
+impl Recorder {
+    pub fn new(computer: Intcode) -> Self {
+        Recorder { computer, steps: 0, session: Session::default() }
+    }
+
+    pub fn computer(&self) -> &Intcode {
+        &self.computer
+    }
+
+    /// Adds a value to the end of the input queue. It's recorded once the program reads it.
+    pub fn push_input(&mut self, value: i32) {
+        self.computer.push_input(value);
+    }
+
+    /// Removes and returns the output produced so far. It's recorded either way.
+    pub fn take_output(&mut self) -> Vec<i32> {
+        self.computer.take_output()
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    pub fn into_session(self) -> Session {
+        self.session
+    }
+
+    /// Runs the program until it halts or needs more input than there is in the queue, like
+    /// [`Intcode::run`]. Halting and errors are recorded too.
+    pub fn run(&mut self, limits: &Limits) -> Result<Status, ExecutionError> {
+        let mut steps = 0;
+
+        loop {
+            if let Err(e) = limits.check(steps) {
+                return Err(self.record_error(e.into()));
+            }
+
+            let instr = self.computer.mem().get(self.computer.pc());
+            let output_len = self.computer.output().len();
+
+            match self.computer.step(limits) {
+                Ok(None) => (),
+                Ok(Some(Status::NeedsInput)) => return Ok(Status::NeedsInput),
+                Ok(Some(Status::Halted)) => {
+                    self.session.events.push(Event::Halt { step: self.steps });
+                    return Ok(Status::Halted);
+                }
+                Err(e) => return Err(self.record_error(e)),
+            }
+
+            let reads_input = isa::lookup(isa::decode(instr).opcode)
+                .is_some_and(|instruction| matches!(instruction.operation, isa::Operation::Input));
+
+            if reads_input {
+                let value = self.computer.mem().get(self.computer.written()[0]);
+                self.session.events.push(Event::Input { step: self.steps, value });
+            }
+
+            for &value in &self.computer.output()[output_len..] {
+                self.session.events.push(Event::Output { step: self.steps, value });
+            }
+
+            self.steps += 1;
+            steps += 1;
+        }
+    }
+
+    fn record_error(&mut self, error: ExecutionError) -> ExecutionError {
+        self.session.events.push(Event::Error { step: self.steps, message: error.to_string() });
+        error
+    }
+}
+
+/// The first event of a replay that differs from the session log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// The index of the event in the log.
+    pub index: usize,
+
+    /// The event in the log, or `None` if the replay went on after the log ended.
+    pub expected: Option<Event>,
+
+    /// The event of the replay, or `None` if the replay stopped to wait for input that the log
+    /// doesn't have.
+    pub actual: Option<Event>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let describe = |event: &Option<Event>| match event {
+            Some(event) => event.to_string(),
+            None => String::from("nothing"),
+        };
+
+        write!(
+            f,
+            "event {} diverges: expected `{}`, got `{}`",
+            self.index,
+            describe(&self.expected),
+            describe(&self.actual)
+        )
+    }
+}
+
+/// Replays a session log against a program, and returns the first divergence, if there is one.
+///
+/// The replay runs until the program halts or stops with an error, or needs input after the
+/// recorded input has run out. `limits` apply to the whole replay.
+pub fn replay(program: &[i32], session: &Session, limits: &Limits) -> Option<Divergence> {
+    let mut recorder = Recorder::new(Intcode::new(program.to_vec(), vec![]));
+    let mut inputs = session.inputs();
+    let expected = &session.events;
+
+    loop {
+        let remaining = Limits {
+            max_instructions: limits.max_instructions.map(|max| max.saturating_sub(recorder.steps)),
+            ..limits.clone()
+        };
+
+        let status = recorder.run(&remaining);
+
+        let actual = &recorder.session().events;
+        if let Some(index) = (0..actual.len()).find(|&i| expected.get(i) != Some(&actual[i])) {
+            return Some(Divergence { index, expected: expected.get(index).cloned(), actual: Some(actual[index].clone()) });
+        }
+
+        if let Ok(Status::NeedsInput) = status {
+            if let Some(value) = inputs.next() {
+                recorder.push_input(value);
+                continue;
+            }
+        }
+
+        // The replay is over. Every event so far matched, so it diverges only if the log has more.
+        let index = actual.len();
+        return expected.get(index).map(|event| Divergence { index, expected: Some(event.clone()), actual: None });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::program::{self, COMPARE_TO_8};
+    use crate::{Intcode, Limits, Status};
+
+    use super::{replay, Divergence, Event, Recorder, Session};
+
+    /// Echoes each input until it reads 0.
+    const ECHO: &str = "3,11,1006,11,10,4,11,1105,1,0,99,0";
+
+    /// Runs the echo program interactively, feeding each value after the program asks for it.
+    fn record_echo(inputs: &[i32]) -> Session {
+        let mut recorder = Recorder::new(Intcode::load(ECHO, vec![]));
+
+        for &input in inputs {
+            assert_eq!(recorder.run(&Limits::default()), Ok(Status::NeedsInput));
+            recorder.push_input(input);
+        }
+
+        recorder.run(&Limits::default()).unwrap();
+        recorder.into_session()
+    }
+
+    #[test]
+    fn records_events() {
+        let mut recorder = Recorder::new(Intcode::load(COMPARE_TO_8, vec![8]));
+        assert_eq!(recorder.run(&Limits::default()), Ok(Status::Halted));
+        assert_eq!(recorder.take_output(), vec![1000]);
+
+        let session = recorder.into_session();
+        assert_eq!(
+            session.events,
+            vec![
+                Event::Input { step: 0, value: 8 },
+                Event::Output { step: 4, value: 1000 },
+                Event::Halt { step: 6 },
+            ]
+        );
+        assert_eq!(session.to_string(), "in 0 8\nout 4 1000\nhalt 6\n");
+        assert_eq!(session.to_string().parse(), Ok(session));
+    }
+
+    #[test]
+    fn records_streaming_io() {
+        let session = record_echo(&[5, -3, 0]);
+
+        assert_eq!(session.inputs().collect::<Vec<_>>(), vec![5, -3, 0]);
+        assert_eq!(session.outputs().collect::<Vec<_>>(), vec![5, -3]);
+        assert_eq!(session.events[1], Event::Output { step: 2, value: 5 });
+        assert_eq!(session.events[2], Event::Input { step: 4, value: -3 });
+        assert_eq!(session.events.last(), Some(&Event::Halt { step: 10 }));
+    }
+
+    #[test]
+    fn records_errors() {
+        let mut recorder = Recorder::new(Intcode::load("104,1,42", vec![]));
+        assert!(recorder.run(&Limits::default()).is_err());
+
+        assert_eq!(
+            recorder.session().events,
+            vec![
+                Event::Output { step: 0, value: 1 },
+                Event::Error { step: 1, message: String::from("unknown opcode 42 in instruction 42 at address 2") },
+            ]
+        );
+    }
+
+    #[test]
+    fn replays_exactly() {
+        let session = record_echo(&[5, -3, 0]);
+        let echo = program::parse(ECHO).unwrap();
+        assert_eq!(replay(&echo, &session, &Limits::default()), None);
+
+        // A session that stopped while waiting for input.
+        let mut recorder = Recorder::new(Intcode::load(ECHO, vec![7]));
+        recorder.run(&Limits::default()).unwrap();
+        assert_eq!(replay(&echo, recorder.session(), &Limits::default()), None);
+
+        let session = "in 0 3\nout 5 999\nhalt 7\n".parse().unwrap();
+        assert_eq!(replay(&program::parse(COMPARE_TO_8).unwrap(), &session, &Limits::default()), None);
+    }
+
+    #[test]
+    fn flags_changed_output() {
+        let session = record_echo(&[5, 0]);
+
+        // Doubles each value before echoing it.
+        let changed = program::parse("3,15,1006,15,14,1002,15,2,15,4,15,1105,1,0,99,0").unwrap();
+
+        assert_eq!(
+            replay(&changed, &session, &Limits::default()),
+            Some(Divergence {
+                index: 1,
+                expected: Some(Event::Output { step: 2, value: 5 }),
+                actual: Some(Event::Output { step: 3, value: 10 }),
+            })
+        );
+    }
+
+    #[test]
+    fn flags_missing_events() {
+        let session = record_echo(&[5, 6, 0]);
+
+        // Echoes the first input, and then halts.
+        let changed = program::parse("3,9,1105,1,5,4,9,99").unwrap();
+        let divergence = replay(&changed, &session, &Limits::default()).unwrap();
+
+        assert_eq!(divergence.index, 2);
+        assert_eq!(divergence.expected, Some(Event::Input { step: 4, value: 6 }));
+        assert_eq!(divergence.actual, Some(Event::Halt { step: 3 }));
+        assert_eq!(divergence.to_string(), "event 2 diverges: expected `in 4 6`, got `halt 3`");
+
+        // The program waits for more input than the log has.
+        let session = "in 0 5\nout 2 5\nhalt 3\n".parse().unwrap();
+        let divergence = replay(&program::parse(ECHO).unwrap(), &session, &Limits::default()).unwrap();
+        assert_eq!(divergence, Divergence { index: 2, expected: Some(Event::Halt { step: 3 }), actual: None });
+    }
+
+    #[test]
+    fn limits_runaway_replays() {
+        let session = "halt 1\n".parse().unwrap();
+        let divergence = replay(&[1105, 1, 0], &session, &Limits { max_instructions: Some(100), ..Limits::default() });
+
+        assert_eq!(
+            divergence.unwrap().actual,
+            Some(Event::Error { step: 100, message: String::from("instruction limit exceeded") })
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!("in 0".parse::<Session>().unwrap_err().line, 1);
+        assert_eq!("halt 3\nout x 1".parse::<Session>().unwrap_err().line, 2);
+        assert!("halt 3 4".parse::<Session>().is_err());
+        assert!("jump 1 2".parse::<Session>().is_err());
+    }
+}
@@ -0,0 +1,616 @@
+//! Transpiling Intcode programs to C.
+//!
+//! Every instruction that is reachable from address 0 through fall-through and immediate jump
+//! targets becomes a labelled block of C code inside a `switch` on the program counter. Jumps to
+//! those instructions become `goto`s, and every other jump goes through the `switch`. Addresses
+//! without a translation, such as targets of jumps that are read from memory, are run by an
+//! interpreter that handles one instruction before going back to the `switch`.
+//!
+//! If the program might modify its code, every translated instruction first checks that its
+//! cells still hold the values it was translated from, and falls back to the interpreter if they
+//! don't.
+//!
+//! The generated program reads input as whitespace-separated integers from stdin and prints each
+//! output on its own line. Passing `-m` prints the memory as a final line once the program halts.
+//! Running out of input exits with status 2, and an unknown opcode exits with status 1. Cells are
+//! 32-bit like the VM's, and a result, address or relative base that doesn't fit in one exits with
+//! status 3. Custom opcodes are not supported.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::isa::{self, Compute, Condition, Mode, Operation};
+use crate::Param;
+
+/// An instruction that can be translated.
+struct Instruction {
+    opcode: i32,
+    modes: Vec<Mode>,
+    params: Vec<i32>,
+}
+
+impl Instruction {
+    fn decode(program: &[i32], addr: usize) -> Option<Self> {
+        let instr = *program.get(addr)?;
+        let isa::Decoded { opcode, modes } = isa::decode(instr);
+
+        let spec = isa::lookup(opcode).filter(|_| instr >= 0)?;
+
+        let params = program.get(addr + 1..addr + spec.size())?.to_vec();
+        let modes = modes.of(spec.params).ok()?;
+
+        Some(Instruction { opcode, modes, params })
+    }
+
+    fn spec(&self) -> &'static isa::Instruction {
+        isa::lookup(self.opcode).unwrap()
+    }
+
+    fn len(&self) -> usize {
+        1 + self.params.len()
+    }
+
+    /// The target of a jump, if it's immediate.
+    fn target(&self) -> Option<usize> {
+        if !self.spec().jumps() {
+            return None;
+        }
+
+        let i = reads(self.spec())[1];
+
+        match self.modes[i] {
+            Mode::Immediate if self.params[i] >= 0 => Some(self.params[i] as usize),
+            _ => None,
+        }
+    }
+
+    /// Whether the instruction is a jump whose target is read from memory.
+    fn jumps_dynamically(&self) -> bool {
+        self.spec().jumps() && self.target().is_none()
+    }
+
+    /// The addresses that may run after this instruction, which is at `addr`, leaving out targets
+    /// of jumps that are read from memory.
+    fn successors(&self, addr: usize) -> Vec<usize> {
+        let next = addr + self.len();
+
+        if self.spec().halts() {
+            vec![]
+        } else if self.spec().jumps() {
+            std::iter::once(next).chain(self.target()).collect()
+        } else {
+            vec![next]
+        }
+    }
+
+    /// The C expression for the value of a read parameter of this instruction, which is at `addr`.
+    fn read(&self, i: usize, addr: usize) -> String {
+        let param = self.params[i];
+
+        match self.modes[i] {
+            Mode::Position => format!("rd({})", param),
+            Mode::Immediate => format!("{}LL", param),
+            Mode::Relative => format!("rd(cell(rb + {}LL, {}))", param, addr),
+        }
+    }
+
+    /// The C expression for the address of a write parameter of this instruction, which is at
+    /// `addr`.
+    fn dst(&self, i: usize, addr: usize) -> String {
+        match self.modes[i] {
+            Mode::Relative => format!("cell(rb + {}LL, {})", self.params[i], addr),
+            _ => format!("{}", self.params[i]),
+        }
+    }
+
+    /// Whether the instruction writes to a cell that can only be known while running.
+    fn writes_dynamically(&self) -> bool {
+        self.spec().write_param().is_some_and(|i| self.modes[i] == Mode::Relative)
+    }
+
+    /// The cell that the instruction writes to, if it's known statically.
+    fn static_dst(&self) -> Option<usize> {
+        self.spec().write_param().filter(|&i| self.modes[i] == Mode::Position).map(|i| self.params[i] as usize)
+    }
+}
+
+/// The indices of the parameters of an instruction that are read, padded so that an instruction
+/// can always be asked for its first two.
+fn reads(spec: &isa::Instruction) -> Vec<usize> {
+    let mut reads = (0..spec.params.len()).filter(|&i| spec.params[i] == Param::Read).collect::<Vec<_>>();
+    reads.resize(reads.len().max(2), 0);
+    reads
+}
+
+/// The C expression for a computation of the read parameters `a` and `b`. They're 64-bit, so the
+/// result is exact and is only checked when it's stored.
+fn compute(compute: Compute) -> String {
+    match compute {
+        Compute::Add => String::from("a + b"),
+        Compute::Multiply => String::from("a * b"),
+        Compute::LessThan => String::from("a < b"),
+        Compute::Equals => String::from("a == b"),
+        Compute::Affine([x, y, c]) => format!("{}LL * a + {}LL * b + {}LL", x, y, c),
+    }
+}
+
+/// The C expression for a jump condition on the read parameter `a`.
+fn condition(condition: Condition) -> &'static str {
+    match condition {
+        Condition::NonZero => "a != 0",
+        Condition::Zero => "a == 0",
+        Condition::Negative => "a < 0",
+    }
+}
+
+/// A C function for the computation or jump condition of each instruction, named after its
+/// mnemonic.
+fn operations() -> String {
+    let mut c = String::new();
+
+    for spec in isa::all() {
+        match spec.operation {
+            Operation::Compute(op) => writeln!(
+                c,
+                "static inline long long op_{}(long long a, long long b) {{ return {}; }}",
+                spec.mnemonic,
+                compute(op)
+            ),
+            Operation::JumpIf(op) => writeln!(
+                c,
+                "static inline int op_{}(long long a) {{ return {}; }}",
+                spec.mnemonic,
+                condition(op)
+            ),
+            _ => continue,
+        }
+        .unwrap();
+    }
+
+    c
+}
+
+/// The cases of the interpreter, which executes the instruction at `pc`.
+fn interpreter() -> String {
+    let mut c = String::from("interp:\n    switch (rd(pc) % 100) {\n");
+
+    for spec in isa::all() {
+        let reads = reads(spec);
+        let size = spec.size();
+        let dst = spec.write_param().unwrap_or(0);
+
+        let body = match spec.operation {
+            Operation::Compute(_) => format!(
+                "wr(param_addr(pc, {}), cell(op_{}(param(pc, {}), param(pc, {})), pc)); pc += {}; break;",
+                dst, spec.mnemonic, reads[0], reads[1], size
+            ),
+            Operation::Input => format!("wr(param_addr(pc, {}), input(pc)); pc += {}; break;", dst, size),
+            Operation::Output => format!("output(param(pc, {})); pc += {}; break;", reads[0], size),
+            Operation::JumpIf(_) => format!(
+                "pc = op_{}(param(pc, {})) ? param(pc, {}) : pc + {}; break;",
+                spec.mnemonic, reads[0], reads[1], size
+            ),
+            Operation::AdjustRelativeBase => {
+                format!("rb = cell(rb + param(pc, {}), pc); pc += {}; break;", reads[0], size)
+            }
+            Operation::Halt => String::from("goto halt;"),
+        };
+
+        writeln!(c, "    case {}: {}", spec.opcode, body).unwrap();
+    }
+
+    c.push_str(INTERPRETER_END);
+    c
+}
+
+const PRELUDE: &str = r#"#include <inttypes.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+static int32_t *mem;
+static size_t mem_len;
+static int32_t rb;
+
+static void fail(const char *message, long long addr) {
+    fprintf(stderr, "%s at address %lld\n", message, addr);
+    exit(1);
+}
+
+/* Checks that a value computed by the instruction at `pc` fits in a cell. */
+static int32_t cell(long long value, long long pc) {
+    if (value < INT32_MIN || value > INT32_MAX) {
+        fprintf(stderr, "the instruction at address %lld overflowed\n", pc);
+        exit(3);
+    }
+
+    return (int32_t)value;
+}
+
+static int32_t rd(long long addr) {
+    if (addr < 0) fail("read from a negative address", addr);
+    return (size_t)addr < mem_len ? mem[addr] : 0;
+}
+
+static void wr(long long addr, int32_t value) {
+    if (addr < 0) fail("write to a negative address", addr);
+
+    if ((size_t)addr >= mem_len) {
+        size_t len = (size_t)addr + 1;
+        mem = realloc(mem, len * sizeof *mem);
+        memset(mem + mem_len, 0, (len - mem_len) * sizeof *mem);
+        mem_len = len;
+    }
+
+    mem[addr] = value;
+}
+
+static int32_t input(long long pc) {
+    int32_t value;
+
+    if (scanf("%" SCNd32, &value) != 1) {
+        fprintf(stderr, "no input left for the instruction at address %lld\n", pc);
+        exit(2);
+    }
+
+    return value;
+}
+
+static void output(int32_t value) {
+    printf("%" PRId32 "\n", value);
+}
+
+/* The mode of parameter `i` of the instruction at `pc`, for the interpreter. */
+static int mode(long long pc, int i) {
+    long long instr = rd(pc);
+    for (int j = 0; j < i + 2; j++) instr /= 10;
+    return instr % 10;
+}
+
+static long long param_addr(long long pc, int i) {
+    switch (mode(pc, i)) {
+    case 0: return rd(pc + 1 + i);
+    case 2: return cell((long long)rb + rd(pc + 1 + i), pc);
+    default: fail("invalid mode", pc); return 0;
+    }
+}
+
+static long long param(long long pc, int i) {
+    return mode(pc, i) == 1 ? rd(pc + 1 + i) : rd(param_addr(pc, i));
+}
+"#;
+
+/// The end of the interpreter, after the cases for the standard instructions.
+const INTERPRETER_END: &str = r#"    default:
+        fprintf(stderr, "unknown opcode %" PRId32 " in instruction %" PRId32 " at address %lld\n",
+                rd(pc) % 100, rd(pc), pc);
+        exit(1);
+    }
+    goto dispatch;
+
+halt:
+    if (dump) {
+        for (size_t i = 0; i < mem_len; i++) printf(i ? ",%" PRId32 : "%" PRId32, mem[i]);
+        printf("\n");
+    }
+
+    return 0;
+}
+"#;
+
+/// Translates a program to a standalone C program.
+pub fn to_c(program: &[i32]) -> String {
+    let instructions = decode_reachable(program);
+
+    // The interpreter only runs if some jump or instruction couldn't be translated. Guards are
+    // needed if it might run, because it could write anywhere, or if a translated instruction
+    // might write to code.
+    let code = instructions
+        .iter()
+        .flat_map(|(&addr, instruction)| addr..addr + instruction.len())
+        .collect::<BTreeSet<_>>();
+
+    let untranslated = instructions.iter().any(|(&addr, instruction)| {
+        instruction.jumps_dynamically()
+            || instruction.successors(addr).iter().any(|next| !instructions.contains_key(next))
+    });
+
+    let guarded = untranslated
+        || instructions.values().any(|instruction| {
+            instruction.writes_dynamically() || instruction.static_dst().is_some_and(|dst| code.contains(&dst))
+        });
+
+    let mut c = String::from(PRELUDE);
+    c.push('\n');
+    c.push_str(&operations());
+
+    c.push_str("\nstatic const int32_t program[] = {");
+    for (i, cell) in program.iter().enumerate() {
+        if i % 16 == 0 {
+            c.push_str("\n   ");
+        }
+        write!(c, " {},", cell).unwrap();
+    }
+    c.push_str("\n    0\n};\n\n");
+
+    c.push_str("int main(int argc, char **argv) {\n");
+    c.push_str("    int dump = argc > 1 && strcmp(argv[1], \"-m\") == 0;\n");
+    writeln!(c, "    mem_len = {};", program.len()).unwrap();
+    c.push_str("    mem = malloc((mem_len + 1) * sizeof *mem);\n");
+    c.push_str("    memcpy(mem, program, mem_len * sizeof *mem);\n");
+    c.push_str("    long long pc = 0;\n\n");
+    c.push_str("dispatch:\n    switch (pc) {\n");
+
+    for (&addr, instruction) in &instructions {
+        translate(&mut c, program, &instructions, addr, instruction, guarded);
+    }
+
+    c.push_str("    default: break;\n    }\n\n");
+    c.push_str(&interpreter());
+
+    c
+}
+
+/// Decodes every instruction that is reachable from address 0 without reading jump targets from
+/// memory.
+fn decode_reachable(program: &[i32]) -> BTreeMap<usize, Instruction> {
+    let mut instructions = BTreeMap::new();
+    let mut queue = vec![0];
+
+    while let Some(addr) = queue.pop() {
+        if instructions.contains_key(&addr) {
+            continue;
+        }
+
+        if let Some(instruction) = Instruction::decode(program, addr) {
+            queue.extend(instruction.successors(addr));
+            instructions.insert(addr, instruction);
+        }
+    }
+
+    instructions
+}
+
+fn translate(
+    c: &mut String,
+    program: &[i32],
+    instructions: &BTreeMap<usize, Instruction>,
+    addr: usize,
+    instruction: &Instruction,
+    guarded: bool,
+) {
+    let jump = |target: &str, known: Option<usize>| match known {
+        Some(target) if instructions.contains_key(&target) => format!("goto L{};", target),
+        _ => format!("{{ pc = {}; goto dispatch; }}", target),
+    };
+
+    writeln!(c, "    case {addr}: L{addr}: {{", addr = addr).unwrap();
+
+    if guarded {
+        let cells = (addr..addr + instruction.len())
+            .map(|cell| format!("rd({}) != {}", cell, program[cell]))
+            .collect::<Vec<_>>();
+
+        writeln!(c, "        if ({}) {{ pc = {}; goto interp; }}", cells.join(" || "), addr).unwrap();
+    }
+
+    let next = addr + instruction.len();
+    let spec = instruction.spec();
+    let reads = reads(spec);
+    let dst = || instruction.dst(spec.write_param().unwrap(), addr);
+    let read = |i| instruction.read(i, addr);
+
+    let body = match spec.operation {
+        Operation::Compute(_) => format!(
+            "wr({}, cell(op_{}({}, {}), {}));",
+            dst(),
+            spec.mnemonic,
+            read(reads[0]),
+            read(reads[1]),
+            addr
+        ),
+        Operation::Input => format!("wr({}, input({}));", dst(), addr),
+        Operation::Output => format!("output({});", read(reads[0])),
+        Operation::JumpIf(_) => format!(
+            "if (op_{}({})) {}",
+            spec.mnemonic,
+            read(reads[0]),
+            jump(&read(reads[1]), instruction.target()),
+        ),
+        Operation::AdjustRelativeBase => format!("rb = cell((long long)rb + {}, {});", read(reads[0]), addr),
+        Operation::Halt => String::from("goto halt;"),
+    };
+
+    writeln!(c, "        {}", body).unwrap();
+
+    if !spec.halts() {
+        writeln!(c, "        {}", jump(&next.to_string(), Some(next))).unwrap();
+    }
+
+    c.push_str("    }\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io::{self, Write};
+    use std::path::PathBuf;
+    use std::process::{Command, Output, Stdio};
+
+    use crate::{isa, program, ExecutionError, Intcode, Limits};
+
+    use super::to_c;
+
+    /// A C program built from an Intcode program.
+    struct Native {
+        dir: PathBuf,
+    }
+
+    impl Native {
+        /// Compiles the program with `$CC`, or `cc` if it isn't set. Returns `None` if the compiler
+        /// isn't installed, so that the test can be skipped.
+        fn build(name: &str, program: &[i32]) -> Option<Self> {
+            let dir = env::temp_dir().join(format!("intcode-transpile-{}-{}", name, std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let native = Native { dir };
+
+            let source = native.dir.join("program.c");
+            fs::write(&source, to_c(program)).unwrap();
+
+            let compiler = env::var("CC").unwrap_or_else(|_| String::from("cc"));
+            let status = Command::new(&compiler)
+                .args(["-O1", "-Wall", "-Werror", "-Wno-unused-label", "-o"])
+                .arg(native.dir.join("program"))
+                .arg(&source)
+                .status();
+
+            match status {
+                Ok(status) => assert!(status.success(), "failed to compile {}", source.display()),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    eprintln!("skipping: the C compiler `{}` isn't installed", compiler);
+                    return None;
+                }
+                Err(e) => panic!("failed to run the C compiler `{}`: {}", compiler, e),
+            }
+
+            Some(native)
+        }
+
+        fn run(&self, args: &[&str], input: &[i32]) -> Output {
+            let mut child = Command::new(self.dir.join("program"))
+                .args(args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .unwrap();
+
+            let input = input.iter().map(|value| format!("{}\n", value)).collect::<String>();
+            child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+
+            child.wait_with_output().unwrap()
+        }
+
+        fn outputs(&self, input: &[i32]) -> Vec<i32> {
+            let output = self.run(&[], input);
+            assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+            String::from_utf8(output.stdout).unwrap().lines().map(|line| line.parse().unwrap()).collect()
+        }
+
+        fn memory(&self) -> Vec<i32> {
+            let output = self.run(&["-m"], &[]);
+            assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+            let stdout = String::from_utf8(output.stdout).unwrap();
+            stdout.lines().last().unwrap().split(',').map(|cell| cell.parse().unwrap()).collect()
+        }
+    }
+
+    impl Drop for Native {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn assert_same_output(name: &str, program: &str, inputs: &[Vec<i32>]) {
+        let program = program::parse(program).unwrap();
+        let Some(native) = Native::build(name, &program) else { return };
+
+        for input in inputs {
+            let expected = Intcode::new(program.clone(), input.clone()).execute();
+            assert_eq!(native.outputs(input), expected, "input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn day2_puzzle_input() {
+        let mut program = program::parse(include_str!("../../inputs/day2.txt")).unwrap();
+        program[1] = 12;
+        program[2] = 2;
+
+        let mut computer = Intcode::new(program.clone(), vec![]);
+        computer.execute();
+
+        let Some(native) = Native::build("day2", &program) else { return };
+        assert_eq!(native.memory(), computer.mem().to_vec());
+    }
+
+    #[test]
+    fn day2_test_cases() {
+        for (i, program) in ["1,0,0,0,99", "2,3,0,3,99", "2,4,4,5,99,0", "1,1,1,4,99,5,6,0,99"].iter().enumerate() {
+            let program = program::parse(program).unwrap();
+
+            let mut computer = Intcode::new(program.clone(), vec![]);
+            computer.execute();
+
+            let Some(native) = Native::build(&format!("day2-{}", i), &program) else { return };
+            assert_eq!(native.memory(), computer.mem().to_vec());
+        }
+    }
+
+    #[test]
+    fn day5_puzzle_input() {
+        // The program modifies its own code, so parts of it run in the interpreter.
+        assert_same_output("day5", include_str!("../../inputs/day5.txt"), &[vec![1], vec![5]]);
+    }
+
+    #[test]
+    fn day5_examples() {
+        let compare = [vec![7], vec![8], vec![9]];
+
+        assert_same_output("equal", "3,9,8,9,10,9,4,9,99,-1,8", &compare);
+        assert_same_output("less-than", "3,3,1107,-1,8,3,4,3,99", &compare);
+        assert_same_output("jump", "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9", &[vec![0], vec![5]]);
+        assert_same_output("larger", program::COMPARE_TO_8, &compare);
+    }
+
+    #[test]
+    fn uses_the_instruction_table() {
+        let _examples = isa::registry::register_examples();
+        assert_same_output("table", isa::registry::EXAMPLE, &[vec![3], vec![8], vec![-5]]);
+
+        // Writes a `sub` over a halt, so the rest of the program runs in the interpreter.
+        let program = "3,101,1101,0,1042,6,99,101,8,100,1043,100,16,104,0,99,104,1,99";
+        assert_same_output("table-interpreted", program, &[vec![3], vec![9]]);
+    }
+
+    #[test]
+    fn relative_base() {
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        assert_same_output("quine", quine, &[vec![]]);
+    }
+
+    #[test]
+    fn errors() {
+        let Some(native) = Native::build("errors", &program::parse("3,0,3,0,42").unwrap()) else { return };
+
+        let output = native.run(&[], &[1]);
+        assert_eq!(output.status.code(), Some(2));
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "no input left for the instruction at address 2\n");
+
+        let output = native.run(&[], &[1, 2]);
+        assert_eq!(output.status.code(), Some(1));
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "unknown opcode 42 in instruction 42 at address 4\n");
+    }
+
+    #[test]
+    fn overflow() {
+        // A product, a relative base, and a product that only the interpreter runs, because the
+        // jump to it is read from memory.
+        let programs = ["1102,65536,65536,0,99", "109,2147483647,109,1,99", "105,1,6,99,0,0,7,1102,65536,65536,0,99"];
+
+        for (i, source) in programs.iter().enumerate() {
+            let program = program::parse(source).unwrap();
+            let error = Intcode::new(program.clone(), vec![]).execute_with_limits(&Limits::default()).unwrap_err();
+            assert!(matches!(error, ExecutionError::Overflow { .. }), "{}", source);
+
+            let Some(native) = Native::build(&format!("overflow-{}", i), &program) else { return };
+            let output = native.run(&[], &[]);
+            assert_eq!(output.status.code(), Some(3), "{}", source);
+            assert_eq!(String::from_utf8_lossy(&output.stderr), format!("{}\n", error), "{}", source);
+        }
+    }
+}
@@ -0,0 +1,374 @@
+//! Static verification of programs before they run.
+//!
+//! The verifier decodes the program from address 0 along every path that can be followed without
+//! running it, and reports the problems that would otherwise stop the machine partway through a
+//! run. Jumps to targets read from memory can't be followed, and cells that the program writes
+//! are treated as unknown, so a program that passes may still fail at runtime.
+//!
+//! The relative base isn't tracked, so an instruction that writes in relative mode may write to
+//! any cell, including code. Once one is reached, every cell is treated as written, and nothing
+//! can be verified.
+
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::isa::{self, InvalidMode, Mode, Operation, Param};
+use crate::Extensions;
+
+/// A problem with an instruction that the program can reach.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Problem {
+    UnknownOpcode { addr: usize, instr: i32 },
+
+    /// A parameter has a mode that doesn't exist, or is written to in immediate mode. `param` is
+    /// the index of the parameter, starting from 0.
+    InvalidMode { addr: usize, instr: i32, param: usize, mode: i32 },
+
+    /// The instruction has more parameters than there are cells left in the program.
+    Truncated { addr: usize, instr: i32, len: usize },
+
+    /// A parameter in position mode has a negative address.
+    NegativeAddress { addr: usize, instr: i32, param: usize },
+
+    /// A jump with an immediate target goes outside of the program.
+    JumpOutOfRange { addr: usize, target: i32 },
+}
+
+impl Problem {
+    /// The address of the instruction with the problem.
+    pub fn addr(&self) -> usize {
+        match *self {
+            Problem::UnknownOpcode { addr, .. }
+            | Problem::InvalidMode { addr, .. }
+            | Problem::Truncated { addr, .. }
+            | Problem::NegativeAddress { addr, .. }
+            | Problem::JumpOutOfRange { addr, .. } => addr,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Problem::UnknownOpcode { addr, instr } => {
+                let opcode = isa::decode(instr).opcode;
+                write!(f, "address {}: unknown opcode {} in instruction {}", addr, opcode, instr)
+            }
+            Problem::InvalidMode { addr, instr, param, mode: 1 } => write!(
+                f,
+                "address {}: parameter {} of instruction {} is written to, so it can't be in immediate mode",
+                addr, param, instr
+            ),
+            Problem::InvalidMode { addr, instr, param, mode } => {
+                write!(f, "address {}: invalid mode {} for parameter {} of instruction {}", addr, mode, param, instr)
+            }
+            Problem::Truncated { addr, instr, len } => write!(
+                f,
+                "address {}: instruction {} runs past the end of the program, which has {} cells",
+                addr, instr, len
+            ),
+            Problem::NegativeAddress { addr, instr, param } => {
+                write!(f, "address {}: parameter {} of instruction {} has a negative address", addr, param, instr)
+            }
+            Problem::JumpOutOfRange { addr, target } => {
+                write!(f, "address {}: jump to {}, which is outside of the program", addr, target)
+            }
+        }
+    }
+}
+
+/// The result of verifying a program.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Report {
+    /// The addresses of the instructions that were reached, including ones with problems.
+    pub instructions: BTreeSet<usize>,
+
+    /// The addresses of reached instructions whose opcode is written by the program, so they
+    /// couldn't be decoded. Paths through them aren't followed.
+    pub unverified: BTreeSet<usize>,
+
+    /// The addresses of reached instructions that write to a cell that isn't known statically,
+    /// because it's in relative mode or its address is written by the program. If there are
+    /// any, every cell may be written, so the rest of the report only covers address 0.
+    pub unknown_writes: BTreeSet<usize>,
+
+    /// The problems that were found, ordered by address.
+    pub problems: Vec<Problem>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for problem in &self.problems {
+            writeln!(f, "{}", problem)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The cells that a program may write.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Writes {
+    cells: BTreeSet<usize>,
+
+    /// The instructions that may write to any cell.
+    anywhere: BTreeSet<usize>,
+}
+
+impl Writes {
+    fn contains(&self, addr: usize) -> bool {
+        !self.anywhere.is_empty() || self.cells.contains(&addr)
+    }
+}
+
+/// Verifies a program, following both sides of conditional jumps unless the condition is
+/// immediate.
+///
+/// Cells that reached instructions write to may hold anything when they're executed, so they
+/// aren't checked: an instruction with a written opcode is unverified, and a written parameter
+/// may have any value.
+pub fn verify(program: &[i32]) -> Report {
+    verify_with_extensions(program, &Extensions::new())
+}
+
+/// Verifies a program that may use the custom instructions registered in `extensions`.
+///
+/// A custom instruction may jump or halt, but only the instruction after it is followed.
+pub fn verify_with_extensions(program: &[i32], extensions: &Extensions) -> Report {
+    let mut written = Writes::default();
+
+    // Skipping written cells can only reach fewer instructions, which write fewer cells, so
+    // adding the cells written by each pass to the next one converges.
+    loop {
+        let mut writes = written.clone();
+        let mut report = walk(program, extensions, &written, &mut writes);
+
+        if writes == written {
+            report.unknown_writes = writes.anywhere;
+            return report;
+        }
+
+        written = writes;
+    }
+}
+
+/// Verifies the instructions reachable from address 0, assuming that the cells in `written` are
+/// unknown. The cells written by the reached instructions are added to `writes`.
+fn walk(program: &[i32], extensions: &Extensions, written: &Writes, writes: &mut Writes) -> Report {
+    let mut report = Report::default();
+    let mut queue = vec![0];
+
+    while let Some(addr) = queue.pop() {
+        if addr >= program.len() || !report.instructions.insert(addr) {
+            continue;
+        }
+
+        if written.contains(addr) {
+            report.unverified.insert(addr);
+            continue;
+        }
+
+        match decode(program, extensions, addr, written, writes) {
+            Ok(next) => queue.extend(next),
+            Err(problem) => report.problems.push(problem),
+        }
+    }
+
+    report.problems.sort_by_key(Problem::addr);
+    report
+}
+
+/// Checks the instruction at `addr`, returning the addresses that execution can continue at.
+fn decode(
+    program: &[i32],
+    extensions: &Extensions,
+    addr: usize,
+    written: &Writes,
+    writes: &mut Writes,
+) -> Result<Vec<usize>, Problem> {
+    let instr = program[addr];
+
+    let isa::Decoded { opcode, modes } = isa::decode(instr);
+
+    // Custom instructions have no operation that the verifier knows about.
+    let (kinds, operation) = match (isa::lookup(opcode), extensions.params(opcode)) {
+        _ if instr < 0 => return Err(Problem::UnknownOpcode { addr, instr }),
+        (Some(instruction), _) => (instruction.params, Some(instruction.operation)),
+        (None, Some(params)) => (params, None),
+        (None, None) => return Err(Problem::UnknownOpcode { addr, instr }),
+    };
+
+    let next = addr + 1 + kinds.len();
+    if next > program.len() {
+        return Err(Problem::Truncated { addr, instr, len: program.len() });
+    }
+
+    // The values of the parameters, if they're known.
+    let params: Vec<_> =
+        (addr + 1..next).map(|addr| if written.contains(addr) { None } else { Some(program[addr]) }).collect();
+
+    let modes = modes
+        .of(kinds)
+        .map_err(|InvalidMode { param, digit }| Problem::InvalidMode { addr, instr, param, mode: digit })?;
+
+    for (param, (&kind, &value)) in kinds.iter().zip(&params).enumerate() {
+        match (modes[param], kind, value) {
+            (Mode::Position, _, Some(value)) if value < 0 => {
+                return Err(Problem::NegativeAddress { addr, instr, param })
+            }
+            (Mode::Position, Param::Write, Some(value)) => {
+                writes.cells.insert(value as usize);
+            }
+            (_, Param::Write, _) => {
+                writes.anywhere.insert(addr);
+            }
+            _ => (),
+        }
+    }
+
+    let immediate = |param: usize| match params[param] {
+        Some(value) if modes[param] == Mode::Immediate => Some(value),
+        _ => None,
+    };
+
+    let condition = match operation {
+        Some(Operation::Halt) => return Ok(vec![]),
+        Some(Operation::JumpIf(condition)) => condition,
+        _ => return Ok(vec![next]),
+    };
+
+    let taken = match immediate(0) {
+        Some(value) if !condition.holds(value) => return Ok(vec![next]),
+        Some(_) => vec![],
+        None => vec![next],
+    };
+
+    let target = match immediate(1) {
+        Some(target) => target,
+        None => return Ok(taken),
+    };
+
+    match usize::try_from(target) {
+        Ok(target) if target < program.len() => Ok(taken.into_iter().chain(Some(target)).collect()),
+        _ => Err(Problem::JumpOutOfRange { addr, target }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{program, Effect, Extensions, Param};
+
+    use super::{verify, verify_with_extensions, Problem};
+
+    #[test]
+    fn day5_is_ok() {
+        let program = program::parse(include_str!("../../inputs/day5.txt")).unwrap();
+        let report = verify(&program);
+        assert!(report.is_ok(), "{}", report);
+
+        // The program adds its input to the opcode of its second instruction.
+        assert_eq!(report.unverified.into_iter().collect::<Vec<_>>(), vec![6]);
+    }
+
+    #[test]
+    fn skips_written_cells() {
+        // Writes the target of the jump before running it, so the jump can't be followed.
+        let report = verify(&[1101, 0, 7, 6, 1105, 1, -1, 42]);
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.instructions.into_iter().collect::<Vec<_>>(), vec![0, 4]);
+
+        // Turns the cell at 4 into a halt.
+        let report = verify(&[1101, 0, 99, 4, 42]);
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.unverified.into_iter().collect::<Vec<_>>(), vec![4]);
+    }
+
+    #[test]
+    fn relative_writes_may_write_anywhere() {
+        // Sets the relative base to 8 and turns the cell at 6 into a halt, which the verifier
+        // can't tell, so it can't check anything after the first instruction.
+        let report = verify(&[109, 8, 21101, 0, 99, -2, 42]);
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.unknown_writes.into_iter().collect::<Vec<_>>(), vec![2]);
+        assert_eq!(report.unverified.into_iter().collect::<Vec<_>>(), vec![0]);
+
+        // An address that the program writes is unknown too.
+        let report = verify(&[1101, 0, 9, 7, 1101, 0, 99, 9, 99, 0]);
+        assert_eq!(report.unknown_writes.into_iter().collect::<Vec<_>>(), vec![4]);
+
+        let report = verify(&[1101, 0, 9, 7, 99, 0, 0, 0]);
+        assert!(report.unknown_writes.is_empty());
+    }
+
+    #[test]
+    fn custom_instructions() {
+        let mut extensions = Extensions::new();
+        extensions.register(42, &[Param::Read, Param::Write], |args, results| {
+            results[0] = args[0];
+            Effect::Continue
+        });
+
+        // Copies 99 over the unknown opcode at 3.
+        let program = [142, 99, 3, 43];
+        let report = verify_with_extensions(&program, &extensions);
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.unverified.into_iter().collect::<Vec<_>>(), vec![3]);
+        assert_eq!(report.instructions.into_iter().collect::<Vec<_>>(), vec![0, 3]);
+        assert_eq!(verify(&program).problems, vec![Problem::UnknownOpcode { addr: 0, instr: 142 }]);
+
+        let report = verify_with_extensions(&[11042, 1, 1, 99], &extensions);
+        assert_eq!(report.problems, vec![Problem::InvalidMode { addr: 0, instr: 11042, param: 1, mode: 1 }]);
+
+        let report = verify_with_extensions(&[42, 0], &extensions);
+        assert_eq!(report.problems, vec![Problem::Truncated { addr: 0, instr: 42, len: 2 }]);
+    }
+
+    #[test]
+    fn follows_reachable_paths() {
+        // The cells after the unconditional jump and the halt are data.
+        let report = verify(&[1105, 1, 7, 42, 42, 42, 42, 1006, 11, 10, 99, 0]);
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.instructions.into_iter().collect::<Vec<_>>(), vec![0, 7, 10]);
+
+        // The jump is never taken, so its target isn't checked.
+        let report = verify(&[1106, 1, -1, 99]);
+        assert!(report.is_ok(), "{}", report);
+    }
+
+    #[test]
+    fn finds_problems() {
+        let report = verify(&[1005, 13, 7, 11101, 1, 1, 0, 1106, 0, 50, 0, 0, 0, 0]);
+        assert_eq!(
+            report.problems,
+            vec![
+                Problem::InvalidMode { addr: 3, instr: 11101, param: 2, mode: 1 },
+                Problem::JumpOutOfRange { addr: 7, target: 50 },
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "address 3: parameter 2 of instruction 11101 is written to, so it can't be in immediate mode\n\
+             address 7: jump to 50, which is outside of the program\n"
+        );
+    }
+
+    #[test]
+    fn finds_runtime_errors() {
+        assert_eq!(verify(&[301, 0, 0, 0, 99]).problems, vec![Problem::InvalidMode { addr: 0, instr: 301, param: 0, mode: 3 }]);
+        assert_eq!(verify(&[1, -1, 0, 0, 99]).problems, vec![Problem::NegativeAddress { addr: 0, instr: 1, param: 0 }]);
+        assert_eq!(verify(&[1105, 1, -4]).problems, vec![Problem::JumpOutOfRange { addr: 0, target: -4 }]);
+        assert_eq!(verify(&[-1]).problems, vec![Problem::UnknownOpcode { addr: 0, instr: -1 }]);
+        assert_eq!(verify(&[1105, 1, 3, 42]).to_string(), "address 3: unknown opcode 42 in instruction 42\n");
+
+        let report = verify(&[104, 1, 1, 0, 0]);
+        assert_eq!(report.problems, vec![Problem::Truncated { addr: 2, instr: 1, len: 5 }]);
+        assert_eq!(report.to_string(), "address 2: instruction 1 runs past the end of the program, which has 5 cells\n");
+    }
+}
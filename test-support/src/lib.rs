@@ -0,0 +1,33 @@
+//! Helpers for tests that load libraries built by the crates in this workspace.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Builds `package` into the target directory of the running test, with the same profile, and
+/// returns the path of its library named `file`, such as `libintcode_ffi.a`.
+///
+/// `cargo test` only builds the crate types that Rust links against, so a static library or a
+/// Python extension module may not exist yet, or may be left over from an older build.
+///
+/// # Panics
+///
+/// Panics if the package fails to build.
+pub fn build_library(package: &str, file: &str) -> PathBuf {
+    // Test executables are in `<target dir>/<profile>/deps`.
+    let exe = env::current_exe().unwrap();
+    let profile_dir = exe.parent().and_then(Path::parent).unwrap();
+    let target_dir = profile_dir.parent().unwrap();
+
+    let mut cargo = Command::new(env::var("CARGO").unwrap_or_else(|_| String::from("cargo")));
+    cargo.args(["build", "--quiet", "--package", package, "--target-dir"]).arg(target_dir);
+
+    if profile_dir.file_name().is_some_and(|name| name == "release") {
+        cargo.arg("--release");
+    }
+
+    let status = cargo.status().expect("could not run cargo");
+    assert!(status.success(), "could not build {}", package);
+
+    profile_dir.join(file)
+}
@@ -0,0 +1,323 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use intcode::{Intcode, Limits, Status};
+
+/// The speeds that the program can run at, in instructions per second.
+pub const SPEEDS: &[u32] = &[1, 2, 5, 10, 20, 50, 100, 1000, 10_000];
+
+/// How many instructions a written cell stays highlighted for.
+pub const RECENT_WRITE_STEPS: u64 = 8;
+
+/// What the machine is doing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum State {
+    Paused,
+    Playing,
+
+    /// The program needs input and the input queue is empty.
+    WaitingForInput,
+    Halted,
+    Error(String),
+}
+
+/// A key press that the visualizer reacts to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Esc,
+    Right,
+}
+
+/// The state of the visualizer.
+pub struct App {
+    program: Vec<i32>,
+    initial_input: Vec<i32>,
+
+    pub computer: Intcode,
+    pub state: State,
+
+    /// An index into [`SPEEDS`].
+    pub speed: usize,
+
+    /// The number of instructions executed.
+    pub steps: u64,
+
+    /// Input that hasn't been read by the program yet.
+    pub input: VecDeque<i32>,
+    pub output: Vec<i32>,
+
+    /// The step at which each cell was last written.
+    pub writes: HashMap<usize, u64>,
+
+    /// The value being typed, when entering input.
+    pub entry: Option<String>,
+
+    /// Instructions that are owed to a playing program, as a fraction of an instruction.
+    budget: f64,
+}
+
+impl App {
+    pub fn new(program: Vec<i32>, input: Vec<i32>) -> Self {
+        App {
+            computer: Intcode::new(program.clone(), vec![]),
+            program,
+            initial_input: input.clone(),
+            state: State::Paused,
+            speed: 3,
+            steps: 0,
+            input: input.into(),
+            output: vec![],
+            writes: HashMap::new(),
+            entry: None,
+            budget: 0.0,
+        }
+    }
+
+    /// Starts the program over, with the input it was started with.
+    pub fn reset(&mut self) {
+        let speed = self.speed;
+        *self = App::new(self.program.clone(), self.initial_input.clone());
+        self.speed = speed;
+    }
+
+    pub fn speed(&self) -> u32 {
+        SPEEDS[self.speed]
+    }
+
+    /// Whether the cell at `addr` was written in the last few instructions.
+    pub fn recently_written(&self, addr: usize) -> bool {
+        self.writes.get(&addr).is_some_and(|&step| self.steps - step < RECENT_WRITE_STEPS)
+    }
+
+    /// Executes one instruction, feeding it from the input queue if it needs input.
+    pub fn step(&mut self) {
+        if matches!(self.state, State::Halted | State::Error(_)) {
+            return;
+        }
+
+        loop {
+            match self.computer.step(&Limits::default()) {
+                Ok(None) => break,
+                Ok(Some(Status::NeedsInput)) => match self.input.pop_front() {
+                    Some(value) => self.computer.push_input(value),
+                    None => {
+                        self.state = State::WaitingForInput;
+                        return;
+                    }
+                },
+                Ok(Some(Status::Halted)) => {
+                    self.state = State::Halted;
+                    return;
+                }
+                Err(e) => {
+                    self.state = State::Error(e.to_string());
+                    return;
+                }
+            }
+        }
+
+        self.steps += 1;
+
+        for &addr in self.computer.written() {
+            self.writes.insert(addr, self.steps);
+        }
+
+        self.output.extend(self.computer.take_output());
+
+        if self.state == State::WaitingForInput {
+            self.state = State::Paused;
+        }
+    }
+
+    /// Runs a playing program for the time that has passed since the last tick.
+    pub fn tick(&mut self, elapsed: Duration) {
+        if self.state != State::Playing {
+            self.budget = 0.0;
+            return;
+        }
+
+        self.budget += elapsed.as_secs_f64() * f64::from(self.speed());
+
+        while self.budget >= 1.0 && self.state == State::Playing {
+            self.budget -= 1.0;
+            self.step();
+        }
+    }
+
+    /// Handles a key press. Returns `false` if the visualizer should quit.
+    pub fn handle_key(&mut self, key: Key) -> bool {
+        if let Some(entry) = &mut self.entry {
+            match key {
+                Key::Char(c) if c.is_ascii_digit() || c == '-' && entry.is_empty() => entry.push(c),
+                Key::Backspace => {
+                    entry.pop();
+                }
+                Key::Enter => {
+                    if let Ok(value) = entry.parse() {
+                        self.input.push_back(value);
+
+                        if self.state == State::WaitingForInput {
+                            self.state = State::Paused;
+                        }
+                    }
+                    self.entry = None;
+                }
+                Key::Esc => self.entry = None,
+                _ => (),
+            }
+
+            return true;
+        }
+
+        match key {
+            Key::Char('q') | Key::Esc => return false,
+            Key::Char(' ') => {
+                self.state = match self.state {
+                    State::Paused | State::WaitingForInput => State::Playing,
+                    State::Playing => State::Paused,
+                    ref state => state.clone(),
+                }
+            }
+            Key::Char('s') | Key::Right => {
+                if self.state == State::Playing {
+                    self.state = State::Paused;
+                }
+                self.step();
+            }
+            Key::Char('+') | Key::Char('=') => self.speed = (self.speed + 1).min(SPEEDS.len() - 1),
+            Key::Char('-') => self.speed = self.speed.saturating_sub(1),
+            Key::Char('i') => self.entry = Some(String::new()),
+            Key::Char('r') => self.reset(),
+            _ => (),
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use intcode::ExecutionError;
+
+    use super::{App, Key, State, RECENT_WRITE_STEPS};
+
+    /// Echoes each input until it reads 0.
+    fn echo() -> App {
+        App::new(vec![3, 11, 1006, 11, 10, 4, 11, 1105, 1, 0, 99, 0], vec![7])
+    }
+
+    #[test]
+    fn steps_and_records_writes() {
+        let mut app = echo();
+
+        app.handle_key(Key::Char('s'));
+        assert_eq!(app.steps, 1);
+        assert_eq!(app.computer.pc(), 2);
+        assert!(app.input.is_empty());
+        assert!(app.recently_written(11));
+        assert!(!app.recently_written(0));
+
+        app.handle_key(Key::Char('s'));
+        app.handle_key(Key::Right);
+        assert_eq!(app.output, vec![7]);
+    }
+
+    #[test]
+    fn forgets_old_writes() {
+        // Writes to 7, then jumps to itself forever.
+        let mut app = App::new(vec![1101, 1, 1, 7, 1105, 1, 4, 0], vec![]);
+
+        app.step();
+        assert!(app.recently_written(7));
+
+        for _ in 0..RECENT_WRITE_STEPS - 1 {
+            app.step();
+        }
+        assert!(app.recently_written(7));
+
+        app.step();
+        assert!(!app.recently_written(7));
+    }
+
+    #[test]
+    fn waits_for_input() {
+        let mut app = echo();
+        app.handle_key(Key::Char(' '));
+        assert_eq!(app.state, State::Playing);
+
+        app.tick(Duration::from_secs(10));
+        assert_eq!(app.state, State::WaitingForInput);
+        assert_eq!(app.output, vec![7]);
+
+        for &key in &[Key::Char('i'), Key::Char('-'), Key::Char('4'), Key::Char('2'), Key::Char('-'), Key::Enter] {
+            assert!(app.handle_key(key));
+        }
+        assert_eq!(app.input, vec![-42]);
+        assert_eq!(app.state, State::Paused);
+
+        app.handle_key(Key::Char('i'));
+        app.handle_key(Key::Char('0'));
+        app.handle_key(Key::Enter);
+
+        app.handle_key(Key::Char(' '));
+        app.tick(Duration::from_secs(10));
+        assert_eq!(app.state, State::Halted);
+        assert_eq!(app.output, vec![7, -42]);
+    }
+
+    #[test]
+    fn plays_at_speed() {
+        let mut app = echo();
+        assert_eq!(app.speed(), 10);
+
+        app.handle_key(Key::Char(' '));
+        app.tick(Duration::from_millis(250));
+        assert_eq!(app.steps, 2);
+
+        app.handle_key(Key::Char('+'));
+        app.tick(Duration::from_millis(100));
+        assert_eq!(app.steps, 4);
+
+        app.handle_key(Key::Char(' '));
+        app.tick(Duration::from_secs(1));
+        assert_eq!(app.steps, 4);
+
+        for _ in 0..20 {
+            app.handle_key(Key::Char('-'));
+        }
+        assert_eq!(app.speed(), 1);
+    }
+
+    #[test]
+    fn reports_errors_and_resets() {
+        let mut app = App::new(vec![104, 1, 42], vec![]);
+        app.step();
+        app.step();
+        assert_eq!(app.state, State::Error(String::from("unknown opcode 42 in instruction 42 at address 2")));
+
+        app.handle_key(Key::Char('+'));
+        app.handle_key(Key::Char('r'));
+        assert_eq!(app.state, State::Paused);
+        assert_eq!(app.steps, 0);
+        assert!(app.output.is_empty());
+        assert_eq!(app.speed(), 20);
+
+        assert!(!app.handle_key(Key::Char('q')));
+    }
+
+    #[test]
+    fn reports_invalid_instructions() {
+        // Writes to an immediate parameter, which the machine can't do.
+        let mut app = App::new(vec![11101, 1, 1, 0, 99], vec![]);
+        app.step();
+
+        let error = ExecutionError::InvalidMode { instr: 11101, addr: 0, param: 2 };
+        assert_eq!(app.state, State::Error(error.to_string()));
+        assert_eq!(app.steps, 0);
+    }
+}
@@ -0,0 +1,132 @@
+//! A terminal visualizer that steps through an Intcode program.
+//!
+//! Usage: `visualizer PROGRAM [INPUT...]`
+
+use std::env;
+use std::error::Error;
+use std::io::{self, Write};
+use std::process;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor};
+use crossterm::{cursor, execute, queue, terminal};
+
+use intcode::program;
+
+mod app;
+mod render;
+
+use app::{App, Key};
+use render::{Line, Style};
+
+/// How often the screen is redrawn while a program is playing.
+const FRAME: Duration = Duration::from_millis(30);
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+
+    let path = match args.next() {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: visualizer PROGRAM [INPUT...]");
+            process::exit(2);
+        }
+    };
+
+    let program = program::read_file(&path)?;
+    let input = args.map(|arg| arg.parse()).collect::<Result<Vec<i32>, _>>()?;
+
+    let mut app = App::new(program, input);
+
+    let mut stdout = io::stdout();
+    let screen = Screen::enter(&mut stdout)?;
+    let result = run(&mut app, &mut stdout);
+    drop(screen);
+
+    result
+}
+
+/// The terminal in raw mode on the alternate screen. It's restored when this is dropped, so an
+/// error or a panic doesn't leave the terminal unusable.
+struct Screen;
+
+impl Screen {
+    fn enter(stdout: &mut impl Write) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let screen = Screen;
+
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        // There's no way to report a failure here, and nothing else to try.
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn run(app: &mut App, stdout: &mut impl Write) -> Result<(), Box<dyn Error>> {
+    let mut last_tick = Instant::now();
+
+    loop {
+        draw(app, stdout)?;
+
+        if event::poll(FRAME)? {
+            if let Event::Key(KeyEvent { code, kind: KeyEventKind::Press | KeyEventKind::Repeat, .. }) =
+                event::read()?
+            {
+                let key = match code {
+                    KeyCode::Char(c) => Some(Key::Char(c)),
+                    KeyCode::Enter => Some(Key::Enter),
+                    KeyCode::Backspace => Some(Key::Backspace),
+                    KeyCode::Esc => Some(Key::Esc),
+                    KeyCode::Right => Some(Key::Right),
+                    _ => None,
+                };
+
+                if let Some(key) = key {
+                    if !app.handle_key(key) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        let now = Instant::now();
+        app.tick(now - last_tick);
+        last_tick = now;
+    }
+}
+
+fn draw(app: &App, stdout: &mut impl Write) -> io::Result<()> {
+    let (width, height) = terminal::size()?;
+    let lines = render::render(app, usize::from(width), usize::from(height));
+
+    queue!(stdout, terminal::Clear(terminal::ClearType::All))?;
+
+    for (row, line) in lines.iter().enumerate() {
+        queue!(stdout, cursor::MoveTo(0, row as u16))?;
+        draw_line(line, stdout)?;
+    }
+
+    stdout.flush()
+}
+
+fn draw_line(line: &Line, stdout: &mut impl Write) -> io::Result<()> {
+    for span in line {
+        match span.style {
+            Style::Normal => (),
+            Style::Current => queue!(stdout, SetAttribute(Attribute::Reverse))?,
+            Style::Written => queue!(stdout, SetForegroundColor(Color::Yellow))?,
+            Style::Header => queue!(stdout, SetAttribute(Attribute::Bold))?,
+        }
+
+        queue!(stdout, Print(&span.text), SetAttribute(Attribute::Reset), ResetColor)?;
+    }
+
+    Ok(())
+}
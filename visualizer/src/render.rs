@@ -0,0 +1,356 @@
+//! Lays out the screen as lines of styled text, independently of the terminal.
+
+use std::fmt::Write;
+
+use intcode::isa::{self, Mode};
+
+use crate::app::{App, State};
+
+/// The number of memory cells in each row of the grid.
+const COLUMNS: usize = 8;
+
+/// The width of a cell in the grid, including the space that separates it from the next one.
+const CELL_WIDTH: usize = 8;
+
+/// The width of the disassembly, input and output panes.
+const SIDE_WIDTH: usize = 36;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Style {
+    Normal,
+
+    /// Part of the instruction at the program counter.
+    Current,
+
+    /// A cell that was written recently.
+    Written,
+    Header,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+pub type Line = Vec<Span>;
+
+fn span(text: impl Into<String>, style: Style) -> Span {
+    Span { text: text.into(), style }
+}
+
+/// The width of a line, in characters.
+fn width(line: &[Span]) -> usize {
+    line.iter().map(|span| span.text.chars().count()).sum()
+}
+
+/// Renders the screen as `height` lines that are at most `width` characters wide.
+pub fn render(app: &App, width: usize, height: usize) -> Vec<Line> {
+    let mut lines = vec![status(app), vec![]];
+
+    let body = height.saturating_sub(lines.len() + 1);
+    let grid = grid(app, body);
+
+    let disassembly_height = body / 2;
+    let input_height = (body - disassembly_height) / 3;
+    let output_height = body - disassembly_height - input_height;
+
+    let mut side = vec![vec![span("Disassembly", Style::Header)]];
+    side.extend(disassembly(app, disassembly_height.saturating_sub(1)));
+    side.push(vec![span("Input", Style::Header)]);
+    side.extend(input(app, input_height.saturating_sub(1)));
+    side.push(vec![span("Output", Style::Header)]);
+    side.extend(output(app, output_height.saturating_sub(1)));
+
+    let grid_width = 6 + COLUMNS * CELL_WIDTH + 2;
+
+    for i in 0..body {
+        let mut line = grid.get(i).cloned().unwrap_or_default();
+        if let Some(side) = side.get(i) {
+            let padding = grid_width.saturating_sub(self::width(&line));
+            line.push(span(" ".repeat(padding), Style::Normal));
+            line.extend(side.iter().cloned());
+        }
+        lines.push(line);
+    }
+
+    lines.push(vec![span(
+        "space play/pause  s step  +/- speed  i input  r reset  q quit",
+        Style::Header,
+    )]);
+
+    lines.truncate(height);
+    lines.into_iter().map(|line| truncate(line, width)).collect()
+}
+
+fn truncate(line: Line, width: usize) -> Line {
+    let mut remaining = width;
+    let mut truncated = vec![];
+
+    for span in line {
+        if remaining == 0 {
+            break;
+        }
+
+        let text: String = span.text.chars().take(remaining).collect();
+        remaining -= text.chars().count();
+        truncated.push(Span { text, style: span.style });
+    }
+
+    truncated
+}
+
+fn status(app: &App) -> Line {
+    let state = match &app.state {
+        State::Paused => String::from("paused"),
+        State::Playing => String::from("playing"),
+        State::WaitingForInput => String::from("waiting for input"),
+        State::Halted => String::from("halted"),
+        State::Error(message) => format!("error: {}", message),
+    };
+
+    vec![
+        span(state, Style::Header),
+        span(
+            format!(
+                "  pc {}  rb {}  steps {}  speed {}/s",
+                app.computer.pc(),
+                app.computer.relative_base(),
+                app.steps,
+                app.speed()
+            ),
+            Style::Normal,
+        ),
+    ]
+}
+
+/// The addresses of the cells of the instruction at the program counter.
+fn current_instruction(app: &App) -> std::ops::Range<usize> {
+    let pc = app.computer.pc();
+    let size = isa::lookup(isa::decode(app.computer.mem().get(pc)).opcode).map_or(1, |i| i.size());
+    pc..pc + size
+}
+
+/// The memory grid, scrolled so that the program counter is visible.
+fn grid(app: &App, height: usize) -> Vec<Line> {
+    let mem = app.computer.mem();
+    let current = current_instruction(app);
+
+    let rows = mem.len().div_ceil(COLUMNS);
+    let pc_row = app.computer.pc() / COLUMNS;
+    let first = pc_row.saturating_sub(height / 2).min(rows.saturating_sub(height));
+
+    (first..rows)
+        .take(height)
+        .map(|row| {
+            let mut line = vec![span(format!("{:>5} ", row * COLUMNS), Style::Header)];
+
+            for addr in row * COLUMNS..((row + 1) * COLUMNS).min(mem.len()) {
+                let style = if current.contains(&addr) {
+                    Style::Current
+                } else if app.recently_written(addr) {
+                    Style::Written
+                } else {
+                    Style::Normal
+                };
+
+                line.push(span(format!("{:>width$}", mem[addr], width = CELL_WIDTH - 1), style));
+                line.push(span(" ", Style::Normal));
+            }
+
+            line
+        })
+        .collect()
+}
+
+/// Disassembles the instruction at `addr`, returning its text and size. Cells that aren't an
+/// instruction are shown as data.
+pub fn disassemble(mem: &[i32], addr: usize) -> (String, usize) {
+    let instr = mem[addr];
+
+    let isa::Decoded { opcode, modes } = isa::decode(instr);
+
+    let instruction = match isa::lookup(opcode) {
+        Some(instruction) if instr >= 0 && addr + instruction.size() <= mem.len() => instruction,
+        _ => return (format!("data {}", instr), 1),
+    };
+
+    let mut text = String::from(instruction.mnemonic);
+
+    for (i, mode) in modes.take(instruction.params.len()).enumerate() {
+        let value = mem[addr + 1 + i];
+
+        text.push_str(if i == 0 { " " } else { ", " });
+
+        match mode {
+            Ok(Mode::Position) => write!(text, "[{}]", value),
+            Ok(Mode::Immediate) => write!(text, "{}", value),
+            Ok(Mode::Relative) => write!(text, "[rb{:+}]", value),
+            Err(_) => return (format!("data {}", instr), 1),
+        }
+        .unwrap();
+    }
+
+    (text, instruction.size())
+}
+
+/// The disassembly from shortly before the program counter.
+///
+/// Since instructions vary in size, disassembling backwards is ambiguous. Instead, the
+/// disassembly starts from a little before the program counter and is resynchronized at it.
+fn disassembly(app: &App, height: usize) -> Vec<Line> {
+    let mem = app.computer.mem().to_vec();
+    let pc = app.computer.pc();
+
+    if pc >= mem.len() {
+        return vec![];
+    }
+
+    let mut before = vec![];
+    let mut addr = pc.saturating_sub(height * 2);
+    while addr < pc {
+        let (text, size) = disassemble(&mem, addr);
+        let size = size.min(pc - addr);
+        before.push((addr, text));
+        addr += size;
+    }
+
+    let keep = before.len().saturating_sub(height / 3);
+    let mut instructions: Vec<_> = before.drain(keep..).collect();
+
+    let mut addr = pc;
+    while addr < mem.len() && instructions.len() < height {
+        let (text, size) = disassemble(&mem, addr);
+        instructions.push((addr, text));
+        addr += size;
+    }
+
+    instructions
+        .into_iter()
+        .take(height)
+        .map(|(addr, text)| {
+            let style = if addr == pc { Style::Current } else { Style::Normal };
+            let marker = if addr == pc { '>' } else { ' ' };
+            vec![span(format!("{}{:>5} {}", marker, addr, text), style)]
+        })
+        .collect()
+}
+
+fn input(app: &App, height: usize) -> Vec<Line> {
+    let mut lines: Vec<Line> = vec![];
+
+    if let Some(entry) = &app.entry {
+        lines.push(vec![span(format!("> {}_", entry), Style::Current)]);
+    }
+
+    if app.input.is_empty() && app.entry.is_none() {
+        lines.push(vec![span("(empty)", Style::Normal)]);
+    }
+
+    lines.extend(wrap(app.input.iter(), SIDE_WIDTH));
+    lines.truncate(height);
+    lines
+}
+
+/// The most recent output that fits in the pane.
+fn output(app: &App, height: usize) -> Vec<Line> {
+    let lines = wrap(app.output.iter(), SIDE_WIDTH);
+    let skip = lines.len().saturating_sub(height);
+    lines.into_iter().skip(skip).collect()
+}
+
+/// Lays out values in lines of at most `width` characters.
+fn wrap<'a>(values: impl Iterator<Item = &'a i32>, width: usize) -> Vec<Line> {
+    let mut lines = vec![];
+    let mut line = String::new();
+
+    for value in values {
+        let value = value.to_string();
+
+        if !line.is_empty() && line.len() + 1 + value.len() > width {
+            lines.push(vec![span(std::mem::take(&mut line), Style::Normal)]);
+        }
+
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&value);
+    }
+
+    if !line.is_empty() {
+        lines.push(vec![span(line, Style::Normal)]);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::{App, Key};
+
+    use super::{disassemble, render, Line, Style};
+
+    fn text(line: &Line) -> String {
+        line.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    fn styled(lines: &[Line], style: Style) -> Vec<String> {
+        lines.iter().flatten().filter(|span| span.style == style).map(|span| span.text.trim().to_owned()).collect()
+    }
+
+    #[test]
+    fn disassembles() {
+        let mem = [1002, 4, 3, 4, 33, 204, -5, 1106, 0, 99, 3];
+        assert_eq!(disassemble(&mem, 0), (String::from("mul [4], 3, [4]"), 4));
+        assert_eq!(disassemble(&mem, 5), (String::from("out [rb-5]"), 2));
+        assert_eq!(disassemble(&mem, 7), (String::from("jz 0, 99"), 3));
+        assert_eq!(disassemble(&mem, 4), (String::from("data 33"), 1));
+
+        // Truncated by the end of memory.
+        assert_eq!(disassemble(&mem, 10), (String::from("data 3"), 1));
+    }
+
+    #[test]
+    fn highlights_current_and_written() {
+        let mut app = App::new(vec![1101, 2, 3, 9, 4, 9, 99, 0, 0, 0], vec![]);
+        app.handle_key(Key::Char('s'));
+
+        let lines = render(&app, 100, 20);
+        assert!(lines.iter().all(|line| super::width(line) <= 100));
+        assert_eq!(lines.len(), 20);
+
+        assert!(text(&lines[0]).starts_with("paused  pc 4"));
+        assert_eq!(styled(&lines, Style::Current), vec!["4", "9", ">    4 out [9]"]);
+        assert_eq!(styled(&lines, Style::Written), vec!["5"]);
+
+        let all: Vec<_> = lines.iter().map(text).collect();
+        assert!(all.iter().any(|line| line.contains("     0 add 2, 3, [9]")));
+        assert!(all.iter().any(|line| line.contains("     6 hlt")));
+    }
+
+    #[test]
+    fn shows_input_and_output() {
+        let mut app = App::new(vec![3, 7, 4, 7, 1105, 1, 0, 0], vec![12, 34, 56]);
+        for _ in 0..5 {
+            app.step();
+        }
+        app.handle_key(Key::Char('i'));
+        app.handle_key(Key::Char('5'));
+
+        let lines: Vec<_> = render(&app, 120, 24).iter().map(text).collect();
+        let input = lines.iter().position(|line| line.ends_with("Input")).unwrap();
+        let output = lines.iter().position(|line| line.ends_with("Output")).unwrap();
+
+        assert!(lines[input + 1].ends_with("> 5_"));
+        assert!(lines[input + 2].ends_with("56"));
+        assert!(lines[output + 1].ends_with("12 34"));
+    }
+
+    #[test]
+    fn fits_small_terminals() {
+        let app = App::new(vec![99; 1000], vec![]);
+        let lines = render(&app, 10, 3);
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|line| super::width(line) <= 10));
+    }
+}